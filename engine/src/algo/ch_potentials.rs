@@ -6,10 +6,14 @@ use crate::{
     },
     datastr::{node_order::*, timestamped_vector::TimestampedVector},
     report::*,
-    util::in_range_option::InRangeOption,
+    util::{great_circle_distance, in_range_option::InRangeOption},
 };
 
+pub mod alt;
+pub mod bidirectional_query;
+pub mod live_td;
 pub mod query;
+pub mod td_turns;
 
 pub trait Potential {
     fn init(&mut self, target: NodeId);
@@ -45,6 +49,26 @@ impl<'a> CCHPotential<'a> {
             num_pot_evals: 0,
         }
     }
+
+    /// Potential for searches on the reversed graph:
+    /// lower bounds of the distance from the node passed to `init` (the source) to each node.
+    pub fn new_reversed<Graph>(cch: &'a CCH, lower_bound: &Graph) -> Self
+    where
+        Graph: for<'b> LinkIterGraph<'b> + RandomLinkAccessGraph + Sync,
+    {
+        let customized = customize(cch, lower_bound);
+        let (forward_up_graph, backward_up_graph) = customized.into_ch_graphs();
+        let forward_elimination_tree = SteppedEliminationTree::new(forward_up_graph, cch.elimination_tree());
+
+        Self {
+            cch,
+            stack: Vec::new(),
+            forward_cch_graph: backward_up_graph,
+            backward_elimination_tree: forward_elimination_tree,
+            potentials: TimestampedVector::new(cch.num_nodes(), InRangeOption::new(None)),
+            num_pot_evals: 0,
+        }
+    }
 }
 
 impl<'a> Potential for CCHPotential<'a> {
@@ -237,6 +261,95 @@ impl Potential for BaselinePotential {
     }
 }
 
+/// Geometric A* potential: the great circle distance to the target divided by the maximum speed of all arcs.
+/// Requires no preprocessing apart from determining the maximum speed, which is derived from the metric.
+/// The potential stays feasible for any metric as long as the speed bound is updated with `update_metric` after changes.
+/// For time-dependent graphs, derive the bound from the lower bounds of the travel time functions.
+#[derive(Debug)]
+pub struct GeoPotential {
+    // coordinates in radians
+    lat: Vec<f32>,
+    lng: Vec<f32>,
+    // meters per unit of weight
+    max_speed: f64,
+    target: NodeId,
+    num_pot_evals: usize,
+}
+
+impl GeoPotential {
+    pub fn new<Graph>(graph: &Graph, lat: &[f32], lng: &[f32]) -> Self
+    where
+        Graph: for<'b> LinkIterGraph<'b>,
+    {
+        assert_eq!(lat.len(), graph.num_nodes());
+        assert_eq!(lng.len(), graph.num_nodes());
+
+        let mut potential = Self {
+            lat: lat.to_vec(),
+            lng: lng.to_vec(),
+            max_speed: 0.0,
+            target: 0,
+            num_pot_evals: 0,
+        };
+        potential.update_metric(graph);
+        potential
+    }
+
+    /// Recompute the speed bound for a new metric.
+    pub fn update_metric<Graph>(&mut self, graph: &Graph)
+    where
+        Graph: for<'b> LinkIterGraph<'b>,
+    {
+        let mut max_speed: f64 = 0.0;
+        for node in 0..graph.num_nodes() as NodeId {
+            for link in graph.link_iter(node) {
+                let distance = self.distance(node, link.node);
+                if link.weight == 0 {
+                    if distance > 0.0 {
+                        max_speed = f64::INFINITY;
+                    }
+                } else {
+                    max_speed = max_speed.max(distance / f64::from(link.weight));
+                }
+            }
+        }
+        // a little slack for rounding errors, so the triangle inequality still holds
+        self.max_speed = max_speed * (1.0 + 1e-9);
+    }
+
+    /// The speed bound in meters per unit of weight.
+    pub fn max_speed(&self) -> f64 {
+        self.max_speed
+    }
+
+    fn distance(&self, from: NodeId, to: NodeId) -> f64 {
+        great_circle_distance(
+            (self.lat[from as usize], self.lng[from as usize]),
+            (self.lat[to as usize], self.lng[to as usize]),
+        )
+    }
+}
+
+impl Potential for GeoPotential {
+    fn init(&mut self, target: NodeId) {
+        self.target = target;
+        self.num_pot_evals = 0;
+    }
+
+    fn potential(&mut self, node: NodeId) -> Option<Weight> {
+        self.num_pot_evals += 1;
+        if self.max_speed == 0.0 || self.max_speed.is_infinite() {
+            return Some(0);
+        }
+        // rounding down keeps the potential consistent
+        Some(std::cmp::min((self.distance(node, self.target) / self.max_speed) as Weight, INFINITY - 1))
+    }
+
+    fn num_pot_evals(&self) -> usize {
+        self.num_pot_evals
+    }
+}
+
 #[derive(Debug)]
 pub struct RecyclingPotential<Potential> {
     potential: Potential,
@@ -0,0 +1,307 @@
+//! ALT potentials - A*, landmarks and the triangle inequality.
+//!
+//! For a few landmark nodes, distances from and to all other nodes are precomputed.
+//! The triangle inequality then yields lower bounds on the distance between any two nodes.
+//! Landmarks can be selected by three strategies:
+//! `Farthest` repeatedly picks the node farthest away from all landmarks so far,
+//! `Planar` divides the map into sectors around the center and picks the node farthest from the center in each sector,
+//! `Avoid` picks landmarks in regions of the shortest path tree of a random root where the current landmarks give bad bounds.
+//!
+//! For each query, only a few active landmarks are used.
+//! They are selected when the first potential after `init` is evaluated (usually the one of the source),
+//! by picking the landmarks with the best bounds for that node.
+
+use super::*;
+use crate::algo::dijkstra::query::dijkstra::Server as DijkServer;
+use crate::io::*;
+use rand::prelude::*;
+
+/// Landmark selection strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandmarkSelection {
+    Avoid,
+    Farthest,
+    Planar,
+}
+
+/// Selected landmarks and the distances from and to all nodes.
+#[derive(Debug, Clone)]
+pub struct Landmarks {
+    num_nodes: usize,
+    landmarks: Vec<NodeId>,
+    // distances of landmark `l` are in `l * n..(l + 1) * n`
+    from_landmark: Vec<Weight>,
+    to_landmark: Vec<Weight>,
+}
+
+type DistanceServer = DijkServer<DefaultOps, OwnedGraph, ZeroPotential>;
+
+impl Landmarks {
+    /// Select up to `num_landmarks` landmarks and precompute their distances with Dijkstra.
+    /// Coordinates are only required for `LandmarkSelection::Planar` and may be empty otherwise.
+    pub fn new<G>(graph: &G, num_landmarks: usize, selection: LandmarkSelection, lat: &[f32], lng: &[f32], rng: &mut impl Rng) -> Self
+    where
+        G: for<'b> LinkIterGraph<'b>,
+    {
+        let n = graph.num_nodes();
+        assert!(n > 0);
+        let mut forward = DistanceServer::new(OwnedGraph::permutated(graph, &NodeOrder::identity(n)));
+        let mut backward = DistanceServer::new(OwnedGraph::reversed(graph));
+
+        let mut landmarks = Landmarks {
+            num_nodes: n,
+            landmarks: Vec::with_capacity(num_landmarks),
+            from_landmark: Vec::with_capacity(num_landmarks * n),
+            to_landmark: Vec::with_capacity(num_landmarks * n),
+        };
+
+        match selection {
+            LandmarkSelection::Farthest => {
+                let start = rng.gen_range(0, n as NodeId);
+                let res = forward.one_to_all(start);
+                let mut min_dist: Vec<Weight> = (0..n as NodeId).map(|node| res.distance(node)).collect();
+
+                while landmarks.num_landmarks() < num_landmarks {
+                    let next = (0..n as NodeId)
+                        .filter(|&node| min_dist[node as usize] < INFINITY)
+                        .max_by_key(|&node| min_dist[node as usize]);
+                    let next = match next {
+                        Some(next) if min_dist[next as usize] > 0 => next,
+                        _ => break,
+                    };
+
+                    landmarks.push(next, &mut forward, &mut backward);
+                    let l = landmarks.num_landmarks() - 1;
+                    for (min_dist, &dist) in min_dist.iter_mut().zip(&landmarks.from_landmark[l * n..]) {
+                        *min_dist = if l == 0 { dist } else { std::cmp::min(*min_dist, dist) };
+                    }
+                }
+            }
+            LandmarkSelection::Planar => {
+                assert_eq!(lat.len(), n);
+                assert_eq!(lng.len(), n);
+                let center_lat = lat.iter().map(|&lat| f64::from(lat)).sum::<f64>() / n as f64;
+                let center_lng = lng.iter().map(|&lng| f64::from(lng)).sum::<f64>() / n as f64;
+                let sq_dist = |node: usize| (f64::from(lat[node]) - center_lat).powi(2) + (f64::from(lng[node]) - center_lng).powi(2);
+                let center = (0..n).min_by(|&a, &b| sq_dist(a).partial_cmp(&sq_dist(b)).unwrap()).unwrap();
+
+                let res = forward.one_to_all(center as NodeId);
+                let mut farthest_in_sector: Vec<Option<(NodeId, Weight)>> = vec![None; num_landmarks];
+                for node in 0..n {
+                    let dist = res.distance(node as NodeId);
+                    if node == center || dist >= INFINITY {
+                        continue;
+                    }
+                    let angle = (f64::from(lat[node]) - f64::from(lat[center])).atan2(f64::from(lng[node]) - f64::from(lng[center]));
+                    let sector = ((angle + std::f64::consts::PI) / (2.0 * std::f64::consts::PI) * num_landmarks as f64) as usize;
+                    let sector = std::cmp::min(sector, num_landmarks - 1);
+                    if farthest_in_sector[sector].map(|(_, farthest)| dist > farthest).unwrap_or(true) {
+                        farthest_in_sector[sector] = Some((node as NodeId, dist));
+                    }
+                }
+
+                for (landmark, _) in farthest_in_sector.into_iter().flatten() {
+                    landmarks.push(landmark, &mut forward, &mut backward);
+                }
+            }
+            LandmarkSelection::Avoid => {
+                let mut tree = StandardDijkstra::new(OwnedGraph::permutated(graph, &NodeOrder::identity(n)));
+                let mut children: Vec<Vec<NodeId>> = vec![Vec::new(); n];
+                let mut size = vec![0u64; n];
+                let mut is_landmark = vec![false; n];
+                let mut has_landmark = vec![false; n];
+
+                let mut attempts = 0;
+                while landmarks.num_landmarks() < num_landmarks && attempts < 10 * num_landmarks {
+                    attempts += 1;
+                    let root = rng.gen_range(0, n as NodeId);
+                    tree.initialize_query(Query { from: root, to: n as NodeId });
+                    let mut settled = Vec::new();
+                    for node in tree.by_ref() {
+                        children[node as usize].clear();
+                        settled.push(node);
+                    }
+                    for &node in &settled[1..] {
+                        children[tree.predecessor(node) as usize].push(node);
+                    }
+
+                    // The weight of a node is how much the current landmarks underestimate its distance from the root.
+                    // The size of a node is the sum of the weights in its subtree, or zero if the subtree contains a landmark.
+                    for &node in settled.iter().rev() {
+                        let node = node as usize;
+                        has_landmark[node] = is_landmark[node] || children[node].iter().any(|&child| has_landmark[child as usize]);
+                        size[node] = if has_landmark[node] {
+                            0
+                        } else {
+                            u64::from(
+                                tree.tentative_distance(node as NodeId)
+                                    .saturating_sub(landmarks.lower_bound(root, node as NodeId)),
+                            ) + children[node].iter().map(|&child| size[child as usize]).sum::<u64>()
+                        };
+                    }
+
+                    // descend along the largest subtrees to a leaf
+                    let mut node = root;
+                    while let Some(&child) = children[node as usize].iter().max_by_key(|&&child| size[child as usize]) {
+                        if size[child as usize] == 0 {
+                            break;
+                        }
+                        node = child;
+                    }
+
+                    if !is_landmark[node as usize] && (node != root || children[node as usize].is_empty()) {
+                        is_landmark[node as usize] = true;
+                        landmarks.push(node, &mut forward, &mut backward);
+                    }
+                }
+            }
+        }
+
+        landmarks
+    }
+
+    fn push(&mut self, landmark: NodeId, forward: &mut DistanceServer, backward: &mut DistanceServer) {
+        let n = self.num_nodes as NodeId;
+        self.landmarks.push(landmark);
+        let res = forward.one_to_all(landmark);
+        self.from_landmark.extend((0..n).map(|node| res.distance(node)));
+        let res = backward.one_to_all(landmark);
+        self.to_landmark.extend((0..n).map(|node| res.distance(node)));
+    }
+
+    // Lower bound from a single landmark, `None` if `to` provably cannot be reached from `from`.
+    fn bound(&self, landmark: usize, from: NodeId, to: NodeId) -> Option<Weight> {
+        let offset = landmark * self.num_nodes;
+        let (landmark_to_from, landmark_to_to) = (self.from_landmark[offset + from as usize], self.from_landmark[offset + to as usize]);
+        let (from_to_landmark, to_to_landmark) = (self.to_landmark[offset + from as usize], self.to_landmark[offset + to as usize]);
+
+        // otherwise, there would be paths landmark -> from -> to or from -> to -> landmark
+        if (landmark_to_from < INFINITY && landmark_to_to >= INFINITY) || (to_to_landmark < INFINITY && from_to_landmark >= INFINITY) {
+            return None;
+        }
+
+        let mut bound = 0;
+        if landmark_to_from < INFINITY && landmark_to_to < INFINITY {
+            bound = landmark_to_to.saturating_sub(landmark_to_from);
+        }
+        if from_to_landmark < INFINITY && to_to_landmark < INFINITY {
+            bound = std::cmp::max(bound, from_to_landmark.saturating_sub(to_to_landmark));
+        }
+        Some(bound)
+    }
+
+    /// Lower bound on the distance from `from` to `to` using all landmarks.
+    /// Returns `INFINITY` if `to` cannot be reached from `from`.
+    pub fn lower_bound(&self, from: NodeId, to: NodeId) -> Weight {
+        (0..self.num_landmarks())
+            .map(|landmark| self.bound(landmark, from, to).unwrap_or(INFINITY))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn num_landmarks(&self) -> usize {
+        self.landmarks.len()
+    }
+
+    /// Number of nodes of the graph the distances were computed for
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    /// The selected landmark nodes
+    pub fn landmarks(&self) -> &[NodeId] {
+        &self.landmarks
+    }
+}
+
+impl Deconstruct for Landmarks {
+    fn store_each(&self, store: &dyn Fn(&str, &dyn Store) -> std::io::Result<()>) -> std::io::Result<()> {
+        store("landmarks", &self.landmarks)?;
+        store("landmark_from_distances", &self.from_landmark)?;
+        store("landmark_to_distances", &self.to_landmark)?;
+        Ok(())
+    }
+}
+
+impl Reconstruct for Landmarks {
+    fn reconstruct_with(loader: Loader) -> std::io::Result<Self> {
+        let landmarks: Vec<NodeId> = loader.load("landmarks")?;
+        let from_landmark: Vec<Weight> = loader.load("landmark_from_distances")?;
+        let to_landmark: Vec<Weight> = loader.load("landmark_to_distances")?;
+        let num_nodes = if landmarks.is_empty() { 0 } else { from_landmark.len() / landmarks.len() };
+        if landmarks.is_empty() || from_landmark.len() != to_landmark.len() || num_nodes * landmarks.len() != from_landmark.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "inconsistent landmark data"));
+        }
+
+        Ok(Landmarks {
+            num_nodes,
+            landmarks,
+            from_landmark,
+            to_landmark,
+        })
+    }
+}
+
+/// A* potential from the lower bounds of a few active landmarks.
+#[derive(Debug)]
+pub struct ALTPotential {
+    landmarks: Landmarks,
+    num_active: usize,
+    active: Vec<usize>,
+    target: NodeId,
+    num_pot_evals: usize,
+}
+
+impl ALTPotential {
+    /// Potential using at most `num_active` landmarks per query.
+    pub fn new(landmarks: Landmarks, num_active: usize) -> Self {
+        assert!(num_active > 0);
+        Self {
+            landmarks,
+            num_active,
+            active: Vec::with_capacity(num_active),
+            target: 0,
+            num_pot_evals: 0,
+        }
+    }
+
+    pub fn landmarks(&self) -> &Landmarks {
+        &self.landmarks
+    }
+
+    fn select_active(&mut self, source: NodeId) {
+        let landmarks = &self.landmarks;
+        let target = self.target;
+        let mut candidates: Vec<(Weight, usize)> = (0..landmarks.num_landmarks())
+            .map(|landmark| (landmarks.bound(landmark, source, target).unwrap_or(INFINITY), landmark))
+            .collect();
+        candidates.sort_unstable_by(|a, b| b.cmp(a));
+        self.active.extend(candidates.into_iter().take(self.num_active).map(|(_, landmark)| landmark));
+    }
+}
+
+impl Potential for ALTPotential {
+    fn init(&mut self, target: NodeId) {
+        self.target = target;
+        self.active.clear();
+        self.num_pot_evals = 0;
+    }
+
+    fn potential(&mut self, node: NodeId) -> Option<Weight> {
+        // the active landmarks must not change during a query, otherwise the potential might become inconsistent
+        if self.active.is_empty() {
+            self.select_active(node);
+        }
+        self.num_pot_evals += 1;
+
+        let mut potential = 0;
+        for &landmark in &self.active {
+            potential = std::cmp::max(potential, self.landmarks.bound(landmark, node, self.target)?);
+        }
+        Some(potential)
+    }
+
+    fn num_pot_evals(&self) -> usize {
+        self.num_pot_evals
+    }
+}
@@ -0,0 +1,152 @@
+//! Bidirectional A* with consistent average potentials.
+//!
+//! The forward search needs lower bounds of the distance to the target, the backward search lower bounds of the distance from the source.
+//! For CH potentials these are a `CCHPotential` and one built with `CCHPotential::new_reversed`
+//! or two `CHPotential`s where the forward and backward graphs are swapped.
+//! Both searches use the average of both potentials, which keeps them consistent with each other.
+//! Both potentials have to be consistent, for example exact distances for a lower bound metric.
+
+use super::*;
+
+// Added to both average potentials, so they can't get negative.
+// Lower bounds are always smaller than `INFINITY`, no matter how much the bounds of both directions differ on directed graphs.
+// Clamping the averages instead would break the stopping criterion.
+const POTENTIAL_OFFSET: Weight = INFINITY;
+
+pub struct Server<G: for<'a> LinkIterGraph<'a>, P, Q> {
+    forward_dijkstra: StandardDijkstra<G>,
+    backward_dijkstra: StandardDijkstra<OwnedGraph>,
+    forward_potential: P,
+    backward_potential: Q,
+    tentative_distance: Weight,
+    meeting_node: NodeId,
+}
+
+impl<G: for<'a> LinkIterGraph<'a>, P: Potential, Q: Potential> Server<G, P, Q> {
+    /// `forward_potential` has to provide lower bounds to the target, `backward_potential` lower bounds from the source.
+    pub fn new(graph: G, forward_potential: P, backward_potential: Q) -> Self {
+        let reversed = OwnedGraph::reversed(&graph);
+
+        Server {
+            forward_dijkstra: StandardDijkstra::new(graph),
+            backward_dijkstra: StandardDijkstra::new(reversed),
+            forward_potential,
+            backward_potential,
+            tentative_distance: INFINITY,
+            meeting_node: 0,
+        }
+    }
+
+    fn distance(&mut self, from: NodeId, to: NodeId) -> Option<Weight> {
+        report!("algo", "Bidirectional CH Potentials Query");
+
+        self.tentative_distance = INFINITY;
+        self.forward_potential.init(to);
+        self.backward_potential.init(from);
+
+        // Target not reachable according to the lower bounds
+        self.forward_potential.potential(from)?;
+
+        self.forward_dijkstra.initialize_query(Query { from, to });
+        self.backward_dijkstra.initialize_query(Query { from: to, to: from });
+
+        let forward_dijkstra = &mut self.forward_dijkstra;
+        let backward_dijkstra = &mut self.backward_dijkstra;
+        let forward_potential = &mut self.forward_potential;
+        let backward_potential = &mut self.backward_potential;
+
+        // Nodes without a lower bound in either direction can't be on any path between source and target.
+        let mut potentials = |node: NodeId| forward_potential.potential(node).zip(backward_potential.potential(node));
+
+        let mut num_queue_pops = 0;
+
+        while let (Some(&State { key: forward_key, .. }), Some(&State { key: backward_key, .. })) =
+            (forward_dijkstra.queue().peek(), backward_dijkstra.queue().peek())
+        {
+            // Keys of both directions overestimate the actual path lengths by at most `POTENTIAL_OFFSET`.
+            // Keys can be almost twice as large as `INFINITY`, so the sums need 64 bits.
+            if u64::from(forward_key) + u64::from(backward_key) >= u64::from(self.tentative_distance) + u64::from(POTENTIAL_OFFSET) {
+                break;
+            }
+            num_queue_pops += 1;
+
+            // Rounding the averages down keeps them consistent.
+            if forward_key <= backward_key {
+                let node = forward_dijkstra
+                    .next_step_with_potential(|node| potentials(node).map(|(to_target, from_source)| (to_target + POTENTIAL_OFFSET - from_source) / 2))
+                    .unwrap();
+                let distance = *forward_dijkstra.tentative_distance(node);
+
+                if distance + backward_dijkstra.tentative_distance(node) < self.tentative_distance {
+                    self.tentative_distance = distance + backward_dijkstra.tentative_distance(node);
+                    self.meeting_node = node;
+                }
+            } else {
+                let node = backward_dijkstra
+                    .next_step_with_potential(|node| potentials(node).map(|(to_target, from_source)| (from_source + POTENTIAL_OFFSET - to_target) / 2))
+                    .unwrap();
+                let distance = *backward_dijkstra.tentative_distance(node);
+
+                if distance + forward_dijkstra.tentative_distance(node) < self.tentative_distance {
+                    self.tentative_distance = distance + forward_dijkstra.tentative_distance(node);
+                    self.meeting_node = node;
+                }
+            }
+        }
+
+        report!("num_queue_pops", num_queue_pops);
+        report!("num_queue_pushs", forward_dijkstra.num_queue_pushs() + backward_dijkstra.num_queue_pushs());
+        report!("num_pot_evals", forward_potential.num_pot_evals() + backward_potential.num_pot_evals());
+        report!("num_relaxed_arcs", forward_dijkstra.num_relaxed_arcs() + backward_dijkstra.num_relaxed_arcs());
+
+        match self.tentative_distance {
+            INFINITY => None,
+            dist => Some(dist),
+        }
+    }
+
+    fn path(&self, query: Query) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        path.push(self.meeting_node);
+
+        while *path.last().unwrap() != query.from {
+            let next = self.forward_dijkstra.predecessor(*path.last().unwrap());
+            path.push(next);
+        }
+
+        path.reverse();
+
+        while *path.last().unwrap() != query.to {
+            let next = self.backward_dijkstra.predecessor(*path.last().unwrap());
+            path.push(next);
+        }
+
+        path
+    }
+}
+
+pub struct PathServerWrapper<'s, G: for<'a> LinkIterGraph<'a>, P, Q>(&'s mut Server<G, P, Q>, Query);
+
+impl<'s, G: for<'a> LinkIterGraph<'a>, P: Potential, Q: Potential> PathServer for PathServerWrapper<'s, G, P, Q> {
+    type NodeInfo = NodeId;
+
+    fn path(&mut self) -> Vec<Self::NodeInfo> {
+        Server::path(self.0, self.1)
+    }
+}
+
+impl<'s, G: for<'a> LinkIterGraph<'a>, P: Potential, Q: Potential> PathServerWrapper<'s, G, P, Q> {
+    /// The lower bound of the forward potential from `node` to the target.
+    pub fn lower_bound(&mut self, node: NodeId) -> Option<Weight> {
+        self.0.forward_potential.potential(node)
+    }
+}
+
+impl<'s, G: 's + for<'a> LinkIterGraph<'a>, P: 's + Potential, Q: 's + Potential> QueryServer<'s> for Server<G, P, Q> {
+    type P = PathServerWrapper<'s, G, P, Q>;
+
+    fn query(&'s mut self, query: Query) -> Option<QueryResult<'s, Self::P, Weight>> {
+        self.distance(query.from, query.to)
+            .map(move |distance| QueryResult::new(distance, PathServerWrapper(self, query)))
+    }
+}
@@ -0,0 +1,127 @@
+//! Departure time queries on predicted travel time profiles combined with live traffic.
+//!
+//! The query is a time-dependent A* on a `LiveTDGraph` guided by a `CCHPotential`.
+//! The potential is customized with the lower bounds of the predicted profiles, or the live travel time where that is even lower.
+//! Live updates only invalidate the potential when they undercut these bounds, the recustomization happens lazily on the next query.
+
+use super::*;
+use crate::algo::dijkstra::query::td_dijkstra::LiveTDDijkstraOps;
+use crate::datastr::graph::time_dependent::*;
+
+pub struct LiveTDServer<'a> {
+    cch: &'a CCH,
+    dijkstra: GenericDijkstra<LiveTDDijkstraOps, LiveTDGraph>,
+    predicted_lower_bound: Vec<Weight>,
+    // the metric the current potential was customized with
+    potential_lower_bound: Vec<Weight>,
+    potential: Option<CCHPotential<'a>>,
+}
+
+impl<'a> LiveTDServer<'a> {
+    /// Create a server without any live traffic.
+    /// The CCH has to be built for the topology of `predicted`.
+    pub fn new(cch: &'a CCH, predicted: TDGraph) -> Self {
+        assert_eq!(cch.num_nodes(), predicted.num_nodes());
+        let predicted_lower_bound: Vec<Weight> = (0..predicted.num_arcs() as EdgeId)
+            .map(|edge_id| predicted.travel_time_function(edge_id).lower_bound())
+            .collect();
+        let live = vec![InRangeOption::new(None); predicted.num_arcs()];
+
+        Self {
+            cch,
+            dijkstra: GenericDijkstra::new(LiveTDGraph::new(predicted, 0, live)),
+            potential_lower_bound: predicted_lower_bound.clone(),
+            predicted_lower_bound,
+            potential: None,
+        }
+    }
+
+    /// Replace the live travel times.
+    /// They will be used for departures up to `soon` and blended into the predicted profiles afterwards.
+    pub fn update_live(&mut self, live: Vec<InRangeOption<Weight>>, soon: Timestamp) {
+        let potential_still_valid = live
+            .iter()
+            .zip(self.potential_lower_bound.iter())
+            .all(|(live, &lower_bound)| live.value().map(|live| live >= lower_bound).unwrap_or(true));
+
+        if !potential_still_valid {
+            self.potential = None;
+            self.potential_lower_bound = self
+                .predicted_lower_bound
+                .iter()
+                .zip(live.iter())
+                .map(|(&lower_bound, live)| live.value().map(|live| std::cmp::min(live, lower_bound)).unwrap_or(lower_bound))
+                .collect();
+        }
+
+        self.dijkstra.graph_mut().set_live(soon, live);
+    }
+
+    /// The current graph including the live travel times
+    pub fn graph(&self) -> &LiveTDGraph {
+        self.dijkstra.graph()
+    }
+
+    fn distance(&mut self, query: TDQuery<Timestamp>) -> Option<Weight> {
+        report!("algo", "Live TD CH Potentials Query");
+
+        if self.potential.is_none() {
+            let predicted = self.dijkstra.graph().predicted();
+            let lower_bound = FirstOutGraph::new(predicted.first_out(), predicted.head(), &self.potential_lower_bound[..]);
+            self.potential = Some(report_time("Live TD CH Potentials customization", || CCHPotential::new(self.cch, &lower_bound)));
+        }
+
+        let dijkstra = &mut self.dijkstra;
+        let potential = self.potential.as_mut().unwrap();
+        potential.init(query.to);
+        dijkstra.initialize_query(query);
+
+        let mut num_queue_pops = 0;
+        let mut result = None;
+        while let Some(node) = dijkstra.next_step_with_potential(|node| potential.potential(node)) {
+            num_queue_pops += 1;
+            if node == query.to {
+                result = Some(*dijkstra.tentative_distance(node) - query.departure);
+                break;
+            }
+        }
+
+        report!("num_queue_pops", num_queue_pops);
+        report!("num_queue_pushs", dijkstra.num_queue_pushs());
+        report!("num_pot_evals", potential.num_pot_evals());
+        report!("num_relaxed_arcs", dijkstra.num_relaxed_arcs());
+
+        result
+    }
+
+    fn path(&self, query: TDQuery<Timestamp>) -> Vec<NodeId> {
+        let mut path = vec![query.to];
+
+        while *path.last().unwrap() != query.from {
+            let next = self.dijkstra.predecessor(*path.last().unwrap());
+            path.push(next);
+        }
+
+        path.reverse();
+        path
+    }
+}
+
+pub struct PathServerWrapper<'s, 'a>(&'s mut LiveTDServer<'a>, TDQuery<Timestamp>);
+
+impl<'s, 'a> PathServer for PathServerWrapper<'s, 'a> {
+    type NodeInfo = NodeId;
+
+    fn path(&mut self) -> Vec<Self::NodeInfo> {
+        LiveTDServer::path(self.0, self.1)
+    }
+}
+
+impl<'s, 'a: 's> TDQueryServer<'s, Timestamp, Weight> for LiveTDServer<'a> {
+    type P = PathServerWrapper<'s, 'a>;
+
+    fn query(&'s mut self, query: TDQuery<Timestamp>) -> Option<QueryResult<'s, Self::P, Weight>> {
+        self.distance(query)
+            .map(move |distance| QueryResult::new(distance, PathServerWrapper(self, query)))
+    }
+}
@@ -1,6 +1,6 @@
 use super::*;
 
-use crate::datastr::rank_select_map::FastClearBitVec;
+use crate::datastr::{index_heap::*, rank_select_map::FastClearBitVec};
 use crate::{
     algo::{dijkstra::gen_topo_dijkstra::*, topocore::*},
     datastr::graph::time_dependent::*,
@@ -8,8 +8,8 @@ use crate::{
 #[cfg(feature = "chpot-visualize")]
 use std::rc::Rc;
 
-pub struct Server<P, Ops: DijkstraOps<Graph>, Graph> {
-    forward_dijkstra: GenTopoDijkstra<VirtualTopocoreOps<Ops>, VirtualTopocoreGraph<Graph>>,
+pub struct Server<P, Ops: DijkstraOps<Graph>, Graph, PQ = IndexdMinHeap<State<Timestamp>>> {
+    forward_dijkstra: GenTopoDijkstra<VirtualTopocoreOps<Ops>, VirtualTopocoreGraph<Graph>, PQ>,
     #[cfg(not(feature = "chpot-no-bcc"))]
     into_comp_graph: VirtualTopocoreGraph<Graph>,
     #[cfg(not(feature = "chpot-no-bcc"))]
@@ -38,6 +38,36 @@ where
         #[cfg(feature = "chpot-visualize")] lat: Rc<[f32]>,
         #[cfg(feature = "chpot-visualize")] lng: Rc<[f32]>,
     ) -> Self
+    where
+        G: for<'a> LinkIterable<'a, NodeId>,
+        Graph: BuildPermutated<G>,
+    {
+        Self::new_with_queue(
+            graph,
+            potential,
+            ops,
+            #[cfg(feature = "chpot-visualize")]
+            lat,
+            #[cfg(feature = "chpot-visualize")]
+            lng,
+        )
+    }
+}
+
+impl<P: Potential, Ops: DijkstraOps<Graph, Label = Timestamp>, Graph, PQ> Server<P, Ops, Graph, PQ>
+where
+    Graph: for<'a> LinkIterable<'a, NodeId> + for<'a> LinkIterable<'a, Ops::Arc>,
+    PQ: PriorityQueue<State<Timestamp>>,
+{
+    /// Create a server using the priority queue type `PQ`.
+    /// The monotone queues from `datastr::bucket_queue` require a consistent potential.
+    pub fn new_with_queue<G>(
+        graph: &G,
+        potential: P,
+        ops: Ops,
+        #[cfg(feature = "chpot-visualize")] lat: Rc<[f32]>,
+        #[cfg(feature = "chpot-visualize")] lng: Rc<[f32]>,
+    ) -> Self
     where
         G: for<'a> LinkIterable<'a, NodeId>,
         Graph: BuildPermutated<G>,
@@ -269,18 +299,23 @@ where
 
         path.reverse();
 
+        for node in &mut path {
+            *node = self.virtual_topocore.order.node(*node);
+        }
+
         path
     }
 }
 
-pub struct PathServerWrapper<'s, P, O: DijkstraOps<G>, G, Q>(&'s mut Server<P, O, G>, Q);
+pub struct PathServerWrapper<'s, P, O: DijkstraOps<G>, G, Q, PQ = IndexdMinHeap<State<Timestamp>>>(&'s mut Server<P, O, G, PQ>, Q);
 
-impl<'s, P, O, G, Q> PathServer for PathServerWrapper<'s, P, O, G, Q>
+impl<'s, P, O, G, Q, PQ> PathServer for PathServerWrapper<'s, P, O, G, Q, PQ>
 where
     P: Potential,
     O: DijkstraOps<G, Label = Timestamp>,
     G: for<'a> LinkIterable<'a, NodeId> + for<'a> LinkIterable<'a, O::Arc>,
     Q: GenQuery<Timestamp> + Copy,
+    PQ: PriorityQueue<State<Timestamp>>,
 {
     type NodeInfo = NodeId;
 
@@ -289,12 +324,13 @@ where
     }
 }
 
-impl<'s, P, O, G, Q> PathServerWrapper<'s, P, O, G, Q>
+impl<'s, P, O, G, Q, PQ> PathServerWrapper<'s, P, O, G, Q, PQ>
 where
     P: Potential,
     O: DijkstraOps<G, Label = Timestamp>,
     G: for<'a> LinkIterable<'a, NodeId> + for<'a> LinkIterable<'a, O::Arc>,
     Q: GenQuery<Timestamp> + Copy,
+    PQ: PriorityQueue<State<Timestamp>>,
 {
     /// Print path with debug info as js to stdout.
     pub fn debug_path(&mut self, lat: &[f32], lng: &[f32]) {
@@ -303,7 +339,7 @@ where
                 "var marker = L.marker([{}, {}], {{ icon: blackIcon }}).addTo(map);",
                 lat[node as usize], lng[node as usize]
             );
-            let dist = *self.0.forward_dijkstra.tentative_distance(node);
+            let dist = *self.0.forward_dijkstra.tentative_distance(self.0.virtual_topocore.order.rank(node));
             let pot = self.lower_bound(node).unwrap_or(INFINITY);
             println!(
                 "marker.bindPopup(\"id: {}<br>distance: {}<br>lower_bound: {}<br>sum: {}\");",
@@ -320,29 +356,31 @@ where
     }
 }
 
-impl<'s, P: 's, O: 's, G: 's> TDQueryServer<'s, Timestamp, Weight> for Server<P, O, G>
+impl<'s, P: 's, O: 's, G: 's, PQ: 's> TDQueryServer<'s, Timestamp, Weight> for Server<P, O, G, PQ>
 where
     P: Potential,
     O: DijkstraOps<G, Label = Timestamp>,
     G: for<'a> LinkIterable<'a, NodeId> + for<'a> LinkIterable<'a, O::Arc>,
+    PQ: PriorityQueue<State<Timestamp>>,
 {
-    type P = PathServerWrapper<'s, P, O, G, TDQuery<Timestamp>>;
+    type P = PathServerWrapper<'s, P, O, G, TDQuery<Timestamp>, PQ>;
 
-    fn query(&'s mut self, query: TDQuery<Timestamp>) -> Option<QueryResult<Self::P, Weight>> {
+    fn query(&'s mut self, query: TDQuery<Timestamp>) -> Option<QueryResult<'s, Self::P, Weight>> {
         self.distance(query)
             .map(move |distance| QueryResult::new(distance, PathServerWrapper(self, query)))
     }
 }
 
-impl<'s, P: 's, O: 's, G: 's> QueryServer<'s> for Server<P, O, G>
+impl<'s, P: 's, O: 's, G: 's, PQ: 's> QueryServer<'s> for Server<P, O, G, PQ>
 where
     P: Potential,
     O: DijkstraOps<G, Label = Timestamp>,
     G: for<'a> LinkIterable<'a, NodeId> + for<'a> LinkIterable<'a, O::Arc>,
+    PQ: PriorityQueue<State<Timestamp>>,
 {
-    type P = PathServerWrapper<'s, P, O, G, Query>;
+    type P = PathServerWrapper<'s, P, O, G, Query, PQ>;
 
-    fn query(&'s mut self, query: Query) -> Option<QueryResult<Self::P, Weight>> {
+    fn query(&'s mut self, query: Query) -> Option<QueryResult<'s, Self::P, Weight>> {
         self.distance(query)
             .map(move |distance| QueryResult::new(distance, PathServerWrapper(self, query)))
     }
@@ -0,0 +1,149 @@
+//! Time-dependent CH potentials queries with turn costs and turn restrictions.
+//!
+//! Turns are modeled by searching on the line graph of a `LiveTDGraph`: every arc becomes a node and every allowed turn an arc.
+//! The travel time of a turn arc is the travel time of the first arc plus the turn cost.
+//! The potential is built for the original graph and evaluated at the tail of each arc.
+//! Turn costs can only make paths longer, so the potential stays a valid lower bound on the line graph.
+
+use super::*;
+use crate::algo::dijkstra::query::td_dijkstra::LiveTDDijkstraOps;
+use crate::datastr::graph::time_dependent::*;
+#[cfg(feature = "chpot-visualize")]
+use std::rc::Rc;
+
+/// Turn restrictions as they are given in the `forbidden_turn_from_arc` and `forbidden_turn_to_arc` files.
+/// U-turns are forbidden, too, all other turns are free.
+#[derive(Debug, Clone)]
+pub struct ForbiddenTurns {
+    tail: Vec<NodeId>,
+    head: Vec<NodeId>,
+    forbidden: Vec<(EdgeId, EdgeId)>,
+}
+
+impl ForbiddenTurns {
+    pub fn new(first_out: &[EdgeId], head: &[NodeId], forbidden_turn_from_arc: &[EdgeId], forbidden_turn_to_arc: &[EdgeId]) -> Self {
+        assert_eq!(forbidden_turn_from_arc.len(), forbidden_turn_to_arc.len());
+        let mut tail = Vec::with_capacity(head.len());
+        for (node, range) in first_out.windows(2).enumerate() {
+            for _ in range[0]..range[1] {
+                tail.push(node as NodeId);
+            }
+        }
+
+        let mut forbidden: Vec<_> = forbidden_turn_from_arc.iter().copied().zip(forbidden_turn_to_arc.iter().copied()).collect();
+        forbidden.sort_unstable();
+
+        Self {
+            tail,
+            head: head.to_vec(),
+            forbidden,
+        }
+    }
+
+    /// Cost of turning from `from_arc` into `to_arc`, `None` if the turn is not allowed.
+    /// Can be passed to `line_graph`.
+    pub fn turn_cost(&self, from_arc: EdgeId, to_arc: EdgeId) -> Option<Weight> {
+        if self.tail[from_arc as usize] == self.head[to_arc as usize] || self.forbidden.binary_search(&(from_arc, to_arc)).is_ok() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+}
+
+type LineGraphServer<P> = query::Server<TurnExpandedPotential<P>, LiveTDDijkstraOps, LiveTDGraph>;
+
+/// Query server on the line graph.
+/// Queries go from the tail of the `from` arc to the tail of the `to` arc, so the travel time of `to` is not included.
+/// Paths consist of the ids of the original arcs.
+pub struct TurnExpandedServer<P> {
+    server: LineGraphServer<P>,
+}
+
+impl<P: Potential> TurnExpandedServer<P> {
+    /// Build the line graph with `turn_costs` (`None` for forbidden turns) and set up the query with `potential`,
+    /// which has to provide lower bounds for the nodes of the original graph.
+    pub fn new(
+        graph: impl Into<LiveTDGraph>,
+        turn_costs: impl FnMut(EdgeId, EdgeId) -> Option<Weight>,
+        potential: P,
+        #[cfg(feature = "chpot-visualize")] lat: &[f32],
+        #[cfg(feature = "chpot-visualize")] lng: &[f32],
+    ) -> Self {
+        let graph = graph.into();
+        let potential = TurnExpandedPotential::new(&graph, potential);
+        let line_graph = report_time_with_key("Line graph construction", "line_graph_construction", || graph.line_graph(turn_costs));
+
+        #[cfg(feature = "chpot-visualize")]
+        let (lat, lng): (Rc<[f32]>, Rc<[f32]>) = {
+            let mut tail_lat = Vec::with_capacity(graph.num_arcs());
+            let mut tail_lng = Vec::with_capacity(graph.num_arcs());
+            for node in 0..graph.num_nodes() {
+                for _ in 0..graph.degree(node as NodeId) {
+                    tail_lat.push(lat[node]);
+                    tail_lng.push(lng[node]);
+                }
+            }
+            (tail_lat.into(), tail_lng.into())
+        };
+
+        Self {
+            server: query::Server::new(
+                &line_graph,
+                potential,
+                LiveTDDijkstraOps::default(),
+                #[cfg(feature = "chpot-visualize")]
+                lat,
+                #[cfg(feature = "chpot-visualize")]
+                lng,
+            ),
+        }
+    }
+}
+
+impl<'a> TurnExpandedServer<CCHPotential<'a>> {
+    /// Use a `CCHPotential` customized with the lower bounds of the travel times, including the live ones.
+    /// The CCH has to be built for the topology of `graph`.
+    pub fn with_cch(
+        cch: &'a CCH,
+        graph: impl Into<LiveTDGraph>,
+        turn_costs: impl FnMut(EdgeId, EdgeId) -> Option<Weight>,
+        #[cfg(feature = "chpot-visualize")] lat: &[f32],
+        #[cfg(feature = "chpot-visualize")] lng: &[f32],
+    ) -> Self {
+        let graph = graph.into();
+        assert_eq!(cch.num_nodes(), graph.num_nodes());
+
+        let predicted = graph.predicted();
+        let mut lower_bound: Vec<Weight> = (0..predicted.num_arcs() as EdgeId)
+            .zip(graph.live().iter())
+            .map(|(edge_id, live)| {
+                let lower_bound = predicted.travel_time_function(edge_id).lower_bound();
+                live.value().map(|live| std::cmp::min(live, lower_bound)).unwrap_or(lower_bound)
+            })
+            .collect();
+        // the CCH only keeps one of several parallel arcs
+        unify_parallel_edges(&mut FirstOutGraph::new(predicted.first_out(), predicted.head(), &mut lower_bound[..]));
+        let potential = report_time("CCH Potentials customization", || {
+            CCHPotential::new(cch, &FirstOutGraph::new(predicted.first_out(), predicted.head(), lower_bound))
+        });
+
+        Self::new(
+            graph,
+            turn_costs,
+            potential,
+            #[cfg(feature = "chpot-visualize")]
+            lat,
+            #[cfg(feature = "chpot-visualize")]
+            lng,
+        )
+    }
+}
+
+impl<'s, P: 's + Potential> TDQueryServer<'s, Timestamp, Weight> for TurnExpandedServer<P> {
+    type P = query::PathServerWrapper<'s, TurnExpandedPotential<P>, LiveTDDijkstraOps, LiveTDGraph, TDQuery<Timestamp>>;
+
+    fn query(&'s mut self, query: TDQuery<Timestamp>) -> Option<QueryResult<'s, Self::P, Weight>> {
+        TDQueryServer::query(&mut self.server, query)
+    }
+}
@@ -4,12 +4,15 @@
 //! And more complicated path unpacking.
 //! This works because the augmented graph was split into an upward and an downward part.
 //! This implicitly makes sure, that both searches only go to higher ranked nodes.
+//! Both searches can use any of the priority queues, the keys are monotone.
 
 use super::*;
+use crate::algo::dijkstra::State;
+use crate::datastr::index_heap::*;
 
-pub struct Server {
-    forward_dijkstra: StandardDijkstra<OwnedGraph>,
-    backward_dijkstra: StandardDijkstra<OwnedGraph>,
+pub struct Server<Q = IndexdMinHeap<State<Weight>>> {
+    forward_dijkstra: GenericDijkstra<DefaultOps, OwnedGraph, Q>,
+    backward_dijkstra: GenericDijkstra<DefaultOps, OwnedGraph, Q>,
     tentative_distance: Weight,
     meeting_node: NodeId,
     shortcut_middle_nodes: Option<(Vec<NodeId>, Vec<NodeId>)>,
@@ -17,10 +20,17 @@ pub struct Server {
 }
 
 impl Server {
-    pub fn new(ch: ContractionHierarchy, order: NodeOrder) -> Server {
+    pub fn new(ch: ContractionHierarchy, order: NodeOrder) -> Self {
+        Self::new_with_queue(ch, order)
+    }
+}
+
+impl<Q: PriorityQueue<State<Weight>>> Server<Q> {
+    /// Create a server using the priority queue type `Q`, for example `Server::<RadixHeap<_>>::new_with_queue(ch, order)`.
+    pub fn new_with_queue(ch: ContractionHierarchy, order: NodeOrder) -> Self {
         Server {
-            forward_dijkstra: StandardDijkstra::new(ch.forward),
-            backward_dijkstra: StandardDijkstra::new(ch.backward),
+            forward_dijkstra: GenericDijkstra::new(ch.forward),
+            backward_dijkstra: GenericDijkstra::new(ch.backward),
             tentative_distance: INFINITY,
             meeting_node: 0,
             shortcut_middle_nodes: ch.middle_nodes,
@@ -141,9 +151,9 @@ impl Server {
     }
 }
 
-pub struct PathServerWrapper<'s>(&'s Server, Query);
+pub struct PathServerWrapper<'s, Q>(&'s Server<Q>, Query);
 
-impl<'s> PathServer for PathServerWrapper<'s> {
+impl<'s, Q: PriorityQueue<State<Weight>>> PathServer for PathServerWrapper<'s, Q> {
     type NodeInfo = NodeId;
 
     fn path(&mut self) -> Vec<Self::NodeInfo> {
@@ -151,8 +161,8 @@ impl<'s> PathServer for PathServerWrapper<'s> {
     }
 }
 
-impl<'s> QueryServer<'s> for Server {
-    type P = PathServerWrapper<'s>;
+impl<'s, Q: 's + PriorityQueue<State<Weight>>> QueryServer<'s> for Server<Q> {
+    type P = PathServerWrapper<'s, Q>;
 
     fn query(&'s mut self, query: Query) -> Option<QueryResult<Self::P, Weight>> {
         self.distance(query.from, query.to)
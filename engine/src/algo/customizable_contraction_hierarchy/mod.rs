@@ -358,6 +358,21 @@ pub struct Customized<'c, CCH> {
 }
 
 impl<'c, CCH: CCHT> Customized<'c, CCH> {
+    /// Reassemble from previously customized weights, for example after loading them from disk.
+    pub fn from_weights(cch: &'c CCH, upward: Vec<Weight>, downward: Vec<Weight>) -> Self {
+        assert_eq!(upward.len(), cch.forward_head().len());
+        assert_eq!(downward.len(), cch.backward_head().len());
+        Customized { cch, upward, downward }
+    }
+
+    pub fn upward_weights(&self) -> &[Weight] {
+        &self.upward
+    }
+
+    pub fn downward_weights(&self) -> &[Weight] {
+        &self.downward
+    }
+
     /// Decompose into an upward and a downward graph which could be used for a CH query.
     #[allow(clippy::type_complexity)]
     pub fn into_ch_graphs(
@@ -1,7 +1,7 @@
 //! Several variants of Dijkstra
 
 use super::*;
-use crate::datastr::index_heap::Indexing;
+use crate::datastr::{bucket_queue::IntegerKey, index_heap::Indexing};
 
 pub mod gen_topo_dijkstra;
 pub mod generic_dijkstra;
@@ -46,6 +46,13 @@ impl<W> Indexing for State<W> {
     }
 }
 
+impl IntegerKey for State<Weight> {
+    #[inline]
+    fn integer_key(&self) -> u32 {
+        self.key
+    }
+}
+
 pub trait Label {
     type Key: Ord;
     fn neutral() -> Self;
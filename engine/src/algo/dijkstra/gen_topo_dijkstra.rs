@@ -4,12 +4,12 @@ use super::generic_dijkstra::*;
 use super::*;
 use crate::datastr::{index_heap::*, timestamped_vector::*};
 
-pub struct GenTopoDijkstra<Ops: DijkstraOps<Graph>, Graph> {
+pub struct GenTopoDijkstra<Ops: DijkstraOps<Graph>, Graph, Q = IndexdMinHeap<State<<<Ops as DijkstraOps<Graph>>::Label as super::Label>::Key>>> {
     graph: Graph,
 
     distances: TimestampedVector<Ops::Label>,
     predecessors: Vec<NodeId>,
-    queue: IndexdMinHeap<State<<Ops::Label as super::Label>::Key>>,
+    queue: Q,
 
     num_relaxed_arcs: usize,
     num_queue_pushs: usize,
@@ -24,11 +24,12 @@ struct ChainStep<LinkResult> {
     next_distance: LinkResult,
 }
 
-impl<Ops, Graph> GenTopoDijkstra<Ops, Graph>
+impl<Ops, Graph, Q> GenTopoDijkstra<Ops, Graph, Q>
 where
     Ops: DijkstraOps<Graph>,
     <Ops::Label as super::Label>::Key: std::ops::Add<Output = <Ops::Label as super::Label>::Key>,
     Graph: for<'a> LinkIterable<'a, NodeId> + for<'a> LinkIterable<'a, Ops::Arc> + SymmetricDegreeGraph,
+    Q: PriorityQueue<State<<Ops::Label as super::Label>::Key>>,
 {
     pub fn new(graph: Graph) -> Self
     where
@@ -45,7 +46,7 @@ where
 
             distances: TimestampedVector::new(n, Label::neutral()),
             predecessors: vec![n as NodeId; n],
-            queue: IndexdMinHeap::new(n),
+            queue: Q::new(n),
 
             num_relaxed_arcs: 0,
             num_queue_pushs: 0,
@@ -213,7 +214,7 @@ where
         self.predecessors[node as usize]
     }
 
-    pub fn queue(&self) -> &IndexdMinHeap<State<<Ops::Label as super::Label>::Key>> {
+    pub fn queue(&self) -> &Q {
         &self.queue
     }
 
@@ -41,12 +41,14 @@ impl Default for DefaultOps {
     }
 }
 
-pub struct GenericDijkstra<Ops: DijkstraOps<Graph>, Graph> {
+/// Dijkstra's algorithm generic over the label type and the priority queue.
+/// The monotone queues from `datastr::bucket_queue` can be used for integer keys, when the keys of queued nodes never decrease below the last settled key.
+pub struct GenericDijkstra<Ops: DijkstraOps<Graph>, Graph, Q = IndexdMinHeap<State<<<Ops as DijkstraOps<Graph>>::Label as super::Label>::Key>>> {
     graph: Graph,
 
     distances: TimestampedVector<Ops::Label>,
     predecessors: Vec<NodeId>,
-    queue: IndexdMinHeap<State<<Ops::Label as super::Label>::Key>>,
+    queue: Q,
 
     ops: Ops,
 
@@ -54,10 +56,11 @@ pub struct GenericDijkstra<Ops: DijkstraOps<Graph>, Graph> {
     num_queue_pushs: usize,
 }
 
-impl<Ops, Graph> GenericDijkstra<Ops, Graph>
+impl<Ops, Graph, Q> GenericDijkstra<Ops, Graph, Q>
 where
     Ops: DijkstraOps<Graph>,
     Graph: for<'a> LinkIterable<'a, Ops::Arc>,
+    Q: PriorityQueue<State<<Ops::Label as super::Label>::Key>>,
 {
     pub fn new(graph: Graph) -> Self
     where
@@ -70,7 +73,7 @@ where
 
             distances: TimestampedVector::new(n, Label::neutral()),
             predecessors: vec![n as NodeId; n],
-            queue: IndexdMinHeap::new(n),
+            queue: Q::new(n),
 
             ops: Default::default(),
 
@@ -82,7 +85,7 @@ where
     /// For CH preprocessing we reuse the distance array and the queue to reduce allocations.
     /// This method creates an algo struct from recycled data.
     /// The counterpart is the `recycle` method.
-    pub fn from_recycled(graph: Graph, recycled: Trash<Ops::Label>) -> Self
+    pub fn from_recycled(graph: Graph, recycled: Trash<Ops::Label, Q>) -> Self
     where
         Ops: Default,
     {
@@ -175,14 +178,19 @@ where
         &self.graph
     }
 
-    pub fn queue(&self) -> &IndexdMinHeap<State<<Ops::Label as super::Label>::Key>> {
+    /// Modify the graph in between queries, for example to update weights.
+    pub fn graph_mut(&mut self) -> &mut Graph {
+        &mut self.graph
+    }
+
+    pub fn queue(&self) -> &Q {
         &self.queue
     }
 
     /// For CH preprocessing we reuse the distance array and the queue to reduce allocations.
     /// This method decomposes this algo struct for later reuse.
     /// The counterpart is `from_recycled`
-    pub fn recycle(self) -> Trash<Ops::Label> {
+    pub fn recycle(self) -> Trash<Ops::Label, Q> {
         Trash {
             distances: self.distances,
             predecessors: self.predecessors,
@@ -199,10 +207,11 @@ where
     }
 }
 
-impl<Ops, Graph> Iterator for GenericDijkstra<Ops, Graph>
+impl<Ops, Graph, Q> Iterator for GenericDijkstra<Ops, Graph, Q>
 where
     Ops: DijkstraOps<Graph>,
     Graph: for<'a> LinkIterable<'a, Ops::Arc>,
+    Q: PriorityQueue<State<<Ops::Label as super::Label>::Key>>,
 {
     type Item = NodeId;
 
@@ -214,8 +223,8 @@ where
 
 pub type StandardDijkstra<G> = GenericDijkstra<DefaultOps, G>;
 
-pub struct Trash<Label: super::Label> {
+pub struct Trash<Label: super::Label, Q = IndexdMinHeap<State<<Label as super::Label>::Key>>> {
     distances: TimestampedVector<Label>,
     predecessors: Vec<NodeId>,
-    queue: IndexdMinHeap<State<Label::Key>>,
+    queue: Q,
 }
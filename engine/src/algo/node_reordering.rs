@@ -0,0 +1,143 @@
+//! Node orders which give adjacent nodes close ids, so graph traversals access memory with better locality.
+//!
+//! All functions return a `NodeOrder` where the rank of a node is its new id.
+//! The order can be applied to a graph with `BuildPermutated` or to a whole graph directory
+//! with the `reorder` command of the `rrr` binary, which moves all node and arc attributes along.
+//!
+//! ```
+//! # use rust_road_router::{algo::node_reordering::*, datastr::graph::*};
+//! // 0 -> 2 -> 1 -> 3
+//! let graph = UnweightedOwnedGraph::new(vec![0, 1, 2, 3, 3], vec![2, 3, 1]);
+//! assert_eq!(dfs_order(&graph).order(), &[0, 2, 1, 3]);
+//! assert_eq!(bfs_order(&graph).order(), &[0, 2, 1, 3]);
+//! let lat = [0.0, 1.0, 0.0, 1.0];
+//! let lng = [0.0, 0.0, 1.0, 1.0];
+//! assert_eq!(hilbert_order(&lat, &lng).order(), &[0, 1, 3, 2]);
+//! ```
+
+use super::customizable_contraction_hierarchy::{CCH, CCHT};
+use crate::datastr::{graph::*, node_order::NodeOrder, rank_select_map::BitVec};
+use std::collections::VecDeque;
+
+/// Depth first search pre-order, following outgoing arcs.
+/// Whenever the search runs out of reachable nodes, it continues at the unvisited node with the smallest id.
+pub fn dfs_order<Graph: for<'a> LinkIterable<'a, NodeId>>(graph: &Graph) -> NodeOrder {
+    let mut order = Vec::with_capacity(graph.num_nodes());
+    let mut visited = BitVec::new(graph.num_nodes());
+    let mut stack = Vec::new();
+    for node in 0..graph.num_nodes() {
+        if !visited.get(node) {
+            stack.push(node as NodeId);
+        }
+
+        while let Some(node) = stack.pop() {
+            if visited.get(node as usize) {
+                continue;
+            }
+            order.push(node);
+            visited.set(node as usize);
+
+            for head in graph.link_iter(node) {
+                if !visited.get(head as usize) {
+                    stack.push(head);
+                }
+            }
+        }
+    }
+    NodeOrder::from_node_order(order)
+}
+
+/// Breadth first search order, following outgoing arcs.
+/// Whenever the search runs out of reachable nodes, it continues at the unvisited node with the smallest id.
+pub fn bfs_order<Graph: for<'a> LinkIterable<'a, NodeId>>(graph: &Graph) -> NodeOrder {
+    let mut order = Vec::with_capacity(graph.num_nodes());
+    let mut visited = BitVec::new(graph.num_nodes());
+    let mut queue = VecDeque::new();
+    for node in 0..graph.num_nodes() {
+        if visited.get(node) {
+            continue;
+        }
+        visited.set(node);
+        queue.push_back(node as NodeId);
+
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for head in graph.link_iter(node) {
+                if !visited.get(head as usize) {
+                    visited.set(head as usize);
+                    queue.push_back(head);
+                }
+            }
+        }
+    }
+    NodeOrder::from_node_order(order)
+}
+
+const HILBERT_BITS: u32 = 16;
+
+/// Order of the nodes along a Hilbert curve through the bounding box of their coordinates.
+/// Nodes are snapped to a grid of `2^16 x 2^16` cells, nodes in the same cell keep their relative order.
+pub fn hilbert_order(latitude: &[f32], longitude: &[f32]) -> NodeOrder {
+    assert_eq!(latitude.len(), longitude.len());
+    let grid_coordinate = |values: &[f32]| {
+        let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let max_cell = ((1u32 << HILBERT_BITS) - 1) as f64;
+        let scale = if max > min { max_cell / f64::from(max - min) } else { 0.0 };
+        move |value: f32| (f64::from(value - min) * scale).round() as u32
+    };
+    let x = grid_coordinate(longitude);
+    let y = grid_coordinate(latitude);
+
+    let mut order: Vec<NodeId> = (0..latitude.len() as NodeId).collect();
+    order.sort_by_cached_key(|&node| hilbert_index(x(longitude[node as usize]), y(latitude[node as usize])));
+    NodeOrder::from_node_order(order)
+}
+
+// Position of the grid cell on the curve, the classic iterative conversion which rotates the quadrant at every level
+fn hilbert_index(mut x: u32, mut y: u32) -> u64 {
+    let n = 1u32 << HILBERT_BITS;
+    let mut index = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        index += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+/// Post-order of the elimination tree of a CCH, built with a nested dissection order.
+/// Each cell of the separator decomposition gets a consecutive block of ids, followed by its separator.
+pub fn separator_order(cch: &CCH) -> NodeOrder {
+    let n = cch.num_nodes();
+    let mut children = vec![Vec::new(); n];
+    let mut roots = Vec::new();
+    for (rank, parent) in cch.elimination_tree().iter().enumerate() {
+        match parent.value() {
+            Some(parent) => children[parent as usize].push(rank as NodeId),
+            None => roots.push(rank as NodeId),
+        }
+    }
+
+    // a node is emitted when it gets popped the second time, after all its children
+    let mut order = Vec::with_capacity(n);
+    let mut stack: Vec<(NodeId, bool)> = roots.into_iter().rev().map(|root| (root, false)).collect();
+    while let Some((rank, children_done)) = stack.pop() {
+        if children_done {
+            order.push(cch.node_order().node(rank));
+        } else {
+            stack.push((rank, true));
+            stack.extend(children[rank as usize].iter().rev().map(|&child| (child, false)));
+        }
+    }
+    NodeOrder::from_node_order(order)
+}
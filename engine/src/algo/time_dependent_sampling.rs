@@ -7,18 +7,266 @@
 //! On this subgraph, a standard time-dependent dijkstra is performed.
 //!
 //! Preprocessing is completely done in CCHs, so this module is actually just the query algorithm.
+//! The windows can be chosen manually, split uniformly or clustered from the profiles of the graph.
+//! The customized windows can be stored and loaded again, so the server can be started without customizing.
 
 use super::*;
 use crate::{
     algo::{
-        customizable_contraction_hierarchy::{customize, query::Server as CCHServer, CCH},
+        customizable_contraction_hierarchy::{customize, query::Server as CCHServer, Customized, CCH},
         dijkstra::{generic_dijkstra::*, query::td_dijkstra::TDDijkstraOps},
     },
     datastr::{graph::time_dependent::*, graph::RandomLinkAccessGraph, timestamped_vector::TimestampedVector},
+    io::*,
 };
 
 use std::ops::Range;
 
+/// How the travel times of an arc within a window are condensed into a single value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    Mean,
+    Min,
+    Max,
+    /// Percentile of the travel time over the window, between 0 and 100.
+    /// Approximated by evaluating the function at evenly spaced points in time.
+    Percentile(u8),
+}
+
+// number of evaluations per window for percentile aggregation
+const PERCENTILE_SAMPLES: Timestamp = 64;
+
+fn aggregate(ttf: &PiecewiseLinearFunction, window: &WrappingRange, aggregation: Aggregation) -> Weight {
+    let (min, max) = ttf.bounds_in(window.clone());
+    if min == max {
+        return min;
+    }
+
+    match aggregation {
+        Aggregation::Mean => ttf.average(window.clone()),
+        Aggregation::Min => min,
+        Aggregation::Max => max,
+        Aggregation::Percentile(percentile) => {
+            assert!(percentile <= 100);
+            let range = window.clone().monotonize();
+            let step = std::cmp::max((range.end - range.start) / PERCENTILE_SAMPLES, 1);
+            let mut samples: Vec<Weight> = (range.start..range.end).step_by(step as usize).map(|t| ttf.eval(t)).collect();
+            samples.sort_unstable();
+            samples[(samples.len() - 1) * percentile as usize / 100]
+        }
+    }
+}
+
+/// The four windows TD-S uses by default: 22-5, 7-10, 11-15 and 16-19 o'clock.
+/// For periods spanning several days, for example a week, the windows are repeated for each day.
+/// Periods which are not a multiple of a day are treated as a single day.
+pub fn default_windows() -> Vec<WrappingRange> {
+    const HOURS: [(Timestamp, Timestamp); 4] = [(22, 5), (7, 10), (11, 15), (16, 19)];
+    let (num_days, day) = if period() / DAY * DAY == period() {
+        (period() / DAY, DAY)
+    } else {
+        (1, period())
+    };
+    let hour = day / 24;
+
+    (0..num_days)
+        .flat_map(|day_idx| {
+            HOURS.iter().map(move |&(start_hour, end_hour)| {
+                let day_start = day_idx * day;
+                // windows ending before they start end on the next day
+                let end_day_start = if end_hour < start_hour { day_start + day } else { day_start };
+                WrappingRange::new(Range {
+                    start: day_start + start_hour * hour,
+                    end: (end_day_start + end_hour * hour) % period(),
+                })
+            })
+        })
+        .collect()
+}
+
+/// Split the period into `num_windows` windows of the same length.
+pub fn uniform_windows(num_windows: usize) -> Vec<WrappingRange> {
+    assert!(num_windows > 0);
+    let boundaries: Vec<Timestamp> = (0..=num_windows)
+        .map(|i| (u64::from(period()) * i as u64 / num_windows as u64) as Timestamp)
+        .collect();
+    windows_from_boundaries(&boundaries)
+}
+
+fn windows_from_boundaries(boundaries: &[Timestamp]) -> Vec<WrappingRange> {
+    boundaries
+        .windows(2)
+        .map(|bounds| WrappingRange::new(bounds[0] % period()..bounds[1] % period()))
+        .collect()
+}
+
+// resolution of the congestion curve for clustering
+const CLUSTERING_BUCKETS: usize = 96;
+
+/// Determine `num_windows` windows with similar traffic conditions from the profiles of the graph.
+///
+/// For each of 96 buckets we calculate the average ratio of the travel time to the free flow travel time over all time-dependent arcs.
+/// This congestion curve is then split into contiguous (wrapping) windows such that the squared deviation from the mean of each window is minimal.
+pub fn clustered_windows(graph: &TDGraph, num_windows: usize) -> Vec<WrappingRange> {
+    let num_buckets = std::cmp::min(CLUSTERING_BUCKETS, period() as usize);
+    assert!(num_windows > 0 && num_windows <= num_buckets);
+    let bucket_start = |bucket: usize| (u64::from(period()) * bucket as u64 / num_buckets as u64) as Timestamp;
+
+    let mut congestion = vec![0.0; num_buckets];
+    let mut num_td_arcs = 0;
+    for edge_id in 0..graph.num_arcs() as EdgeId {
+        let ttf = graph.travel_time_function(edge_id);
+        let lower_bound = ttf.lower_bound();
+        if lower_bound == 0 || ttf.bounds_in(WrappingRange::new(0..0)) == (lower_bound, lower_bound) {
+            continue;
+        }
+        num_td_arcs += 1;
+        for (bucket, value) in congestion.iter_mut().enumerate() {
+            let mid = (bucket_start(bucket) + bucket_start(bucket + 1)) / 2;
+            *value += f64::from(ttf.eval(mid)) / f64::from(lower_bound);
+        }
+    }
+
+    if num_td_arcs == 0 {
+        return uniform_windows(num_windows);
+    }
+
+    let mut best: Option<(f64, usize, Vec<usize>)> = None;
+    for rotation in 0..num_buckets {
+        let rotated: Vec<f64> = (0..num_buckets).map(|i| congestion[(i + rotation) % num_buckets]).collect();
+        let (cost, cuts) = segment(&rotated, num_windows);
+        if best.as_ref().map(|(best_cost, _, _)| cost < *best_cost).unwrap_or(true) {
+            best = Some((cost, rotation, cuts));
+        }
+    }
+    let (_, rotation, cuts) = best.unwrap();
+
+    let boundaries: Vec<Timestamp> = cuts.iter().map(|&cut| bucket_start((cut + rotation) % num_buckets)).collect();
+    if num_windows == 1 {
+        return vec![WrappingRange::new(boundaries[0]..boundaries[0])];
+    }
+    windows_from_boundaries(&boundaries)
+}
+
+/// Optimal segmentation of `values` into `k` contiguous parts minimizing the sum of squared deviations.
+/// Returns the cost and the `k + 1` segment boundaries.
+fn segment(values: &[f64], k: usize) -> (f64, Vec<usize>) {
+    let n = values.len();
+    let mut prefix_sum = vec![0.0; n + 1];
+    let mut prefix_square_sum = vec![0.0; n + 1];
+    for (i, &value) in values.iter().enumerate() {
+        prefix_sum[i + 1] = prefix_sum[i] + value;
+        prefix_square_sum[i + 1] = prefix_square_sum[i] + value * value;
+    }
+    let cost = |from: usize, to: usize| {
+        let sum = prefix_sum[to] - prefix_sum[from];
+        prefix_square_sum[to] - prefix_square_sum[from] - sum * sum / (to - from) as f64
+    };
+
+    // table[j][i]: best cost for the first i values in j segments, and where the last segment starts
+    let mut table = vec![vec![(f64::INFINITY, 0); n + 1]; k + 1];
+    table[0][0] = (0.0, 0);
+    for j in 1..=k {
+        for i in j..=n {
+            for start in (j - 1)..i {
+                let candidate = table[j - 1][start].0 + cost(start, i);
+                if candidate < table[j][i].0 {
+                    table[j][i] = (candidate, start);
+                }
+            }
+        }
+    }
+
+    let mut boundaries = vec![n];
+    for j in (1..=k).rev() {
+        let last = *boundaries.last().unwrap();
+        boundaries.push(table[j][last].1);
+    }
+    boundaries.reverse();
+    (table[k][n].0, boundaries)
+}
+
+/// The customized CCHs of all windows.
+#[derive(Debug)]
+pub struct Samples<'a> {
+    windows: Vec<WrappingRange>,
+    customized: Vec<Customized<'a, CCH>>,
+}
+
+impl<'a> Samples<'a> {
+    /// Determine a static metric for each window and customize the CCH with it.
+    pub fn new(graph: &TDGraph, cch: &'a CCH, windows: &[WrappingRange], aggregation: Aggregation) -> Self {
+        let customized = windows
+            .iter()
+            .map(|window| {
+                let metric = (0..graph.num_arcs() as EdgeId)
+                    .map(|edge_id| aggregate(&graph.travel_time_function(edge_id), window, aggregation))
+                    .collect::<Vec<Weight>>();
+                customize(cch, &FirstOutGraph::new(graph.first_out(), graph.head(), metric))
+            })
+            .collect();
+
+        Samples {
+            windows: windows.to_vec(),
+            customized,
+        }
+    }
+
+    pub fn windows(&self) -> &[WrappingRange] {
+        &self.windows
+    }
+}
+
+impl<'a> Deconstruct for Samples<'a> {
+    fn store_each(&self, store: &dyn Fn(&str, &dyn Store) -> std::io::Result<()>) -> std::io::Result<()> {
+        let window_start: Vec<Timestamp> = self.windows.iter().map(WrappingRange::start).collect();
+        let window_end: Vec<Timestamp> = self.windows.iter().map(WrappingRange::end).collect();
+        store("tds_window_start", &window_start)?;
+        store("tds_window_end", &window_end)?;
+        for (i, customized) in self.customized.iter().enumerate() {
+            store(&format!("tds_upward_weight_{}", i), &customized.upward_weights())?;
+            store(&format!("tds_downward_weight_{}", i), &customized.downward_weights())?;
+        }
+        Ok(())
+    }
+}
+
+/// Additional data to load customized TD-S windows back from disk.
+#[derive(Debug)]
+pub struct SamplesReconstructor<'a> {
+    pub cch: &'a CCH,
+}
+
+impl<'a> ReconstructPrepared<Samples<'a>> for SamplesReconstructor<'a> {
+    fn reconstruct_with(self, loader: Loader) -> std::io::Result<Samples<'a>> {
+        let window_start: Vec<Timestamp> = loader.load("tds_window_start")?;
+        let window_end: Vec<Timestamp> = loader.load("tds_window_end")?;
+        let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("inconsistent TD-S data: {}", msg));
+        if window_start.len() != window_end.len() {
+            return Err(invalid("number of window starts and ends differ"));
+        }
+        let windows: Vec<WrappingRange> = window_start
+            .iter()
+            .zip(window_end.iter())
+            .map(|(&start, &end)| WrappingRange::new(start..end))
+            .collect();
+
+        let customized = (0..windows.len())
+            .map(|i| {
+                let upward_weights: Vec<Weight> = loader.load(format!("tds_upward_weight_{}", i))?;
+                let downward_weights: Vec<Weight> = loader.load(format!("tds_downward_weight_{}", i))?;
+                // samples of a different graph or CCH
+                if upward_weights.len() != self.cch.num_arcs() || downward_weights.len() != self.cch.num_arcs() {
+                    return Err(invalid("number of weights does not match the CCH"));
+                }
+                Ok(Customized::from_weights(self.cch, upward_weights, downward_weights))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        Ok(Samples { windows, customized })
+    }
+}
+
 /// Query server struct for TD-S.
 /// Implements the common query trait.
 pub struct Server<'a> {
@@ -31,36 +279,18 @@ pub struct Server<'a> {
 }
 
 impl<'a> Server<'a> {
+    /// Create a server with the default windows and averaged travel times
     pub fn new(graph: TDGraph, cch: &'a CCH) -> Server<'a> {
-        let hour = period() / 24; // here, we assume, that our travel time functions cover one day
-                                  // hardcoded four time windows
-        let samples = [
-            Range { start: 22, end: 5 },
-            Range { start: 7, end: 10 },
-            Range { start: 11, end: 15 },
-            Range { start: 16, end: 19 },
-        ]
-        .iter()
-        .map(|range| {
-            let range = Range {
-                start: range.start * hour,
-                end: range.end * hour,
-            };
-            WrappingRange::new(range)
-        })
-        .map(|range| {
-            // average travel time for each window
-            (0..graph.num_arcs() as EdgeId)
-                .map(|edge_id| graph.travel_time_function(edge_id).average(range.clone()))
-                .collect::<Vec<Weight>>()
-        })
-        .map(|metric| CCHServer::new(customize(cch, &FirstOutGraph::new(graph.first_out(), graph.head(), metric)))) // customize CCH for each window
-        .collect();
+        let samples = Samples::new(&graph, cch, &default_windows(), Aggregation::Mean);
+        Self::with_samples(graph, samples)
+    }
 
+    /// Create a server with custom windows, which might have been loaded from disk.
+    pub fn with_samples(graph: TDGraph, samples: Samples<'a>) -> Server<'a> {
         Server {
             active_edges: TimestampedVector::new(graph.num_arcs(), false),
             dijkstra: GenericDijkstra::new(graph),
-            samples,
+            samples: samples.customized.into_iter().map(CCHServer::new).collect(),
         }
     }
 
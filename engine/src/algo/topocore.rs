@@ -1,17 +1,250 @@
+//! Topocore: removing nodes of low degree and nodes outside of the biggest biconnected component from the graph.
+//!
+//! Queries only have to use the remaining core with full Dijkstra searches, see `query`.
+
 use super::*;
 use crate::algo::dijkstra::gen_topo_dijkstra::{SymmetricDeg, SymmetricDegreeGraph};
+use crate::algo::node_reordering::dfs_order;
 use crate::datastr::node_order::NodeOrder;
 use crate::datastr::rank_select_map::*;
+use crate::io::*;
 use crate::report::*;
 use crate::util::{in_range_option::InRangeOption, Bool, TapOps};
 use std::cmp::{max, min};
+use std::collections::HashMap;
+
+pub mod query;
 
+/// Result of the topocore preprocessing.
+///
+/// Nodes are reordered so the core nodes get the ranks `0..core_size`.
+/// Arcs of core nodes only lead to other core nodes.
+/// All other nodes keep the arcs they had when they were removed from the core.
+/// Shortcuts inserted while removing nodes remember the nodes they skip, so paths can be unpacked.
 #[derive(Debug)]
 pub struct Topocore {
     pub forward: OwnedGraph,
     pub backward: OwnedGraph,
     pub order: NodeOrder,
     pub core_size: usize,
+    forward_paths: ShortcutPaths,
+    backward_paths: ShortcutPaths,
+    contraction_kinds: Vec<ContractionKind>,
+}
+
+/// Why a node is not part of the core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractionKind {
+    Core,
+    /// Not in the biggest biconnected component
+    OtherBCC,
+    /// Removed with degree one or zero
+    Deg1,
+    /// Part of a chain of degree two nodes, which was replaced by a shortcut
+    Deg2Chain,
+    /// Removed with three neighbors, which got connected by shortcuts
+    Deg3,
+}
+
+impl ContractionKind {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ContractionKind::Core),
+            1 => Some(ContractionKind::OtherBCC),
+            2 => Some(ContractionKind::Deg1),
+            3 => Some(ContractionKind::Deg2Chain),
+            4 => Some(ContractionKind::Deg3),
+            _ => None,
+        }
+    }
+}
+
+impl Topocore {
+    /// Why `node` (an original node id) is not part of the core.
+    pub fn contraction_kind(&self, node: NodeId) -> ContractionKind {
+        self.contraction_kinds[self.order.rank(node) as usize]
+    }
+
+    pub fn in_core(&self, node: NodeId) -> bool {
+        (self.order.rank(node) as usize) < self.core_size
+    }
+
+    /// The nodes (as ranks) skipped by the arc with the given id of the forward graph.
+    pub fn forward_shortcut_path(&self, edge: EdgeId) -> &[NodeId] {
+        self.forward_paths.path(edge)
+    }
+
+    /// The nodes (as ranks) skipped by the arc with the given id of the backward graph, in the direction of the original graph.
+    pub fn backward_shortcut_path(&self, edge: EdgeId) -> &[NodeId] {
+        self.backward_paths.path(edge)
+    }
+}
+
+impl Deconstruct for Topocore {
+    fn store_each(&self, store: &dyn Fn(&str, &dyn Store) -> std::io::Result<()>) -> std::io::Result<()> {
+        store("topocore_forward_first_out", &self.forward.first_out())?;
+        store("topocore_forward_head", &self.forward.head())?;
+        store("topocore_forward_weight", &self.forward.weight())?;
+        store("topocore_forward_first_path_node", &self.forward_paths.first_node)?;
+        store("topocore_forward_path_node", &self.forward_paths.nodes)?;
+        store("topocore_backward_first_out", &self.backward.first_out())?;
+        store("topocore_backward_head", &self.backward.head())?;
+        store("topocore_backward_weight", &self.backward.weight())?;
+        store("topocore_backward_first_path_node", &self.backward_paths.first_node)?;
+        store("topocore_backward_path_node", &self.backward_paths.nodes)?;
+        store("topocore_ranks", &self.order.ranks())?;
+        store("topocore_core_size", &vec![self.core_size as u32])?;
+        store(
+            "topocore_contraction_kind",
+            &self.contraction_kinds.iter().map(|&kind| kind as u8).collect::<Vec<u8>>(),
+        )?;
+        Ok(())
+    }
+}
+
+impl Reconstruct for Topocore {
+    fn reconstruct_with(loader: Loader) -> std::io::Result<Self> {
+        let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("inconsistent topocore data: {}", msg));
+
+        let ranks: Vec<NodeId> = loader.load("topocore_ranks")?;
+        let n = ranks.len();
+        let mut seen = vec![false; n];
+        for &rank in &ranks {
+            if rank as usize >= n || std::mem::replace(&mut seen[rank as usize], true) {
+                return Err(invalid("ranks are not a permutation"));
+            }
+        }
+
+        let core_size: Vec<u32> = loader.load("topocore_core_size")?;
+        let core_size = match core_size[..] {
+            [core_size] if core_size as usize <= n => core_size as usize,
+            _ => return Err(invalid("invalid core size")),
+        };
+
+        let contraction_kinds = loader
+            .load::<Vec<u8>, _>("topocore_contraction_kind")?
+            .into_iter()
+            .map(ContractionKind::from_u8)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("unknown contraction kind"))?;
+        if contraction_kinds.len() != n
+            || contraction_kinds
+                .iter()
+                .enumerate()
+                .any(|(rank, &kind)| (kind == ContractionKind::Core) != (rank < core_size))
+        {
+            return Err(invalid("contraction kinds do not match the core"));
+        }
+
+        let load_graph = |direction: &str| -> std::io::Result<(OwnedGraph, ShortcutPaths)> {
+            let first_out: Vec<EdgeId> = loader.load(format!("topocore_{}_first_out", direction))?;
+            let head: Vec<NodeId> = loader.load(format!("topocore_{}_head", direction))?;
+            let weight: Vec<Weight> = loader.load(format!("topocore_{}_weight", direction))?;
+            let paths = ShortcutPaths {
+                first_node: loader.load(format!("topocore_{}_first_path_node", direction))?,
+                nodes: loader.load(format!("topocore_{}_path_node", direction))?,
+            };
+
+            if first_out.len() != n + 1
+                || !is_adjacency_array(&first_out, head.len())
+                || weight.len() != head.len()
+                || head.iter().any(|&head| head as usize >= n)
+            {
+                return Err(invalid(&format!("invalid {} graph", direction)));
+            }
+            if !is_adjacency_array(&paths.first_node, paths.nodes.len())
+                || paths.first_node.len() != head.len() + 1
+                || paths.nodes.iter().any(|&node| node as usize >= n)
+            {
+                return Err(invalid(&format!("invalid {} shortcut paths", direction)));
+            }
+            if first_out[..=core_size]
+                .windows(2)
+                .any(|edges| head[edges[0] as usize..edges[1] as usize].iter().any(|&head| head as usize >= core_size))
+            {
+                return Err(invalid(&format!("{} core arcs leave the core", direction)));
+            }
+
+            Ok((OwnedGraph::new(first_out, head, weight), paths))
+        };
+        let (forward, forward_paths) = load_graph("forward")?;
+        let (backward, backward_paths) = load_graph("backward")?;
+
+        Ok(Topocore {
+            forward,
+            backward,
+            order: NodeOrder::from_ranks(ranks),
+            core_size,
+            forward_paths,
+            backward_paths,
+            contraction_kinds,
+        })
+    }
+}
+
+fn is_adjacency_array(first: &[EdgeId], len: usize) -> bool {
+    first.first() == Some(&0) && first.last() == Some(&(len as EdgeId)) && first.windows(2).all(|w| w[0] <= w[1])
+}
+
+// For each arc the nodes it skips, as an adjacency array over the arcs.
+// Empty for arcs of the original graph.
+#[derive(Debug, Clone)]
+struct ShortcutPaths {
+    first_node: Vec<EdgeId>,
+    nodes: Vec<NodeId>,
+}
+
+impl ShortcutPaths {
+    fn path(&self, edge: EdgeId) -> &[NodeId] {
+        &self.nodes[self.first_node[edge as usize] as usize..self.first_node[edge as usize + 1] as usize]
+    }
+}
+
+// During preprocessing: the skipped nodes of all shortcuts by tail and head
+type ShortcutPathMap = HashMap<(NodeId, NodeId), Vec<NodeId>>;
+
+// The skipped nodes of a walk over the current arcs, including the inner nodes of the walk itself.
+fn unpack_walk(shortcut_paths: &ShortcutPathMap, walk: &[NodeId]) -> Vec<NodeId> {
+    let mut path = Vec::new();
+    for (i, arc) in walk.windows(2).enumerate() {
+        if i > 0 {
+            path.push(arc[0]);
+        }
+        if let Some(skipped) = shortcut_paths.get(&(arc[0], arc[1])) {
+            path.extend_from_slice(skipped);
+        }
+    }
+    path
+}
+
+fn insert_or_decrease(links: &mut Vec<Link>, target: NodeId, weight: Weight) {
+    if let Some(link) = links.iter_mut().find(|&&mut Link { node, .. }| node == target) {
+        link.weight = min(link.weight, weight);
+    } else {
+        links.push(Link { node: target, weight });
+    }
+}
+
+// Insert or decrease the arc in both adjacency lists and remember the skipped nodes, if the shortcut is better than the existing arc.
+fn insert_or_decrease_shortcut(
+    outs: &mut [Vec<Link>],
+    ins: &mut [Vec<Link>],
+    shortcut_paths: &mut ShortcutPathMap,
+    tail: NodeId,
+    head: NodeId,
+    weight: Weight,
+    path: Vec<NodeId>,
+) {
+    let improves = outs[tail as usize]
+        .iter()
+        .find(|&&Link { node, .. }| node == head)
+        .map(|link| weight < link.weight)
+        .unwrap_or(true);
+    insert_or_decrease(&mut outs[tail as usize], head, weight);
+    insert_or_decrease(&mut ins[head as usize], tail, weight);
+    if improves {
+        shortcut_paths.insert((tail, head), path);
+    }
 }
 
 #[derive(Debug)]
@@ -46,7 +279,7 @@ impl<'a, 'b, L, G: LinkIterable<'a, L>, H: LinkIterable<'a, L>> LinkIterable<'a,
 pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a, NodeId>, ReorderBCC: Bool, Deg1: Bool, Deg2: Bool, Deg3: Bool>(
     graph: &Graph,
 ) -> Topocore {
-    let order = dfs_pre_order(graph);
+    let order = dfs_order(graph);
 
     let n = graph.num_nodes();
 
@@ -87,9 +320,12 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
 
     let mut to_contract = BitVec::new(n);
     let mut queue = Vec::new();
+    let mut contraction_kinds = vec![ContractionKind::Core; n];
+    let mut shortcut_paths = ShortcutPathMap::new();
 
     if ReorderBCC::VALUE {
         to_contract.set_all();
+        contraction_kinds = vec![ContractionKind::OtherBCC; n];
 
         let biggest = biconnected(&UndirectedGraph { ins: &reversed, outs: graph })
             .into_iter()
@@ -98,6 +334,8 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
         for (u, v) in biggest {
             to_contract.unset(order.rank(u) as usize);
             to_contract.unset(order.rank(v) as usize);
+            contraction_kinds[order.rank(u) as usize] = ContractionKind::Core;
+            contraction_kinds[order.rank(v) as usize] = ContractionKind::Core;
         }
     }
 
@@ -124,6 +362,7 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
         for node in 0..n {
             if deg_zero_or_one(&outs[node], &ins[node]) && !to_contract.get(node) {
                 to_contract.set(node);
+                contraction_kinds[node] = ContractionKind::Deg1;
                 queue.push(node);
             }
         }
@@ -136,6 +375,7 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
 
                 if deg_zero_or_one(&outs[head as usize], head_ins) && !to_contract.get(head as usize) {
                     to_contract.set(head as usize);
+                    contraction_kinds[head as usize] = ContractionKind::Deg1;
                     queue.push(head as usize);
                 }
             }
@@ -147,6 +387,7 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
 
                 if deg_zero_or_one(head_outs, &ins[head as usize]) && !to_contract.get(head as usize) {
                     to_contract.set(head as usize);
+                    contraction_kinds[head as usize] = ContractionKind::Deg1;
                     queue.push(head as usize);
                 }
             }
@@ -162,14 +403,6 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
         }
     }
 
-    let insert_or_decrease = |links: &mut Vec<Link>, target: NodeId, weight: Weight| {
-        if let Some(link) = links.iter_mut().find(|&&mut Link { node, .. }| node == target) {
-            link.weight = min(link.weight, weight);
-        } else {
-            links.push(Link { node: target, weight });
-        }
-    };
-
     if Deg2::VALUE {
         let deg_two = |node_outs: &Vec<Link>, node_ins: &Vec<Link>| match (&node_outs[..], &node_ins[..]) {
             (&[_], &[_]) => true,
@@ -206,11 +439,13 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
             if !to_contract.get(node) && deg_two(&outs[node], &ins[node]) {
                 debug_assert!(!deg_zero_or_one(&outs[node], &ins[node]));
                 to_contract.set(node);
+                contraction_kinds[node] = ContractionKind::Deg2Chain;
 
                 let mut prev = node;
                 let mut next = outs[node][0].node as usize;
                 while !deg_zero_or_one(&outs[next], &ins[next]) && deg_two(&outs[next as usize], &ins[next as usize]) && !to_contract.get(next) {
                     to_contract.set(next);
+                    contraction_kinds[next] = ContractionKind::Deg2Chain;
                     let (first, second) = deg_two_neighbors(&outs[next], &ins[next]);
                     if prev as NodeId == first {
                         prev = next as usize;
@@ -222,16 +457,24 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
                 }
 
                 debug_assert!(!deg_zero_or_one(&outs[next], &ins[next]));
-                debug_assert!(!to_contract.get(next)); // isolated cycle
+                if to_contract.get(next) {
+                    // isolated cycle, we need to keep one node in the core as the end of the chain
+                    debug_assert_eq!(next, node);
+                    to_contract.unset(next);
+                    contraction_kinds[next] = ContractionKind::Core;
+                }
 
                 let end1 = next;
                 let end1_prev = prev;
                 std::mem::swap(&mut next, &mut prev);
 
                 let (mut forward, mut backward) = deg_two_weights(next as NodeId, &outs[prev], &ins[prev]);
+                let mut chain = vec![end1 as NodeId];
 
-                while !deg_zero_or_one(&outs[next], &ins[next]) && deg_two(&outs[next as usize], &ins[next as usize]) {
+                while next != end1 && !deg_zero_or_one(&outs[next], &ins[next]) && deg_two(&outs[next as usize], &ins[next as usize]) {
                     to_contract.set(next);
+                    contraction_kinds[next] = ContractionKind::Deg2Chain;
+                    chain.push(next as NodeId);
                     let (first, second) = deg_two_neighbors(&outs[next], &ins[next]);
                     if prev as NodeId == first {
                         prev = next as usize;
@@ -246,6 +489,10 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
                     backward = backward.and_then(|backward| next_backward.map(|next_backward| backward + next_backward));
                 }
                 let end2 = next;
+                chain.push(end2 as NodeId);
+                let forward_path = unpack_walk(&shortcut_paths, &chain);
+                chain.reverse();
+                let backward_path = unpack_walk(&shortcut_paths, &chain);
 
                 if let Some(pos) = outs[end1].iter().position(|&Link { node: head, .. }| head == end1_prev as NodeId) {
                     outs[end1].swap_remove(pos);
@@ -267,14 +514,12 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
 
                 if let Some(weight) = forward {
                     if end1 != end2 {
-                        insert_or_decrease(&mut outs[end1], end2 as NodeId, weight);
-                        insert_or_decrease(&mut ins[end2], end1 as NodeId, weight);
+                        insert_or_decrease_shortcut(&mut outs, &mut ins, &mut shortcut_paths, end1 as NodeId, end2 as NodeId, weight, forward_path);
                     }
                 }
                 if let Some(weight) = backward {
                     if end1 != end2 {
-                        insert_or_decrease(&mut ins[end1], end2 as NodeId, weight);
-                        insert_or_decrease(&mut outs[end2], end1 as NodeId, weight);
+                        insert_or_decrease_shortcut(&mut outs, &mut ins, &mut shortcut_paths, end2 as NodeId, end1 as NodeId, weight, backward_path);
                     }
                 }
 
@@ -323,6 +568,7 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
                 let neighbors = neighborhood(&outs[node], &ins[node]);
                 if neighbors.len() == 3 && neighbors.iter().all(|&neighbor| !to_contract.get(neighbor as usize)) {
                     to_contract.set(node);
+                    contraction_kinds[node] = ContractionKind::Deg3;
                     queue.push(node);
                 }
             }
@@ -354,8 +600,8 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
                 } in &node_in
                 {
                     if head != tail {
-                        insert_or_decrease(&mut outs[tail as usize], head, first_weight + second_weight);
-                        insert_or_decrease(&mut ins[head as usize], tail, first_weight + second_weight);
+                        let path = unpack_walk(&shortcut_paths, &[tail, node as NodeId, head]);
+                        insert_or_decrease_shortcut(&mut outs, &mut ins, &mut shortcut_paths, tail, head, first_weight + second_weight, path);
                     }
                 }
             }
@@ -400,31 +646,55 @@ pub fn preprocess<'c, Graph: for<'a> LinkIterGraph<'a> + for<'a> LinkIterable<'a
     let mut forward_weight = Vec::with_capacity(m_forward);
     let mut backward_head = Vec::with_capacity(m_backward);
     let mut backward_weight = Vec::with_capacity(m_backward);
+    let mut forward_paths = ShortcutPaths {
+        first_node: Vec::with_capacity(m_forward + 1),
+        nodes: Vec::new(),
+    };
+    forward_paths.first_node.push(0);
+    let mut backward_paths = ShortcutPaths {
+        first_node: Vec::with_capacity(m_backward + 1),
+        nodes: Vec::new(),
+    };
+    backward_paths.first_node.push(0);
 
     let new_order = NodeOrder::from_node_order(new_order);
+    let new_rank = |node: NodeId| new_order.rank(order.node(node));
     for &orig_node in new_order.order() {
         let node = order.rank(orig_node);
 
         let node_outs = &outs[node as usize];
         forward_first_out.push(forward_first_out.last().unwrap() + node_outs.len() as EdgeId);
         for &Link { node: head, weight } in node_outs {
-            forward_head.push(new_order.rank(order.node(head)));
+            forward_head.push(new_rank(head));
             forward_weight.push(weight);
+            if let Some(path) = shortcut_paths.get(&(node, head)) {
+                forward_paths.nodes.extend(path.iter().map(|&node| new_rank(node)));
+            }
+            forward_paths.first_node.push(forward_paths.nodes.len() as EdgeId);
         }
 
         let node_ins = &ins[node as usize];
         backward_first_out.push(backward_first_out.last().unwrap() + node_ins.len() as EdgeId);
-        for &Link { node: head, weight } in node_ins {
-            backward_head.push(new_order.rank(order.node(head)));
+        for &Link { node: tail, weight } in node_ins {
+            backward_head.push(new_rank(tail));
             backward_weight.push(weight);
+            if let Some(path) = shortcut_paths.get(&(tail, node)) {
+                backward_paths.nodes.extend(path.iter().map(|&node| new_rank(node)));
+            }
+            backward_paths.first_node.push(backward_paths.nodes.len() as EdgeId);
         }
     }
 
+    let contraction_kinds = new_order.order().iter().map(|&node| contraction_kinds[order.rank(node) as usize]).collect();
+
     Topocore {
         forward: OwnedGraph::new(forward_first_out, forward_head, forward_weight),
         backward: OwnedGraph::new(backward_first_out, backward_head, backward_weight),
         order: new_order,
         core_size,
+        forward_paths,
+        backward_paths,
+        contraction_kinds,
     }
 }
 
@@ -474,7 +744,7 @@ impl VirtualTopocore {
 
 #[allow(clippy::cognitive_complexity)]
 pub fn virtual_topocore<'c, Graph: for<'a> LinkIterable<'a, NodeId>>(graph: &Graph) -> VirtualTopocore {
-    let order = dfs_pre_order(graph);
+    let order = dfs_order(graph);
     let n = graph.num_nodes();
 
     let reversed = UnweightedOwnedGraph::reversed(graph);
@@ -630,38 +900,6 @@ impl<G> SymmetricDegreeGraph for VirtualTopocoreGraph<G> {
     }
 }
 
-fn dfs_pre_order<Graph: for<'a> LinkIterable<'a, NodeId>>(graph: &Graph) -> NodeOrder {
-    let mut order = Vec::with_capacity(graph.num_nodes());
-    dfs(graph, &mut |node| {
-        order.push(node);
-    });
-    NodeOrder::from_node_order(order)
-}
-
-fn dfs<Graph: for<'a> LinkIterable<'a, NodeId>>(graph: &Graph, visit: &mut impl FnMut(NodeId)) {
-    let mut visited = BitVec::new(graph.num_nodes());
-    let mut stack = Vec::new();
-    for node in 0..graph.num_nodes() {
-        if !visited.get(node) {
-            stack.push(node as NodeId);
-        }
-
-        while let Some(node) = stack.pop() {
-            if visited.get(node as usize) {
-                continue;
-            }
-            visit(node);
-            visited.set(node as usize);
-
-            for head in graph.link_iter(node) {
-                if !visited.get(head as usize) {
-                    stack.push(head);
-                }
-            }
-        }
-    }
-}
-
 fn biconnected<Graph: for<'a> LinkIterable<'a, NodeId>>(graph: &Graph) -> Vec<Vec<(NodeId, NodeId)>> {
     let mut stack = Vec::new();
 
@@ -730,50 +968,77 @@ fn biconnected<Graph: for<'a> LinkIterable<'a, NodeId>>(graph: &Graph) -> Vec<Ve
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
-
-    // #[test]
-    // fn test_minimal_chain() {
-    //     let first_out = vec![0,  1,     3,  4];
-    //     let head =      vec![1,  0, 2,  1];
-    //     let weight =    vec![1,  1, 1,  1];
-    //     let graph = OwnedGraph::new(first_out, head, weight);
-    //     let mut topocore = preprocess(&graph);
-    //     assert_eq!(topocore.distance(0, 2), Some(2));
-    //     assert_eq!(topocore.distance(2, 0), Some(2));
-    // }
-
-    // #[test]
-    // fn test_triangle() {
-    //     let first_out = vec![0,  1,  2,  3];
-    //     let head =      vec![1,  2,  0];
-    //     let weight =    vec![1,  1,  1];
-    //     let graph = OwnedGraph::new(first_out, head, weight);
-    //     let mut topocore = preprocess(&graph);
-    //     assert_eq!(topocore.distance(0, 2), Some(2));
-    //     assert_eq!(topocore.distance(0, 1), Some(1));
-    //     assert_eq!(topocore.distance(2, 0), Some(1));
-    //     assert_eq!(topocore.distance(2, 1), Some(2));
-    // }
-
-    // #[test]
-    // fn test_square_with_diag() {
-    //     let first_out = vec![0,     3,   5,     8,  10];
-    //     let head =      vec![1,2,3, 0,2, 0,1,3, 0,2];
-    //     let weight =    vec![1,5,2, 1,1, 5,1,2, 2,2];
-    //     let graph = OwnedGraph::new(first_out, head, weight);
-    //     let mut topocore = preprocess(&graph);
-    //     assert_eq!(topocore.distance(0, 1), Some(1));
-    //     assert_eq!(topocore.distance(1, 0), Some(1));
-    //     assert_eq!(topocore.distance(0, 2), Some(2));
-    //     assert_eq!(topocore.distance(2, 0), Some(2));
-    //     assert_eq!(topocore.distance(0, 3), Some(2));
-    //     assert_eq!(topocore.distance(3, 0), Some(2));
-    //     assert_eq!(topocore.distance(1, 2), Some(1));
-    //     assert_eq!(topocore.distance(2, 1), Some(1));
-    //     assert_eq!(topocore.distance(1, 3), Some(3));
-    //     assert_eq!(topocore.distance(3, 1), Some(3));
-    //     assert_eq!(topocore.distance(2, 3), Some(2));
-    //     assert_eq!(topocore.distance(3, 2), Some(2));
-    // }
+    use super::*;
+    use crate::util::True;
+
+    fn server(graph: &OwnedGraph) -> query::Server {
+        query::Server::new(preprocess::<_, True, True, True, True>(graph))
+    }
+
+    // Checks the distance and that the path is a valid path of this length in the graph
+    fn assert_query(server: &mut query::Server, graph: &OwnedGraph, from: NodeId, to: NodeId, expected: Option<Weight>) {
+        let mut result = server.query(Query { from, to });
+        assert_eq!(result.as_ref().map(|res| res.distance()), expected);
+        if let Some(result) = result.as_mut() {
+            let path = result.path();
+            assert_eq!(path.first(), Some(&from));
+            assert_eq!(path.last(), Some(&to));
+            let length: Weight = path
+                .windows(2)
+                .map(|arc| {
+                    LinkIterable::<Link>::link_iter(graph, arc[0])
+                        .filter(|link| link.node == arc[1])
+                        .map(|link| link.weight)
+                        .min()
+                        .unwrap()
+                })
+                .sum();
+            assert_eq!(Some(length), expected);
+        }
+    }
+
+    #[test]
+    fn test_minimal_chain() {
+        let first_out = vec![0, 1, 3, 4];
+        let head = vec![1, 0, 2, 1];
+        let weight = vec![1, 1, 1, 1];
+        let graph = OwnedGraph::new(first_out, head, weight);
+        let mut server = server(&graph);
+        assert_query(&mut server, &graph, 0, 2, Some(2));
+        assert_query(&mut server, &graph, 2, 0, Some(2));
+    }
+
+    #[test]
+    fn test_triangle() {
+        let first_out = vec![0, 1, 2, 3];
+        let head = vec![1, 2, 0];
+        let weight = vec![1, 1, 1];
+        let graph = OwnedGraph::new(first_out, head, weight);
+        let mut server = server(&graph);
+        assert_query(&mut server, &graph, 0, 2, Some(2));
+        assert_query(&mut server, &graph, 0, 1, Some(1));
+        assert_query(&mut server, &graph, 2, 0, Some(1));
+        assert_query(&mut server, &graph, 2, 1, Some(2));
+    }
+
+    #[test]
+    fn test_square_with_diag() {
+        let first_out = vec![0, 3, 5, 8, 10];
+        let head = vec![1, 2, 3, 0, 2, 0, 1, 3, 0, 2];
+        let weight = vec![1, 5, 2, 1, 1, 5, 1, 2, 2, 2];
+        let graph = OwnedGraph::new(first_out, head, weight);
+        let mut server = server(&graph);
+        assert_query(&mut server, &graph, 0, 1, Some(1));
+        assert_query(&mut server, &graph, 1, 0, Some(1));
+        assert_query(&mut server, &graph, 0, 2, Some(2));
+        assert_query(&mut server, &graph, 2, 0, Some(2));
+        assert_query(&mut server, &graph, 0, 3, Some(2));
+        assert_query(&mut server, &graph, 3, 0, Some(2));
+        assert_query(&mut server, &graph, 1, 2, Some(1));
+        assert_query(&mut server, &graph, 2, 1, Some(1));
+        assert_query(&mut server, &graph, 1, 3, Some(3));
+        assert_query(&mut server, &graph, 3, 1, Some(3));
+        assert_query(&mut server, &graph, 2, 3, Some(2));
+        assert_query(&mut server, &graph, 3, 2, Some(2));
+    }
 }
@@ -0,0 +1,132 @@
+//! Topocore query server.
+//!
+//! A bidirectional Dijkstra on the forward and backward part of the preprocessed graph.
+//! Since core nodes only have arcs to other core nodes, both searches leave the removed parts of the graph as fast as possible
+//! and then continue only on the core.
+//! The searches can not be stopped as soon as they meet, so we use the same stopping criterion as for CH queries.
+
+use super::*;
+use crate::algo::dijkstra::generic_dijkstra::*;
+
+pub struct Server {
+    forward_dijkstra: StandardDijkstra<OwnedGraph>,
+    backward_dijkstra: StandardDijkstra<OwnedGraph>,
+    forward_paths: ShortcutPaths,
+    backward_paths: ShortcutPaths,
+    tentative_distance: Weight,
+    meeting_node: NodeId,
+    order: NodeOrder,
+}
+
+impl Server {
+    pub fn new(topocore: Topocore) -> Server {
+        Server {
+            forward_dijkstra: StandardDijkstra::new(topocore.forward),
+            backward_dijkstra: StandardDijkstra::new(topocore.backward),
+            forward_paths: topocore.forward_paths,
+            backward_paths: topocore.backward_paths,
+            tentative_distance: INFINITY,
+            meeting_node: 0,
+            order: topocore.order,
+        }
+    }
+
+    fn distance(&mut self, from: NodeId, to: NodeId) -> Option<Weight> {
+        let from = self.order.rank(from);
+        let to = self.order.rank(to);
+
+        self.tentative_distance = INFINITY;
+
+        self.forward_dijkstra.initialize_query(Query { from, to });
+        self.backward_dijkstra.initialize_query(Query { from: to, to: from });
+
+        let mut forward_progress = 0;
+        let mut backward_progress = 0;
+        let mut forward_done = false;
+        let mut backward_done = false;
+
+        while (self.tentative_distance > forward_progress || self.tentative_distance > backward_progress) && !(forward_done && backward_done) {
+            if backward_done || (forward_progress <= backward_progress && !forward_done) {
+                if let Some(node) = self.forward_dijkstra.next() {
+                    let distance = *self.forward_dijkstra.tentative_distance(node);
+                    forward_progress = distance;
+
+                    if distance + self.backward_dijkstra.tentative_distance(node) < self.tentative_distance {
+                        self.tentative_distance = distance + self.backward_dijkstra.tentative_distance(node);
+                        self.meeting_node = node;
+                    }
+                } else {
+                    forward_done = true;
+                }
+            } else {
+                if let Some(node) = self.backward_dijkstra.next() {
+                    let distance = *self.backward_dijkstra.tentative_distance(node);
+                    backward_progress = distance;
+
+                    if distance + self.forward_dijkstra.tentative_distance(node) < self.tentative_distance {
+                        self.tentative_distance = distance + self.forward_dijkstra.tentative_distance(node);
+                        self.meeting_node = node;
+                    }
+                } else {
+                    backward_done = true;
+                }
+            }
+        }
+
+        match self.tentative_distance {
+            INFINITY => None,
+            dist => Some(dist),
+        }
+    }
+
+    fn path(&self, query: Query) -> Vec<NodeId> {
+        let from = self.order.rank(query.from);
+        let to = self.order.rank(query.to);
+
+        // collected backwards from the meeting node to the source
+        let mut forward_path = vec![self.meeting_node];
+        let mut node = self.meeting_node;
+        while node != from {
+            let pred = self.forward_dijkstra.predecessor(node);
+            let edge = self.forward_dijkstra.graph().edge_index(pred, node).unwrap();
+            forward_path.extend(self.forward_paths.path(edge).iter().rev());
+            forward_path.push(pred);
+            node = pred;
+        }
+        forward_path.reverse();
+
+        let mut node = self.meeting_node;
+        while node != to {
+            let pred = self.backward_dijkstra.predecessor(node);
+            let edge = self.backward_dijkstra.graph().edge_index(pred, node).unwrap();
+            forward_path.extend_from_slice(self.backward_paths.path(edge));
+            forward_path.push(pred);
+            node = pred;
+        }
+
+        for node in &mut forward_path {
+            *node = self.order.node(*node);
+        }
+
+        forward_path
+    }
+}
+
+pub struct PathServerWrapper<'s>(&'s Server, Query);
+
+impl<'s> PathServer for PathServerWrapper<'s> {
+    type NodeInfo = NodeId;
+
+    fn path(&mut self) -> Vec<Self::NodeInfo> {
+        Server::path(self.0, self.1)
+    }
+}
+
+impl<'s> QueryServer<'s> for Server {
+    type P = PathServerWrapper<'s>;
+
+    fn query(&'s mut self, query: Query) -> Option<QueryResult<'s, Self::P, Weight>> {
+        self.distance(query.from, query.to)
+            .map(move |distance| QueryResult::new(distance, PathServerWrapper(self, query)))
+    }
+}
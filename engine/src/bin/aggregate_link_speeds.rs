@@ -0,0 +1,58 @@
+// Aggregate link speed estimates into time-dependent travel time profiles.
+// Takes as input a graph directory and one or more CSV files with estimates.
+// Each line should contain the arc id, the link entered timestamp in ms, the estimate quality and the velocity in km/h.
+// The profiles will be written into the graph directory, which can then be used for `tdcch_customization`.
+// When the graph directory contains a `period` and a `period_start` file, for example for a weekly period, the profiles will cover that period.
+
+use rust_road_router::{
+    cli::CliErr,
+    datastr::graph::{time_dependent::load_period_config, *},
+    io::*,
+    link_speed_estimates::{profile_aggregation::*, LinkSpeedData},
+};
+use std::{env, error::Error, fs::File, path::Path};
+
+use csv::ReaderBuilder;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args();
+    args.next();
+    let arg = &args.next().ok_or(CliErr("No graph directory arg given"))?;
+    let path = Path::new(arg);
+
+    let geo_distance = Vec::<Weight>::load_from(path.join("geo_distance"))?;
+    let travel_time = Vec::<Weight>::load_from(path.join("travel_time"))?;
+    let num_arcs = travel_time.len();
+
+    let calendar = load_period_config(path)?;
+    let params = AggregationParams {
+        calendar,
+        ..AggregationParams::default()
+    };
+    let mut aggregator = ProfileAggregator::new(&geo_distance, &travel_time, params);
+
+    for file in args {
+        let file = File::open(file)?;
+        let mut reader = ReaderBuilder::new().has_headers(false).from_reader(file);
+
+        for line in reader.records() {
+            let records = line?;
+            let mut records = records.iter();
+            let edge: EdgeId = records.next().ok_or(CliErr("missing arc id"))?.parse()?;
+            let observation = LinkSpeedData {
+                link_id: u64::from(edge),
+                link_entered_timestamp: records.next().ok_or(CliErr("missing timestamp"))?.parse()?,
+                estimate_quality: records.next().ok_or(CliErr("missing quality"))?.parse()?,
+                velocity: records.next().ok_or(CliErr("missing velocity"))?.parse()?,
+            };
+
+            if (edge as usize) < num_arcs {
+                aggregator.add(edge, &observation);
+            }
+        }
+    }
+
+    aggregator.profiles().deconstruct_to(&path)?;
+
+    Ok(())
+}
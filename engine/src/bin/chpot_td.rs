@@ -37,6 +37,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let arg = &args.next().ok_or(CliErr("No directory arg given"))?;
     let path = Path::new(arg);
+    load_period_config(path)?;
 
     let first_out = Vec::load_from(path.join("first_out"))?;
     let head = Vec::load_from(path.join("head"))?;
@@ -4,7 +4,7 @@ extern crate rust_road_router;
 use rust_road_router::algo::customizable_contraction_hierarchy::*;
 use rust_road_router::{
     algo::{
-        ch_potentials::{query::Server as TopoServer, *},
+        ch_potentials::{query::Server as TopoServer, td_turns::ForbiddenTurns, *},
         dijkstra::{generic_dijkstra::DefaultOps, query::dijkstra::Server as DijkServer},
         *,
     },
@@ -48,32 +48,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let graph = FirstOutGraph::new(&first_out[..], &head[..], &travel_time[..]);
 
-    let mut tail = Vec::with_capacity(graph.num_arcs());
-    for node in 0..graph.num_nodes() {
-        for _ in 0..graph.degree(node as NodeId) {
-            tail.push(node as NodeId);
-        }
-    }
-
-    let mut iter = forbidden_turn_from_arc.iter().zip(forbidden_turn_to_arc.iter()).peekable();
-
-    let exp_graph = graph.line_graph(|edge1_idx, edge2_idx| {
-        while let Some((&from_arc, &to_arc)) = iter.peek() {
-            if from_arc < edge1_idx || (from_arc == edge1_idx && to_arc < edge2_idx) {
-                iter.next();
-            } else {
-                break;
-            }
-        }
-
-        if iter.peek() == Some(&(&edge1_idx, &edge2_idx)) {
-            return None;
-        }
-        if tail[edge1_idx as usize] == head[edge2_idx as usize] {
-            return None;
-        }
-        Some(0)
-    });
+    let turns = ForbiddenTurns::new(&first_out, &head, &forbidden_turn_from_arc, &forbidden_turn_to_arc);
+    let exp_graph = graph.line_graph(|edge1_idx, edge2_idx| turns.turn_cost(edge1_idx, edge2_idx));
 
     let mut algo_runs_ctxt = push_collection_context("algo_runs".to_string());
 
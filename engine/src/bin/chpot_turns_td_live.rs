@@ -8,7 +8,7 @@ extern crate rust_road_router;
 use rust_road_router::algo::customizable_contraction_hierarchy::*;
 use rust_road_router::{
     algo::{
-        ch_potentials::{query::Server, *},
+        ch_potentials::{td_turns::*, *},
         dijkstra::query::{dijkstra::Server as DijkServer, td_dijkstra::LiveTDDijkstraOps},
         *,
     },
@@ -44,6 +44,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let arg = &args.next().ok_or(CliErr("No directory arg given"))?;
     let path = Path::new(arg);
+    load_period_config(path)?;
 
     let first_out = Vec::load_from(path.join("first_out"))?;
     let head = Vec::load_from(path.join("head"))?;
@@ -64,7 +65,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let graph = TDGraph::new(first_out, head.clone(), first_ipp_of_arc, ipp_departure_time, ipp_travel_time);
 
-    let n = graph.num_nodes();
+    // queries on the line graph go from arc to arc
+    let m = graph.num_arcs();
 
     report!("graph", { "num_nodes": graph.num_nodes(), "num_arcs": graph.num_arcs(), "num_ipps": graph.num_ipps(), "num_constant_ttfs": graph.num_constant() });
 
@@ -141,7 +143,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         contract(&graph, cch_order)
     };
 
-    let potential = TurnExpandedPotential::new(&graph, {
+    let potential = {
         #[cfg(feature = "chpot-only-topo")]
         {
             ZeroPotential()
@@ -169,45 +171,19 @@ fn main() -> Result<(), Box<dyn Error>> {
                 )
             }
         }
-    });
+    };
 
     let graph = LiveTDGraph::new(graph, soon, live);
 
     let forbidden_turn_from_arc = Vec::<EdgeId>::load_from(path.join("forbidden_turn_from_arc"))?;
     let forbidden_turn_to_arc = Vec::<EdgeId>::load_from(path.join("forbidden_turn_to_arc"))?;
 
-    let mut tail = Vec::with_capacity(graph.num_arcs());
-    for node in 0..graph.num_nodes() {
-        for _ in 0..graph.degree(node as NodeId) {
-            tail.push(node as NodeId);
-        }
-    }
-
-    let mut iter = forbidden_turn_from_arc.iter().zip(forbidden_turn_to_arc.iter()).peekable();
-
-    let graph = graph.line_graph(|edge1_idx, edge2_idx| {
-        while let Some((&from_arc, &to_arc)) = iter.peek() {
-            if from_arc < edge1_idx || (from_arc == edge1_idx && to_arc < edge2_idx) {
-                iter.next();
-            } else {
-                break;
-            }
-        }
-
-        if iter.peek() == Some(&(&edge1_idx, &edge2_idx)) {
-            return None;
-        }
-        if tail[edge1_idx as usize] == head[edge2_idx as usize] {
-            return None;
-        }
-        Some(0)
-    });
-
+    let turns = ForbiddenTurns::new(graph.predicted().first_out(), &head, &forbidden_turn_from_arc, &forbidden_turn_to_arc);
     let core_ids = core_affinity::get_core_ids().unwrap();
     core_affinity::set_for_current(core_ids[0]);
 
     let virtual_topocore_ctxt = algo_runs_ctxt.push_collection_item();
-    let mut server = Server::new(&graph, potential, LiveTDDijkstraOps::default());
+    let mut server = TurnExpandedServer::new(graph.clone(), |edge1_idx, edge2_idx| turns.turn_cost(edge1_idx, edge2_idx), potential);
     drop(virtual_topocore_ctxt);
 
     let num_queries = rust_road_router::experiments::chpot::NUM_QUERIES;
@@ -216,8 +192,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     for _i in 0..num_queries {
         let _query_ctxt = algo_runs_ctxt.push_collection_item();
-        let from: NodeId = rng.gen_range(0, n as NodeId);
-        let to: NodeId = rng.gen_range(0, n as NodeId);
+        let from: NodeId = rng.gen_range(0, m as NodeId);
+        let to: NodeId = rng.gen_range(0, m as NodeId);
 
         report!("from", from);
         report!("to", to);
@@ -229,17 +205,20 @@ fn main() -> Result<(), Box<dyn Error>> {
         astar_time = astar_time + time;
     }
     eprintln!("A* {}", astar_time / (num_queries as i32));
+    // the line graph for the baseline is only built now, so there is never more than one copy in memory
+    drop(server);
 
     let num_queries = rust_road_router::experiments::NUM_DIJKSTRA_QUERIES;
 
-    let mut server = DijkServer::<LiveTDDijkstraOps, _, _>::new(graph);
+    let line_graph = graph.line_graph(|edge1_idx, edge2_idx| turns.turn_cost(edge1_idx, edge2_idx));
+    let mut server = DijkServer::<LiveTDDijkstraOps, _, _>::new(line_graph);
 
     let mut dijkstra_time = Duration::zero();
 
     for _i in 0..num_queries {
         let _query_ctxt = algo_runs_ctxt.push_collection_item();
-        let from: NodeId = rng.gen_range(0, n as NodeId);
-        let to: NodeId = rng.gen_range(0, n as NodeId);
+        let from: NodeId = rng.gen_range(0, m as NodeId);
+        let to: NodeId = rng.gen_range(0, m as NodeId);
         let at: NodeId = rng.gen_range(0, period() as Timestamp);
 
         report!("from", from);
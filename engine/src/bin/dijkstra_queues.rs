@@ -0,0 +1,152 @@
+// Benchmark of the different priority queues for Dijkstra and CH queries.
+// Takes a graph directory as argument, CH queries are only run when it contains a CH in the `travel_time_ch` subdirectory.
+
+#[macro_use]
+extern crate rust_road_router;
+use rust_road_router::{
+    algo::{
+        contraction_hierarchy::{query::Server as CHServer, ContractionHierarchy},
+        dijkstra::{generic_dijkstra::*, State},
+        *,
+    },
+    cli::CliErr,
+    datastr::{bucket_queue::*, graph::*, index_heap::*, node_order::NodeOrder},
+    experiments::{queries::uniform, NUM_DIJKSTRA_QUERIES},
+    io::*,
+    report::*,
+};
+
+use rand::prelude::*;
+use std::{env, error::Error, path::Path};
+use time::Duration;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let _reporter = enable_reporting();
+
+    let seed = Default::default();
+    report!("seed", seed);
+    let mut rng = StdRng::from_seed(seed);
+
+    report!("program", "dijkstra_queues");
+    report!("start_time", format!("{}", time::now_utc().rfc822()));
+    report!("args", env::args().collect::<Vec<String>>());
+
+    let mut args = env::args();
+    args.next();
+    let arg = &args.next().ok_or(CliErr("No graph directory arg given"))?;
+    let path = Path::new(arg);
+
+    let first_out = Vec::load_from(path.join("first_out"))?;
+    let head = Vec::load_from(path.join("head"))?;
+    let travel_time = Vec::load_from(path.join("travel_time"))?;
+    let graph = FirstOutGraph::new(&first_out[..], &head[..], &travel_time[..]);
+
+    let ch = if path.join("travel_time_ch").exists() {
+        let ch_first_out = Vec::load_from(path.join("travel_time_ch/first_out"))?;
+        let ch_head = Vec::load_from(path.join("travel_time_ch/head"))?;
+        let ch_weight = Vec::load_from(path.join("travel_time_ch/weight"))?;
+        let ch_order = NodeOrder::from_node_order(Vec::load_from(path.join("travel_time_ch/order"))?);
+        Some((OwnedGraph::new(ch_first_out, ch_head, ch_weight), ch_order))
+    } else {
+        None
+    };
+
+    let queries = uniform(graph.num_nodes(), NUM_DIJKSTRA_QUERIES, &mut rng);
+
+    let core_ids = core_affinity::get_core_ids().unwrap();
+    core_affinity::set_for_current(core_ids[0]);
+
+    let mut algo_runs_ctxt = push_collection_context("algo_runs".to_string());
+
+    let expected = dijkstra_queries::<IndexdMinHeap<_>>("binary_heap", &graph, &queries, &mut algo_runs_ctxt);
+    assert_eq!(dijkstra_queries::<RadixHeap<_>>("radix_heap", &graph, &queries, &mut algo_runs_ctxt), expected);
+    assert_eq!(
+        dijkstra_queries::<MultiLevelBucketQueue<_>>("multi_level_buckets", &graph, &queries, &mut algo_runs_ctxt),
+        expected
+    );
+
+    if let Some((ch_graph, ch_order)) = ch {
+        let ch = || ContractionHierarchy::from_contracted_graph(ch_graph.clone(), &ch_order);
+        let identity = || NodeOrder::identity(graph.num_nodes());
+
+        let mut server = CHServer::<IndexdMinHeap<_>>::new_with_queue(ch(), identity());
+        assert_eq!(ch_queries("binary_heap", &mut server, &queries, &mut algo_runs_ctxt), expected);
+        let mut server = CHServer::<RadixHeap<_>>::new_with_queue(ch(), identity());
+        assert_eq!(ch_queries("radix_heap", &mut server, &queries, &mut algo_runs_ctxt), expected);
+        let mut server = CHServer::<MultiLevelBucketQueue<_>>::new_with_queue(ch(), identity());
+        assert_eq!(ch_queries("multi_level_buckets", &mut server, &queries, &mut algo_runs_ctxt), expected);
+    }
+
+    Ok(())
+}
+
+fn dijkstra_queries<Q: PriorityQueue<State<Weight>>>(
+    queue: &str,
+    graph: &FirstOutGraph<&[EdgeId], &[NodeId], &[Weight]>,
+    queries: &[Query],
+    algo_runs_ctxt: &mut CollectionContextGuard,
+) -> Vec<Option<Weight>> {
+    let mut dijkstra = GenericDijkstra::<DefaultOps, _, Q>::new(graph.clone());
+    let mut total_query_time = Duration::zero();
+    let mut results = Vec::with_capacity(queries.len());
+
+    for &query in queries {
+        let _query_ctxt = algo_runs_ctxt.push_collection_item();
+        report!("algo", "Dijkstra");
+        report!("queue", queue);
+        report!("from", query.from);
+        report!("to", query.to);
+
+        let (result, time) = measure(|| {
+            dijkstra.initialize_query(query);
+            while let Some(node) = dijkstra.next() {
+                if node == query.to {
+                    return Some(*dijkstra.tentative_distance(node));
+                }
+            }
+            None
+        });
+
+        report!("running_time_ms", time.to_std().unwrap().as_nanos() as f64 / 1_000_000.0);
+        report!("result", result);
+        total_query_time = total_query_time + time;
+        results.push(result);
+    }
+
+    if !queries.is_empty() {
+        eprintln!("Dijkstra with {}: avg. query time {}", queue, total_query_time / (queries.len() as i32))
+    };
+
+    results
+}
+
+fn ch_queries<Q: PriorityQueue<State<Weight>>>(
+    queue: &str,
+    server: &mut CHServer<Q>,
+    queries: &[Query],
+    algo_runs_ctxt: &mut CollectionContextGuard,
+) -> Vec<Option<Weight>> {
+    let mut total_query_time = Duration::zero();
+    let mut results = Vec::with_capacity(queries.len());
+
+    for &query in queries {
+        let _query_ctxt = algo_runs_ctxt.push_collection_item();
+        report!("algo", "CH");
+        report!("queue", queue);
+        report!("from", query.from);
+        report!("to", query.to);
+
+        let (result, time) = measure(|| QueryServer::query(server, query).map(|res| res.distance()));
+
+        report!("running_time_ms", time.to_std().unwrap().as_nanos() as f64 / 1_000_000.0);
+        report!("result", result);
+        total_query_time = total_query_time + time;
+        results.push(result);
+    }
+
+    if !queries.is_empty() {
+        eprintln!("CH with {}: avg. query time {}", queue, total_query_time / (queries.len() as i32))
+    };
+
+    results
+}
@@ -0,0 +1,34 @@
+// Run the experiments described in a JSON config file, see `experiments::harness` for the format.
+
+#[macro_use]
+extern crate rust_road_router;
+use rust_road_router::{cli::CliErr, experiments::harness::*, report::*};
+use std::{env, error::Error, path::Path};
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let _reporter = enable_reporting();
+
+    report!("program", "experiment");
+    report!("start_time", format!("{}", time::now_utc().rfc822()));
+    report!("args", env::args().collect::<Vec<String>>());
+
+    let mut args = env::args();
+    args.next();
+    let arg = &args.next().ok_or(CliErr("No experiment config arg given"))?;
+    let experiments = Experiment::load_all(Path::new(arg))?;
+
+    let core_ids = core_affinity::get_core_ids().unwrap();
+    core_affinity::set_for_current(core_ids[0]);
+
+    for (experiment, result) in experiments.iter().zip(run_all(&experiments)?) {
+        if result.num_query_runs > 0 {
+            eprintln!(
+                "{}: avg. query time {}ms",
+                experiment.name,
+                result.total_query_time_ms / result.num_query_runs as f64
+            )
+        };
+    }
+
+    Ok(())
+}
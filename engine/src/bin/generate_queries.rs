@@ -0,0 +1,68 @@
+// Generate a query set for a graph and store it in a directory, so all algorithms can be benchmarked on the same queries.
+// Usage: generate_queries GRAPH_DIR (uniform|rank|local|td) NUM_QUERIES OUTPUT_DIR
+// For rank queries, NUM_QUERIES is the number of sources. Local queries need `latitude` and `longitude` files.
+// Time-dependent queries are uniform queries with uniformly random departure times within the period of the graph.
+
+#[macro_use]
+extern crate rust_road_router;
+use rust_road_router::{
+    cli::CliErr,
+    datastr::graph::{time_dependent::load_period_config, *},
+    experiments::queries::*,
+    io::*,
+    report::*,
+};
+
+use rand::prelude::*;
+use std::{env, error::Error, path::Path};
+
+/// Targets for local queries are at most this far away from the source [m]
+const LOCAL_RADIUS: f64 = 10_000.0;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let _reporter = enable_reporting();
+
+    report!("program", "generate_queries");
+    report!("start_time", format!("{}", time::now_utc().rfc822()));
+    report!("args", env::args().collect::<Vec<String>>());
+
+    let seed = Default::default();
+    report!("seed", seed);
+    let mut rng = StdRng::from_seed(seed);
+
+    let mut args = env::args();
+    args.next();
+    let arg = &args.next().ok_or(CliErr("No graph directory arg given"))?;
+    let path = Path::new(arg);
+    let kind = args.next().ok_or(CliErr("No query kind arg given"))?;
+    let num_queries = args.next().ok_or(CliErr("No number of queries arg given"))?.parse::<usize>()?;
+    let out = args.next().ok_or(CliErr("No output directory arg given"))?;
+    let out = Path::new(&out);
+    std::fs::create_dir_all(out)?;
+
+    let first_out = Vec::<EdgeId>::load_from(path.join("first_out"))?;
+    let head = Vec::<NodeId>::load_from(path.join("head"))?;
+    let num_nodes = first_out.len() - 1;
+
+    match kind.as_str() {
+        "uniform" => store_queries(out, &uniform(num_nodes, num_queries, &mut rng))?,
+        "rank" => {
+            let travel_time = Vec::<Weight>::load_from(path.join("travel_time"))?;
+            let graph = FirstOutGraph::new(&first_out[..], &head[..], &travel_time[..]);
+            store_rank_queries(out, &dijkstra_rank(graph, num_queries, &mut rng))?
+        }
+        "local" => {
+            let lat = Vec::<f32>::load_from(path.join("latitude"))?;
+            let lng = Vec::<f32>::load_from(path.join("longitude"))?;
+            store_queries(out, &local(&lat, &lng, LOCAL_RADIUS, num_queries, &mut rng))?
+        }
+        "td" => {
+            load_period_config(path)?;
+            let queries = uniform(num_nodes, num_queries, &mut rng);
+            store_td_queries(out, &with_departures(queries, &mut rng))?
+        }
+        _ => return Err(Box::new(CliErr("Unknown query kind, expected one of uniform, rank, local or td"))),
+    }
+
+    Ok(())
+}
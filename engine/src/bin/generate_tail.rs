@@ -1,26 +0,0 @@
-use rust_road_router::{cli::CliErr, datastr::graph::*, io::*};
-
-use std::{env, error::Error, path::Path};
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut args = env::args();
-    args.next();
-    let arg = &args.next().ok_or(CliErr("No graph directory arg given"))?;
-    let path = Path::new(arg);
-
-    let first_out = Vec::load_from(path.join("first_out"))?;
-    let head = Vec::load_from(path.join("head"))?;
-
-    let graph = FirstOutGraph::new(&first_out[..], &head[..], &head[..]);
-
-    let mut tail = Vec::with_capacity(graph.num_arcs());
-    for node in 0..graph.num_nodes() {
-        for _ in 0..graph.degree(node as NodeId) {
-            tail.push(node as NodeId);
-        }
-    }
-
-    tail.write_to(&path.join("tail"))?;
-
-    Ok(())
-}
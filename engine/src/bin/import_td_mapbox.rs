@@ -15,6 +15,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     args.next();
     let arg = &args.next().ok_or(CliErr("No graph directory arg given"))?;
     let path = Path::new(arg);
+    load_period_config(path)?;
+    let period = period();
 
     let first_out = Vec::<NodeId>::load_from(path.join("first_out"))?;
     let head = Vec::<EdgeId>::load_from(path.join("head"))?;
@@ -107,7 +109,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                         for i in (0..profile.len()).rev() {
                             profile[i].1 = std::cmp::min(
                                 profile[i].1,
-                                profile[(i + 1) % profile.len()].1 + (profile[(i + 1) % profile.len()].0 + 1000 * 3600 * 24 - profile[i].0) % 1000 * 3600 * 24,
+                                profile[(i + 1) % profile.len()].1 + (profile[(i + 1) % profile.len()].0 + period - profile[i].0) % period,
                             );
                         }
                     }
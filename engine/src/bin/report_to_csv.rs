@@ -0,0 +1,20 @@
+// Convert a JSON report of one of the experiment binaries into CSV.
+// Reads the report from the file given as argument or from stdin and writes the CSV to stdout.
+// See `report::table` for how the report gets flattened.
+
+use rust_road_router::report::table::Table;
+use std::{env, error::Error, fs::File, io};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args();
+    args.next();
+
+    let report: serde_json::Value = match args.next() {
+        Some(path) => serde_json::from_reader(File::open(path)?)?,
+        None => serde_json::from_reader(io::stdin())?,
+    };
+
+    Table::from_report(&report).write_csv(io::stdout().lock())?;
+
+    Ok(())
+}
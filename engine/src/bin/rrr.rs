@@ -0,0 +1,9 @@
+// Graph processing toolbox with chainable subcommands, see `rrr --help`.
+// Example: rrr read GRAPH_DIR subgraph --bbox 48.9,8.3,49.1,8.5 largest-scc write OUTPUT_DIR
+
+use rust_road_router::cli::toolbox;
+use std::{env, error::Error};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    toolbox::run(env::args().skip(1))
+}
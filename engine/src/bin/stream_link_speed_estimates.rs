@@ -0,0 +1,42 @@
+// Estimate link speeds from a stream of interleaved vehicle events read from stdin.
+// Takes as input the format arg, either `csv` or `jsonl`, see `link_speed_estimates::streaming` for the details.
+// Estimates are written to stdout as CSV lines of link id, link entered timestamp, estimate quality and velocity,
+// which is the input format of `aggregate_link_speeds`.
+
+use rust_road_router::{cli::CliErr, link_speed_estimates::streaming::*};
+use std::{
+    env,
+    error::Error,
+    io::{stdin, stdout, BufWriter, Write},
+};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args();
+    args.next();
+    let format = args.next().ok_or(CliErr("No format arg given"))?;
+
+    let stdin = stdin();
+    let events: Box<dyn Iterator<Item = Result<VehicleEvent, Box<dyn Error>>>> = match format.as_str() {
+        "csv" => Box::new(csv_events(stdin.lock())),
+        "jsonl" => Box::new(json_lines_events(stdin.lock())),
+        _ => return Err(Box::new(CliErr("Unknown format, expected csv or jsonl"))),
+    };
+
+    let mut num_invalid = 0;
+    let events = events.filter_map(|event| event.map_err(|_| num_invalid += 1).ok());
+
+    let stdout = stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for estimate in estimate_stream(events, StreamingParams::default()) {
+        writeln!(
+            out,
+            "{},{},{},{}",
+            estimate.link_id, estimate.link_entered_timestamp, estimate.estimate_quality, estimate.velocity
+        )?;
+    }
+    out.flush()?;
+
+    eprintln!("skipped {} invalid lines", num_invalid);
+
+    Ok(())
+}
@@ -2,7 +2,11 @@
 
 use std::{env, error::Error, fs::File, io::prelude::*, path::Path};
 
-use rust_road_router::{cli::CliErr, io::*};
+use rust_road_router::{
+    cli::CliErr,
+    datastr::graph::time_dependent::{load_period_config, period},
+    io::*,
+};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut args = env::args();
@@ -10,7 +14,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     let arg = &args.next().ok_or(CliErr("No directory arg given"))?;
     let path = Path::new(arg);
 
-    let period = f64::from(Vec::<u32>::load_from(path.join("period"))?[0]);
+    load_period_config(path)?;
+    let period = f64::from(period());
     let first_out = Vec::<u32>::load_from(path.join("first_out"))?;
     let head = Vec::<u32>::load_from(path.join("head"))?;
     let first_ipp_of_arc = Vec::<u32>::load_from(path.join("first_ipp_of_arc"))?;
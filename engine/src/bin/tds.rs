@@ -1,13 +1,19 @@
 // Example of complete Time-Dependent Sampling toolchain.
 // Takes a directory as argument, which has to contain the graph (in RoutingKit format) and a nested disection order.
+// Optionally, the number of windows, which will then be clustered from the profiles, and the aggregation (mean, min, max or p<percentile>) can be given.
+// The customized windows are stored in a subdirectory of the graph directory and reused on subsequent runs with the same parameters.
 
-use std::{env, error::Error, path::Path};
+use std::{env, error::Error, fs, path::Path};
 
 use rust_road_router::{
-    algo::{customizable_contraction_hierarchy, time_dependent_sampling::Server, *},
+    algo::{
+        customizable_contraction_hierarchy,
+        time_dependent_sampling::{Server, *},
+        *,
+    },
     cli::CliErr,
     datastr::{graph::time_dependent::*, node_order::NodeOrder},
-    io::Load,
+    io::*,
 };
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -16,6 +22,20 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let arg = &args.next().ok_or(CliErr("No directory arg given"))?;
     let path = Path::new(arg);
+    load_period_config(path)?;
+
+    let num_windows = args.next().map(|arg| arg.parse::<usize>()).transpose()?;
+    let aggregation_arg = args.next().unwrap_or_else(|| "mean".to_string());
+    let aggregation = match aggregation_arg.as_str() {
+        "mean" => Aggregation::Mean,
+        "min" => Aggregation::Min,
+        "max" => Aggregation::Max,
+        percentile if percentile.starts_with('p') => match percentile[1..].parse()? {
+            percentile @ 0..=100 => Aggregation::Percentile(percentile),
+            _ => return Err(Box::new(CliErr("Percentile has to be between 0 and 100"))),
+        },
+        _ => return Err(Box::new(CliErr("Unknown aggregation, expected mean, min, max or p<percentile>"))),
+    };
 
     let first_out = Vec::load_from(path.join("first_out"))?;
     let head = Vec::load_from(path.join("head"))?;
@@ -29,7 +49,30 @@ fn main() -> Result<(), Box<dyn Error>> {
     let cch_order = Vec::load_from(path.join("cch_perm"))?;
 
     let cch = customizable_contraction_hierarchy::contract(&graph, NodeOrder::from_node_order(cch_order));
-    let mut server = Server::new(graph, &cch);
+
+    let samples_dir = path.join(format!(
+        "tds_{}_{}",
+        num_windows.map(|n| n.to_string()).unwrap_or_else(|| "default".to_string()),
+        aggregation_arg
+    ));
+    let samples = if samples_dir.exists() {
+        SamplesReconstructor { cch: &cch }.reconstruct_from(&samples_dir)?
+    } else {
+        let windows = num_windows.map(|n| clustered_windows(&graph, n)).unwrap_or_else(default_windows);
+        let samples = Samples::new(&graph, &cch, &windows, aggregation);
+        // written to a temporary directory first, so an interrupted run does not leave incomplete samples behind
+        let tmp_dir = samples_dir.with_extension("tmp");
+        if tmp_dir.exists() {
+            fs::remove_dir_all(&tmp_dir)?;
+        }
+        fs::create_dir(&tmp_dir)?;
+        samples.deconstruct_to(&tmp_dir)?;
+        fs::rename(&tmp_dir, &samples_dir)?;
+        samples
+    };
+    println!("windows: {:?}", samples.windows());
+
+    let mut server = Server::with_samples(graph, samples);
     println!("{:?}", server.query(TDQuery { from: 0, to: 1, departure: 42 }).map(|res| res.distance()));
 
     Ok(())
@@ -2,6 +2,9 @@
 
 use std::{error::Error, fmt, fmt::Display};
 
+pub mod args;
+pub mod toolbox;
+
 /// An error struct to wrap simple static error messages
 #[derive(Debug)]
 pub struct CliErr(pub &'static str);
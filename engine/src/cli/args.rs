@@ -0,0 +1,206 @@
+//! Parsing of command lines consisting of a sequence of subcommands with positional arguments and named flags.
+//!
+//! Each subcommand is described by a `CommandSpec`.
+//! The command line is split into invocations: a subcommand name, exactly as many positional arguments as the spec demands
+//! and any number of flags, either switches (`--name`) or flags with a value (`--name value`).
+//! Unknown commands or flags, missing arguments and flag values are reported as errors with a hint to `--help`.
+
+use std::{error::Error, fmt::Write, str::FromStr};
+
+/// Description of a subcommand, used for parsing, validation and help texts.
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Names of the positional arguments, shown in the usage
+    pub args: &'static [&'static str],
+    pub flags: &'static [FlagSpec],
+    /// One line summary
+    pub about: &'static str,
+}
+
+#[derive(Debug)]
+pub struct FlagSpec {
+    pub name: &'static str,
+    /// Placeholder for the value in the help text, `None` for switches without values
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+/// A parsed subcommand with its arguments.
+#[derive(Debug)]
+pub struct Invocation<'s> {
+    pub spec: &'s CommandSpec,
+    args: Vec<String>,
+    flags: Vec<(&'static str, Option<String>)>,
+}
+
+/// Result of parsing a command line.
+#[derive(Debug)]
+pub enum Parsed<'s> {
+    Commands(Vec<Invocation<'s>>),
+    /// `--help` was given, contains the help text for the command it followed or the general help
+    Help(String),
+}
+
+/// Split a command line (without the program name) into invocations of the given commands.
+pub fn parse<'s>(program: &str, specs: &'s [CommandSpec], tokens: impl IntoIterator<Item = String>) -> Result<Parsed<'s>, Box<dyn Error>> {
+    let mut invocations: Vec<Invocation<'s>> = Vec::new();
+    let mut tokens = tokens.into_iter();
+
+    while let Some(token) = tokens.next() {
+        if token == "--help" || token == "-h" {
+            return Ok(Parsed::Help(match invocations.last() {
+                Some(invocation) => command_help(program, invocation.spec),
+                None => help(program, specs),
+            }));
+        }
+
+        if token.starts_with("--") {
+            let invocation = invocations
+                .last_mut()
+                .ok_or_else(|| format!("flag {} given before any command, see {} --help", token, program))?;
+            let spec = invocation.spec;
+            let flag = spec
+                .flags
+                .iter()
+                .find(|flag| flag.name == &token[2..])
+                .ok_or_else(|| format!("unknown flag {} for command {}, see {} {} --help", token, spec.name, program, spec.name))?;
+            let value = match flag.value {
+                Some(placeholder) => Some(
+                    tokens
+                        .next()
+                        .ok_or_else(|| format!("missing value {} for flag {} of command {}", placeholder, token, spec.name))?,
+                ),
+                None => None,
+            };
+            invocation.flags.push((flag.name, value));
+            continue;
+        }
+
+        if let Some(invocation) = invocations.last_mut() {
+            if invocation.args.len() < invocation.spec.args.len() {
+                invocation.args.push(token);
+                continue;
+            }
+        }
+
+        let spec = specs
+            .iter()
+            .find(|spec| spec.name == token)
+            .ok_or_else(|| format!("unknown command {}, see {} --help", token, program))?;
+        invocations.push(Invocation {
+            spec,
+            args: Vec::new(),
+            flags: Vec::new(),
+        });
+    }
+
+    for invocation in &invocations {
+        let spec = invocation.spec;
+        if invocation.args.len() < spec.args.len() {
+            return Err(format!(
+                "missing argument {} for command {}, see {} {} --help",
+                spec.args[invocation.args.len()],
+                spec.name,
+                program,
+                spec.name
+            )
+            .into());
+        }
+    }
+
+    Ok(Parsed::Commands(invocations))
+}
+
+impl<'s> Invocation<'s> {
+    /// The i-th positional argument
+    pub fn arg(&self, idx: usize) -> &str {
+        &self.args[idx]
+    }
+
+    /// Whether the switch was given
+    pub fn switch(&self, name: &str) -> bool {
+        self.flags.iter().any(|(flag, _)| *flag == name)
+    }
+
+    /// The parsed value of the flag, if it was given (the last one, if given multiple times).
+    pub fn value<T: FromStr>(&self, name: &str) -> Result<Option<T>, Box<dyn Error>> {
+        match self.flags.iter().rev().find(|(flag, _)| *flag == name) {
+            Some((_, Some(value))) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value {} for flag --{} of command {}", value, name, self.spec.name).into()),
+            _ => Ok(None),
+        }
+    }
+
+    /// Like `value` but fails if the flag was not given
+    pub fn required<T: FromStr>(&self, name: &str) -> Result<T, Box<dyn Error>> {
+        self.value(name)?
+            .ok_or_else(|| format!("missing flag --{} for command {}", name, self.spec.name).into())
+    }
+
+    /// Parse a comma separated list of values, for example a bounding box.
+    pub fn list<T: FromStr>(&self, name: &str, len: usize) -> Result<Option<Vec<T>>, Box<dyn Error>> {
+        match self.value::<String>(name)? {
+            Some(value) => value
+                .split(',')
+                .map(|v| v.trim().parse::<T>())
+                .collect::<Result<Vec<T>, _>>()
+                .ok()
+                .filter(|values| values.len() == len)
+                .map(Some)
+                .ok_or_else(|| format!("expected {} comma separated values for flag --{} of command {}", len, name, self.spec.name).into()),
+            None => Ok(None),
+        }
+    }
+}
+
+fn usage(spec: &CommandSpec) -> String {
+    let mut usage = spec.name.to_string();
+    for arg in spec.args {
+        usage.push(' ');
+        usage.push_str(arg);
+    }
+    if !spec.flags.is_empty() {
+        usage.push_str(" [FLAGS]");
+    }
+    usage
+}
+
+/// General help listing all commands
+pub fn help(program: &str, specs: &[CommandSpec]) -> String {
+    let mut help = String::new();
+    writeln!(help, "Usage: {} COMMAND [ARGS] [FLAGS] [COMMAND [ARGS] [FLAGS] ...]", program).unwrap();
+    writeln!(help).unwrap();
+    writeln!(help, "Commands are executed one after another.").unwrap();
+    writeln!(help, "Use {} COMMAND --help for details on a command.", program).unwrap();
+    writeln!(help).unwrap();
+    writeln!(help, "Commands:").unwrap();
+    let width = specs.iter().map(|spec| usage(spec).len()).max().unwrap_or(0);
+    for spec in specs {
+        writeln!(help, "  {:width$}  {}", usage(spec), spec.about, width = width).unwrap();
+    }
+    help
+}
+
+/// Help for a single command
+pub fn command_help(program: &str, spec: &CommandSpec) -> String {
+    let mut help = String::new();
+    writeln!(help, "Usage: {} {}", program, usage(spec)).unwrap();
+    writeln!(help).unwrap();
+    writeln!(help, "{}", spec.about).unwrap();
+    if !spec.flags.is_empty() {
+        writeln!(help).unwrap();
+        writeln!(help, "Flags:").unwrap();
+        let flag_usage = |flag: &FlagSpec| match flag.value {
+            Some(value) => format!("--{} {}", flag.name, value),
+            None => format!("--{}", flag.name),
+        };
+        let width = spec.flags.iter().map(|flag| flag_usage(flag).len()).max().unwrap_or(0);
+        for flag in spec.flags {
+            writeln!(help, "  {:width$}  {}", flag_usage(flag), flag.help, width = width).unwrap();
+        }
+    }
+    help
+}
@@ -0,0 +1,842 @@
+//! Graph processing toolbox behind the `rrr` binary.
+//!
+//! Graph commands work on an in-memory copy of a graph directory which is loaded with `read` and stored with `write`,
+//! so several processing steps can be chained without writing intermediate results, for example
+//! `rrr read in subgraph --bbox 48.9,8.3,49.1,8.5 largest-scc reorder --by hilbert write out`.
+//! Besides `first_out` and `head`, the known per-node and per-arc attributes, the time-dependent profiles and the period configuration
+//! are carried through all steps.
+//! Other files of the input directory, for example node orders, are not, since they would be invalid after most steps.
+
+use super::args::*;
+use crate::{
+    algo::{customizable_contraction_hierarchy::contract, node_reordering::*},
+    datastr::{
+        graph::{cleanup::largest_strongly_connected_component_nodes, time_dependent::*, *},
+        node_order::NodeOrder,
+    },
+    export::*,
+    import::{dimacs, time_dependent::*},
+    io::*,
+};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    mem,
+    path::Path,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const PROGRAM: &str = "rrr";
+
+/// Per node attributes carried through all graph commands
+pub const NODE_ATTRIBUTES: [&str; 3] = ["latitude", "longitude", "osm_node_ids"];
+/// Per arc attributes carried through all graph commands
+pub const ARC_ATTRIBUTES: [&str; 4] = ["travel_time", "geo_distance", "lower_bound", "arc_category"];
+const PERIOD_FILES: [&str; 2] = ["period", "period_start"];
+const PROFILE_FILES: [&str; 3] = ["first_ipp_of_arc", "ipp_departure_time", "ipp_travel_time"];
+const GRAPH_FILES: [&str; 3] = ["first_out", "head", "tail"];
+
+const BBOX_FLAG: FlagSpec = FlagSpec {
+    name: "bbox",
+    value: Some("MIN_LAT,MIN_LNG,MAX_LAT,MAX_LNG"),
+    help: "geographic bounding box in degrees",
+};
+
+pub static COMMANDS: [CommandSpec; 15] = [
+    CommandSpec {
+        name: "read",
+        args: &["DIR"],
+        flags: &[],
+        about: "Load a graph directory in the RoutingKit format, all following graph commands work on it",
+    },
+    CommandSpec {
+        name: "read-dimacs",
+        args: &["PREFIX"],
+        flags: &[FlagSpec {
+            name: "weight",
+            value: Some("ATTRIBUTE"),
+            help: "arc attribute for the weights, default travel_time",
+        }],
+        about: "Load the graph from PREFIX.gr and the coordinates from PREFIX.co, if it exists, in the DIMACS format",
+    },
+    CommandSpec {
+        name: "read-tpgr",
+        args: &["FILE"],
+        flags: &[],
+        about: "Load a time-dependent graph in the tpgr text format used by KaTCH",
+    },
+    CommandSpec {
+        name: "read-tbgr",
+        args: &["FILE"],
+        flags: &[],
+        about: "Load a time-dependent graph in the binary tbgr format used by TD-CRP",
+    },
+    CommandSpec {
+        name: "write",
+        args: &["DIR"],
+        flags: &[FlagSpec {
+            name: "tail",
+            value: None,
+            help: "additionally write the tail node of each arc",
+        }],
+        about: "Store the current graph into a directory, which will be created if necessary. Graph files left by a previous graph are removed, other files are kept unless the node ids changed, then writing fails",
+    },
+    CommandSpec {
+        name: "subgraph",
+        args: &[],
+        flags: &[BBOX_FLAG],
+        about: "Keep only the nodes within a bounding box",
+    },
+    CommandSpec {
+        name: "largest-scc",
+        args: &[],
+        flags: &[],
+        about: "Keep only the largest strongly connected component",
+    },
+    CommandSpec {
+        name: "reorder",
+        args: &[],
+        flags: &[
+            FlagSpec {
+                name: "order",
+                value: Some("FILE"),
+                help: "permutation file, listing the node ids in their new order",
+            },
+            FlagSpec {
+                name: "ranks",
+                value: None,
+                help: "the file contains the new id for each node instead",
+            },
+            FlagSpec {
+                name: "by",
+                value: Some("METHOD"),
+                help: "compute a locality preserving order instead: dfs, bfs, hilbert or separator (the --order file is a nested dissection order)",
+            },
+            FlagSpec {
+                name: "mapping",
+                value: Some("FILE"),
+                help: "store the previous id of each node, to map results back",
+            },
+        ],
+        about: "Renumber the nodes of the graph",
+    },
+    CommandSpec {
+        name: "lower-bound",
+        args: &[],
+        flags: &[],
+        about: "Add the lower bounds of the travel time profiles as lower_bound arc attribute",
+    },
+    CommandSpec {
+        name: "export-dimacs",
+        args: &["PREFIX"],
+        flags: &[FlagSpec {
+            name: "weight",
+            value: Some("ATTRIBUTE"),
+            help: "arc attribute to export as weights, default travel_time",
+        }],
+        about: "Write the graph as PREFIX.gr and the coordinates as PREFIX.co in the DIMACS format",
+    },
+    CommandSpec {
+        name: "export-tbgr",
+        args: &["FILE"],
+        flags: &[],
+        about: "Write the time-dependent graph in the binary tbgr format used by TD-CRP",
+    },
+    CommandSpec {
+        name: "export-svg",
+        args: &["FILE"],
+        flags: &[BBOX_FLAG],
+        about: "Plot the graph, or the part within a bounding box, to an SVG file",
+    },
+    CommandSpec {
+        name: "info",
+        args: &[],
+        flags: &[],
+        about: "Print the size and the attributes of the current graph",
+    },
+    CommandSpec {
+        name: "invert-order",
+        args: &["INPUT", "OUTPUT"],
+        flags: &[],
+        about: "Turn a node order file into a ranking or vice versa",
+    },
+    CommandSpec {
+        name: "scale-tpgr",
+        args: &["INPUT", "OUTPUT"],
+        flags: &[FlagSpec {
+            name: "factor",
+            value: Some("FACTOR"),
+            help: "factor for all time values including the period, default 10 (KaTCH uses tenths of seconds)",
+        }],
+        about: "Scale the weights of a tpgr text file",
+    },
+];
+
+/// Parse and execute a command line (without the program name).
+pub fn run(tokens: impl IntoIterator<Item = String>) -> Result<()> {
+    let invocations = match parse(PROGRAM, &COMMANDS, tokens)? {
+        Parsed::Help(help) => {
+            print!("{}", help);
+            return Ok(());
+        }
+        Parsed::Commands(invocations) => invocations,
+    };
+
+    if invocations.is_empty() {
+        print!("{}", help(PROGRAM, &COMMANDS));
+        return Err(format!("no command given, see {} --help", PROGRAM).into());
+    }
+
+    // validate the whole pipeline before doing any work
+    let mut loaded = false;
+    for invocation in &invocations {
+        match invocation.spec.name {
+            "read" | "read-dimacs" | "read-tpgr" | "read-tbgr" => loaded = true,
+            "invert-order" | "scale-tpgr" => (),
+            name if !loaded => return Err(format!("command {} needs a graph, start with read DIR or one of the importers", name).into()),
+            _ => (),
+        }
+    }
+
+    let mut graph = None;
+    for invocation in &invocations {
+        execute(invocation, &mut graph)?;
+    }
+
+    Ok(())
+}
+
+fn execute(invocation: &Invocation, current: &mut Option<GraphData>) -> Result<()> {
+    let read = match invocation.spec.name {
+        "read" => Some(GraphData::load(Path::new(invocation.arg(0)))?),
+        "read-dimacs" => {
+            let prefix = Path::new(invocation.arg(0));
+            let weight = invocation.value::<String>("weight")?.unwrap_or_else(|| "travel_time".to_string());
+            let weight = ARC_ATTRIBUTES
+                .iter()
+                .find(|&&name| name == weight)
+                .ok_or_else(|| format!("unknown arc attribute {}, expected one of {}", weight, ARC_ATTRIBUTES.join(", ")))?;
+            let graph = dimacs::read_graph(prefix.with_extension("gr"))?;
+            let coords = if prefix.with_extension("co").exists() {
+                Some(dimacs::read_coords(prefix.with_extension("co"))?)
+            } else {
+                None
+            };
+            Some(GraphData::from_dimacs(graph, weight, coords)?)
+        }
+        "read-tpgr" => Some(GraphData::from_td(read_tpgr(invocation.arg(0))?)),
+        "read-tbgr" => Some(GraphData::from_td(read_tbgr(invocation.arg(0))?)),
+        _ => None,
+    };
+    if let Some(graph) = read {
+        println!("read {} nodes and {} arcs", graph.num_nodes(), graph.num_arcs());
+        *current = Some(graph);
+        return Ok(());
+    }
+
+    match invocation.spec.name {
+        "invert-order" => {
+            let ranks = Vec::<NodeId>::load_from(invocation.arg(0))?;
+            if !is_permutation(&ranks) {
+                return Err(format!("{} is not a permutation", invocation.arg(0)).into());
+            }
+            let order = NodeOrder::from_ranks(ranks);
+            order.order().write_to(&invocation.arg(1))?;
+            return Ok(());
+        }
+        "scale-tpgr" => {
+            let factor = invocation.value("factor")?.unwrap_or(10.0);
+            return scale_tpgr(Path::new(invocation.arg(0)), Path::new(invocation.arg(1)), factor);
+        }
+        _ => (),
+    }
+
+    // the pipeline was validated, so there is a graph
+    let graph = current.as_mut().unwrap();
+    match invocation.spec.name {
+        "write" => graph.write(Path::new(invocation.arg(0)), invocation.switch("tail"))?,
+        "subgraph" => {
+            let bbox = invocation.list("bbox", 4)?.ok_or("missing flag --bbox for command subgraph")?;
+            *graph = graph.subgraph_in_bounding_box(bounding_box(&bbox)?)?;
+            println!("subgraph with {} nodes and {} arcs", graph.num_nodes(), graph.num_arcs());
+        }
+        "largest-scc" => {
+            *graph = graph.largest_scc();
+            println!("largest scc with {} nodes and {} arcs", graph.num_nodes(), graph.num_arcs());
+        }
+        "reorder" => {
+            let topology = UnweightedFirstOutGraph::new(&graph.first_out[..], &graph.head[..]);
+            let method = invocation.value::<String>("by")?;
+            if let Some(method) = method.as_deref().filter(|&method| method != "separator") {
+                if invocation.value::<String>("order")?.is_some() || invocation.switch("ranks") {
+                    return Err(format!("reorder --by {} computes the order itself, --order and --ranks do not apply", method).into());
+                }
+            }
+            let order = match method.as_deref() {
+                None => load_order(invocation, graph.num_nodes())?,
+                Some("dfs") => dfs_order(&topology),
+                Some("bfs") => bfs_order(&topology),
+                Some("hilbert") => hilbert_order(&graph.node_attribute("latitude")?, &graph.node_attribute("longitude")?),
+                Some("separator") => separator_order(&contract(&topology, load_order(invocation, graph.num_nodes())?)),
+                Some(method) => return Err(format!("unknown order {}, expected one of dfs, bfs, hilbert, separator", method).into()),
+            };
+            if let Some(mapping) = invocation.value::<String>("mapping")? {
+                order.order().write_to(&mapping)?;
+            }
+            *graph = graph.induced_subgraph(order.order());
+        }
+        "lower-bound" => graph.add_lower_bound()?,
+        "export-dimacs" => {
+            let prefix = Path::new(invocation.arg(0));
+            let weight = invocation.value("weight")?.unwrap_or_else(|| "travel_time".to_string());
+            let weights = graph.arc_attribute::<Weight>(&weight)?;
+            let exported = FirstOutGraph::new(&graph.first_out[..], &graph.head[..], &weights[..]);
+            write_graph_to_gr(&exported, prefix.with_extension("gr").to_str().ok_or("invalid output path")?)?;
+            if graph.has_node_attribute("latitude") && graph.has_node_attribute("longitude") {
+                let lat = graph.node_attribute::<f32>("latitude")?;
+                let lng = graph.node_attribute::<f32>("longitude")?;
+                write_coords_to_co(&lat, &lng, prefix.with_extension("co").to_str().ok_or("invalid output path")?)?;
+            }
+        }
+        "export-tbgr" => graph.write_tbgr(Path::new(invocation.arg(0)))?,
+        "export-svg" => {
+            let bbox = match invocation.list("bbox", 4)? {
+                Some(bbox) => Some(bounding_box(&bbox)?),
+                None => None,
+            };
+            graph.write_svg(Path::new(invocation.arg(0)), bbox)?;
+        }
+        "info" => graph.print_info(),
+        name => unreachable!("command {} not handled", name),
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BoundingBox {
+    min_lat: f32,
+    min_lng: f32,
+    max_lat: f32,
+    max_lng: f32,
+}
+
+impl BoundingBox {
+    fn contains(&self, lat: f32, lng: f32) -> bool {
+        lat >= self.min_lat && lat <= self.max_lat && lng >= self.min_lng && lng <= self.max_lng
+    }
+}
+
+fn bounding_box(values: &[f32]) -> Result<BoundingBox> {
+    let bbox = BoundingBox {
+        min_lat: values[0],
+        min_lng: values[1],
+        max_lat: values[2],
+        max_lng: values[3],
+    };
+    if !(bbox.min_lat <= bbox.max_lat && bbox.min_lng <= bbox.max_lng) {
+        return Err("bounding box minimum has to be smaller than the maximum".into());
+    }
+    Ok(bbox)
+}
+
+// The --order file of the reorder command
+fn load_order(invocation: &Invocation, num_nodes: usize) -> Result<NodeOrder> {
+    let order = Vec::<NodeId>::load_from(invocation.required::<String>("order")?)?;
+    if order.len() != num_nodes {
+        return Err(format!("order has {} entries, but the graph has {} nodes", order.len(), num_nodes).into());
+    }
+    if !is_permutation(&order) {
+        return Err("order is not a permutation".into());
+    }
+    Ok(if invocation.switch("ranks") {
+        NodeOrder::from_ranks(order)
+    } else {
+        NodeOrder::from_node_order(order)
+    })
+}
+
+// Files and subdirectories which are not managed by `GraphData`, sorted by name
+fn other_files(dir: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        let managed = [&GRAPH_FILES[..], &NODE_ATTRIBUTES, &ARC_ATTRIBUTES, &PROFILE_FILES, &PERIOD_FILES]
+            .iter()
+            .any(|files| files.contains(&name.as_str()));
+        if !managed {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn is_permutation(order: &[NodeId]) -> bool {
+    let mut seen = vec![false; order.len()];
+    for &node in order {
+        match seen.get_mut(node as usize) {
+            Some(seen) if !*seen => *seen = true,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Raw data of an attribute file, with elements of `width` bytes, independent of the actual type.
+#[derive(Debug, Clone)]
+struct Attribute {
+    name: &'static str,
+    width: usize,
+    bytes: Vec<u8>,
+}
+
+impl Attribute {
+    fn new<T: Copy>(name: &'static str, data: &[T]) -> Attribute {
+        Attribute {
+            name,
+            width: mem::size_of::<T>(),
+            bytes: data.data_bytes().to_vec(),
+        }
+    }
+
+    fn load(dir: &Path, name: &'static str, num_elements: usize) -> Result<Option<Attribute>> {
+        let path = dir.join(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&path)?;
+        let width = bytes.len().checked_div(num_elements).unwrap_or(0);
+        if width * num_elements != bytes.len() || (num_elements > 0 && width == 0) {
+            return Err(format!("{} has {} bytes, which does not fit {} elements", path.display(), bytes.len(), num_elements).into());
+        }
+        Ok(Some(Attribute { name, width, bytes }))
+    }
+
+    fn select(&self, indices: impl Iterator<Item = usize>) -> Attribute {
+        let mut bytes = Vec::new();
+        for idx in indices {
+            bytes.extend_from_slice(&self.bytes[idx * self.width..(idx + 1) * self.width]);
+        }
+        Attribute { bytes, ..*self }
+    }
+
+    fn to_vec<T: Default + Copy>(&self) -> Result<Vec<T>> {
+        if self.width != mem::size_of::<T>() && !self.bytes.is_empty() {
+            return Err(format!("{} has elements of {} bytes, expected {}", self.name, self.width, mem::size_of::<T>()).into());
+        }
+        let mut data = Vec::<T>::new_with_bytes(self.bytes.len());
+        data.data_bytes_mut().copy_from_slice(&self.bytes);
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Profiles {
+    first_ipp_of_arc: Vec<u32>,
+    ipp_departure_time: Vec<Timestamp>,
+    ipp_travel_time: Vec<Weight>,
+}
+
+/// In-memory copy of a graph directory.
+#[derive(Debug, Clone)]
+pub struct GraphData {
+    first_out: Vec<EdgeId>,
+    head: Vec<NodeId>,
+    node_attributes: Vec<Attribute>,
+    arc_attributes: Vec<Attribute>,
+    profiles: Option<Profiles>,
+    // copied unchanged
+    period_files: Vec<(&'static str, Vec<u8>)>,
+    // other files of the loaded directory, they only match the graph as long as no ids change
+    uncarried_files: Vec<String>,
+    // whether node or arc ids changed since the graph was read
+    renumbered: bool,
+}
+
+impl GraphData {
+    /// Load the graph and all known attributes from a directory and validate their sizes.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let graph_dir = GraphDirectory::open(dir)?;
+        let first_out = graph_dir.first_out()?.to_vec();
+        let head = graph_dir.head()?.to_vec();
+        let num_nodes = first_out.len() - 1;
+
+        let mut node_attributes = Vec::new();
+        for &name in &NODE_ATTRIBUTES {
+            node_attributes.extend(Attribute::load(dir, name, num_nodes)?);
+        }
+        let mut arc_attributes = Vec::new();
+        for &name in &ARC_ATTRIBUTES {
+            arc_attributes.extend(Attribute::load(dir, name, head.len())?);
+        }
+
+        let mut period_files = Vec::new();
+        for &name in &PERIOD_FILES {
+            if dir.join(name).exists() {
+                period_files.push((name, fs::read(dir.join(name))?));
+            }
+        }
+
+        let profiles = if graph_dir.has("first_ipp_of_arc") {
+            load_period_config(dir)?;
+            Some(Profiles {
+                first_ipp_of_arc: graph_dir.first_ipp_of_arc()?.to_vec(),
+                ipp_departure_time: graph_dir.ipp_departure_time()?.to_vec(),
+                ipp_travel_time: graph_dir.ipp_travel_time()?.to_vec(),
+            })
+        } else {
+            None
+        };
+
+        Ok(GraphData {
+            first_out,
+            head,
+            node_attributes,
+            arc_attributes,
+            profiles,
+            period_files,
+            uncarried_files: other_files(dir)?,
+            renumbered: false,
+        })
+    }
+
+    fn from_dimacs(graph: OwnedGraph, weight: &'static str, coords: Option<(Vec<f32>, Vec<f32>)>) -> Result<Self> {
+        let (first_out, head, weights) = graph.decompose();
+        let mut node_attributes = Vec::new();
+        if let Some((lat, lng)) = coords {
+            if lat.len() != first_out.len() - 1 {
+                return Err(format!("coordinates for {} nodes given, but the graph has {} nodes", lat.len(), first_out.len() - 1).into());
+            }
+            node_attributes.push(Attribute::new("latitude", &lat));
+            node_attributes.push(Attribute::new("longitude", &lng));
+        }
+        Ok(GraphData {
+            first_out,
+            head,
+            node_attributes,
+            arc_attributes: vec![Attribute::new(weight, &weights)],
+            profiles: None,
+            period_files: Vec::new(),
+            uncarried_files: Vec::new(),
+            renumbered: false,
+        })
+    }
+
+    // Also configures the period of the imported graph for following commands.
+    fn from_td(data: TDGraphData) -> Self {
+        configure_period(data.period);
+        GraphData {
+            first_out: data.first_out,
+            head: data.head,
+            node_attributes: Vec::new(),
+            arc_attributes: Vec::new(),
+            profiles: Some(Profiles {
+                first_ipp_of_arc: data.first_ipp_of_arc,
+                ipp_departure_time: data.ipp_departure_time,
+                ipp_travel_time: data.ipp_travel_time,
+            }),
+            period_files: vec![("period", vec![data.period].data_bytes().to_vec())],
+            uncarried_files: Vec::new(),
+            renumbered: false,
+        }
+    }
+
+    /// Store the graph and all attributes into a directory.
+    /// Other files already in the directory are kept, unless the node ids changed, then writing fails.
+    pub fn write(&self, dir: &Path, with_tail: bool) -> Result<()> {
+        if self.renumbered && dir.exists() {
+            let stale = other_files(dir)?;
+            if !stale.is_empty() {
+                return Err(format!(
+                    "{} contains files which would not match the renumbered graph: {}, remove them or write to another directory",
+                    dir.display(),
+                    stale.join(", ")
+                )
+                .into());
+            }
+        }
+        for name in &self.uncarried_files {
+            if !dir.join(name).exists() {
+                eprintln!("warning: {} is not carried over to {}", name, dir.display());
+            }
+        }
+
+        fs::create_dir_all(dir)?;
+        // attributes this graph does not have would otherwise be mixed up with the new graph
+        for name in GRAPH_FILES
+            .iter()
+            .chain(&NODE_ATTRIBUTES)
+            .chain(&ARC_ATTRIBUTES)
+            .chain(&PROFILE_FILES)
+            .chain(&PERIOD_FILES)
+        {
+            if dir.join(name).exists() {
+                fs::remove_file(dir.join(name))?;
+            }
+        }
+        self.first_out.write_to(&dir.join("first_out"))?;
+        self.head.write_to(&dir.join("head"))?;
+        for attribute in self.node_attributes.iter().chain(self.arc_attributes.iter()) {
+            attribute.bytes.write_to(&dir.join(attribute.name))?;
+        }
+        if let Some(profiles) = &self.profiles {
+            profiles.first_ipp_of_arc.write_to(&dir.join("first_ipp_of_arc"))?;
+            profiles.ipp_departure_time.write_to(&dir.join("ipp_departure_time"))?;
+            profiles.ipp_travel_time.write_to(&dir.join("ipp_travel_time"))?;
+        }
+        for (name, bytes) in &self.period_files {
+            bytes.write_to(&dir.join(name))?;
+        }
+        if with_tail {
+            let mut tail = Vec::with_capacity(self.num_arcs());
+            for node in 0..self.num_nodes() {
+                for _ in self.first_out[node]..self.first_out[node + 1] {
+                    tail.push(node as NodeId);
+                }
+            }
+            tail.write_to(&dir.join("tail"))?;
+        }
+        Ok(())
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.first_out.len() - 1
+    }
+
+    pub fn num_arcs(&self) -> usize {
+        self.head.len()
+    }
+
+    pub fn has_node_attribute(&self, name: &str) -> bool {
+        self.node_attributes.iter().any(|attribute| attribute.name == name)
+    }
+
+    pub fn node_attribute<T: Default + Copy>(&self, name: &str) -> Result<Vec<T>> {
+        self.node_attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .ok_or_else(|| format!("graph has no node attribute {}", name))?
+            .to_vec()
+    }
+
+    pub fn arc_attribute<T: Default + Copy>(&self, name: &str) -> Result<Vec<T>> {
+        self.arc_attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .ok_or_else(|| format!("graph has no arc attribute {}", name))?
+            .to_vec()
+    }
+
+    /// The subgraph induced by the given nodes, where node `new_to_old[i]` becomes node `i`.
+    /// Arcs keep their relative order, attributes and profiles are moved along.
+    pub fn induced_subgraph(&self, new_to_old: &[NodeId]) -> Self {
+        let mut old_to_new = vec![None; self.num_nodes()];
+        for (new, &old) in new_to_old.iter().enumerate() {
+            old_to_new[old as usize] = Some(new as NodeId);
+        }
+
+        let mut first_out = Vec::with_capacity(new_to_old.len() + 1);
+        first_out.push(0);
+        let mut head = Vec::new();
+        let mut new_to_old_arc = Vec::new();
+        for &old in new_to_old {
+            for arc in self.first_out[old as usize]..self.first_out[old as usize + 1] {
+                if let Some(new_head) = old_to_new[self.head[arc as usize] as usize] {
+                    head.push(new_head);
+                    new_to_old_arc.push(arc as usize);
+                }
+            }
+            first_out.push(head.len() as EdgeId);
+        }
+
+        let profiles = self.profiles.as_ref().map(|profiles| {
+            let mut first_ipp_of_arc = Vec::with_capacity(new_to_old_arc.len() + 1);
+            first_ipp_of_arc.push(0);
+            let mut ipp_departure_time = Vec::new();
+            let mut ipp_travel_time = Vec::new();
+            for &arc in &new_to_old_arc {
+                let ipps = profiles.first_ipp_of_arc[arc] as usize..profiles.first_ipp_of_arc[arc + 1] as usize;
+                ipp_departure_time.extend_from_slice(&profiles.ipp_departure_time[ipps.clone()]);
+                ipp_travel_time.extend_from_slice(&profiles.ipp_travel_time[ipps]);
+                first_ipp_of_arc.push(ipp_departure_time.len() as u32);
+            }
+            Profiles {
+                first_ipp_of_arc,
+                ipp_departure_time,
+                ipp_travel_time,
+            }
+        });
+
+        GraphData {
+            first_out,
+            head,
+            node_attributes: self
+                .node_attributes
+                .iter()
+                .map(|attribute| attribute.select(new_to_old.iter().map(|&node| node as usize)))
+                .collect(),
+            arc_attributes: self
+                .arc_attributes
+                .iter()
+                .map(|attribute| attribute.select(new_to_old_arc.iter().cloned()))
+                .collect(),
+            profiles,
+            period_files: self.period_files.clone(),
+            uncarried_files: self.uncarried_files.clone(),
+            renumbered: true,
+        }
+    }
+
+    fn subgraph_in_bounding_box(&self, bbox: BoundingBox) -> Result<Self> {
+        let lat = self.node_attribute::<f32>("latitude")?;
+        let lng = self.node_attribute::<f32>("longitude")?;
+        let nodes: Vec<NodeId> = (0..self.num_nodes())
+            .filter(|&node| bbox.contains(lat[node], lng[node]))
+            .map(|node| node as NodeId)
+            .collect();
+        Ok(self.induced_subgraph(&nodes))
+    }
+
+    /// The subgraph induced by the largest strongly connected component, node ids keep their relative order.
+    pub fn largest_scc(&self) -> Self {
+        let nodes = largest_strongly_connected_component_nodes(&UnweightedFirstOutGraph::new(&self.first_out[..], &self.head[..]));
+        self.induced_subgraph(&nodes)
+    }
+
+    fn add_lower_bound(&mut self) -> Result<()> {
+        let profiles = self.profiles.as_ref().ok_or("graph has no travel time profiles")?;
+        let graph = TDGraph::new(
+            self.first_out.clone(),
+            self.head.clone(),
+            profiles.first_ipp_of_arc.clone(),
+            profiles.ipp_departure_time.clone(),
+            profiles.ipp_travel_time.clone(),
+        );
+        let lower_bound: Vec<Weight> = (0..graph.num_arcs() as EdgeId)
+            .map(|edge_id| graph.travel_time_function(edge_id).lower_bound())
+            .collect();
+
+        self.arc_attributes.retain(|attribute| attribute.name != "lower_bound");
+        self.arc_attributes.push(Attribute::new("lower_bound", &lower_bound));
+        Ok(())
+    }
+
+    fn write_tbgr(&self, file: &Path) -> Result<()> {
+        let profiles = self.profiles.as_ref().ok_or("graph has no travel time profiles")?;
+        let mut output = BufWriter::new(File::create(file)?);
+
+        output.write_all(&2i32.to_ne_bytes())?;
+        output.write_all(&(self.num_nodes() as u32).to_ne_bytes())?;
+        output.write_all(&(self.num_arcs() as u32).to_ne_bytes())?;
+        output.write_all(&(profiles.ipp_departure_time.len() as u32).to_ne_bytes())?;
+        output.write_all(&(period() as i32).to_ne_bytes())?;
+
+        for node in &self.first_out[..self.num_nodes()] {
+            output.write_all(&node.to_ne_bytes())?;
+        }
+
+        for (head, first_ipp) in self.head.iter().zip(profiles.first_ipp_of_arc.iter()) {
+            output.write_all(&head.to_ne_bytes())?;
+            output.write_all(&first_ipp.to_ne_bytes())?;
+        }
+
+        for (&dept, &tt) in profiles.ipp_departure_time.iter().zip(profiles.ipp_travel_time.iter()) {
+            output.write_all(&(dept as i32).to_ne_bytes())?;
+            output.write_all(&(tt as i32).to_ne_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn write_svg(&self, file: &Path, bbox: Option<BoundingBox>) -> Result<()> {
+        let lat = self.node_attribute::<f32>("latitude")?;
+        let lng = self.node_attribute::<f32>("longitude")?;
+        let bbox = bbox.unwrap_or_else(|| BoundingBox {
+            min_lat: lat.iter().cloned().fold(f32::INFINITY, f32::min),
+            min_lng: lng.iter().cloned().fold(f32::INFINITY, f32::min),
+            max_lat: lat.iter().cloned().fold(f32::NEG_INFINITY, f32::max),
+            max_lng: lng.iter().cloned().fold(f32::NEG_INFINITY, f32::max),
+        });
+        let in_bounding_box = |node: usize| bbox.contains(lat[node], lng[node]);
+
+        let mut output = BufWriter::new(File::create(file)?);
+        writeln!(output, "<svg version=\"1.1\" xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" viewBox=\"{} {} {} {}\" style=\"transform: scale(1,-1);\" preserveAspectRatio=\"none\">", bbox.min_lng, bbox.min_lat, bbox.max_lng - bbox.min_lng, bbox.max_lat - bbox.min_lat)?;
+        writeln!(output, "<g>")?;
+
+        for node in 0..self.num_nodes() {
+            if in_bounding_box(node) {
+                writeln!(
+                    output,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" class=\"node\" />",
+                    lng[node], lat[node], lng[node], lat[node]
+                )?;
+                for &head in &self.head[self.first_out[node] as usize..self.first_out[node + 1] as usize] {
+                    let head = head as usize;
+                    if in_bounding_box(head) {
+                        writeln!(
+                            output,
+                            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" class=\"arc\" />",
+                            lng[node], lat[node], lng[head], lat[head]
+                        )?;
+                    }
+                }
+            }
+        }
+
+        writeln!(output, "</g>")?;
+        writeln!(output, "</svg>")?;
+        Ok(())
+    }
+
+    fn print_info(&self) {
+        println!("nodes: {}", self.num_nodes());
+        println!("arcs: {}", self.num_arcs());
+        for attribute in &self.node_attributes {
+            println!("node attribute {} with {} bytes per node", attribute.name, attribute.width);
+        }
+        for attribute in &self.arc_attributes {
+            println!("arc attribute {} with {} bytes per arc", attribute.name, attribute.width);
+        }
+        if let Some(profiles) = &self.profiles {
+            println!("travel time profiles with {} points, period {}", profiles.ipp_departure_time.len(), period());
+        }
+    }
+}
+
+fn scale_tpgr(input: &Path, output: &Path, factor: f64) -> Result<()> {
+    let mut lines = BufReader::new(File::open(input)?).lines();
+    let mut output = BufWriter::new(File::create(output)?);
+
+    // the period in the header has to be scaled along with the profiles
+    let header = lines.next().ok_or("empty tpgr file")??;
+    let invalid_header = || format!("invalid tpgr header, expected NODES ARCS INTERPOLATION_POINTS PERIOD: {}", header);
+    let words: Vec<&str> = header.split_whitespace().collect();
+    if words.len() != 4 || words[..3].iter().any(|word| word.parse::<usize>().is_err()) {
+        return Err(invalid_header().into());
+    }
+    let period = words[3].parse::<f64>().map_err(|_| invalid_header())?;
+    writeln!(output, "{} {} {} {}", words[0], words[1], words[2], period * factor)?;
+
+    for line in lines {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let mut next_word = || words.next().ok_or_else(|| format!("invalid tpgr line: {}", line));
+        let tail = next_word()?;
+        let head = next_word()?;
+        let ipp_count = next_word()?;
+
+        write!(output, "{} {} {}", tail, head, ipp_count)?;
+
+        for num in words {
+            let num = num.parse::<f64>()? * factor;
+            write!(output, " {}", num)?;
+        }
+
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 //! Data structures used by algorithms.
 
+pub mod bucket_queue;
 pub mod clearlist_vector;
 pub mod graph;
 pub mod index_heap;
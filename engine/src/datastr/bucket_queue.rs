@@ -0,0 +1,362 @@
+//! Monotone priority queues for integer keys.
+//!
+//! Both queues only support keys which are not smaller than the key of the last popped element.
+//! This is the case for Dijkstra's algorithm with non-negative weights and consistent potentials.
+//! Elements are sorted into buckets by the digits in which their key differs from the key of the last popped element, so elements are (almost) never compared.
+//! When a bucket gets popped which contains different keys, its elements are redistributed into the buckets below.
+//!
+//! `MultiLevelBucketQueue` uses digits of eight bits and thus four levels of 256 buckets each.
+//! `RadixHeap` is the same with digits of a single bit, so there is one bucket for each bit in which keys can differ from the minimum.
+//!
+//! # Examples
+//!
+//! ```
+//! use rust_road_router::datastr::{bucket_queue::*, index_heap::*};
+//!
+//! #[derive(Copy, Clone, Eq, PartialEq, Debug, Ord, PartialOrd)]
+//! pub struct State {
+//!     pub distance: u32,
+//!     pub node: usize,
+//! }
+//!
+//! impl Indexing for State {
+//!     fn as_index(&self) -> usize {
+//!         self.node
+//!     }
+//! }
+//!
+//! impl IntegerKey for State {
+//!     fn integer_key(&self) -> u32 {
+//!         self.distance
+//!     }
+//! }
+//!
+//! let mut queue = RadixHeap::new(3);
+//! queue.push(State { node: 0, distance: 42 });
+//! queue.push(State { node: 1, distance: 23 });
+//! queue.push(State { node: 2, distance: 50000 });
+//! assert_eq!(queue.pop(), Some(State { node: 1, distance: 23 }));
+//! queue.decrease_key(State { node: 2, distance: 30 });
+//! assert_eq!(queue.pop(), Some(State { node: 2, distance: 30 }));
+//! ```
+
+use super::index_heap::*;
+
+/// Elements of the integer queues need to provide their key as an integer.
+/// The key has to be consistent with the `Ord` implementation of the element.
+pub trait IntegerKey {
+    fn integer_key(&self) -> u32;
+}
+
+const KEY_BITS: u32 = 32;
+const INVALID_BUCKET: u32 = u32::MAX;
+
+/// Priority queue with four levels of 256 buckets, see module docs.
+#[derive(Debug)]
+pub struct MultiLevelBucketQueue<T> {
+    buckets: Vec<Vec<T>>,
+    // bucket and position in the bucket for each index
+    positions: Vec<(u32, u32)>,
+    // key of the last popped element, all elements have keys greater or equal
+    min_key: u32,
+    len: usize,
+    digit_bits: u32,
+}
+
+impl<T: IntegerKey + Indexing> MultiLevelBucketQueue<T> {
+    /// Creates an empty queue.
+    /// The indices (as defined by the `Indexing` trait) of all inserted elements
+    /// will have to be in `[0, max_id)`
+    pub fn new(max_id: usize) -> Self {
+        Self::with_digit_bits(max_id, 8)
+    }
+
+    fn with_digit_bits(max_id: usize, digit_bits: u32) -> Self {
+        let num_levels = KEY_BITS.div_ceil(digit_bits);
+        MultiLevelBucketQueue {
+            buckets: (0..num_levels << digit_bits).map(|_| Vec::new()).collect(),
+            positions: vec![(INVALID_BUCKET, 0); max_id],
+            min_key: 0,
+            len: 0,
+            digit_bits,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains_index(&self, id: usize) -> bool {
+        self.positions[id].0 != INVALID_BUCKET
+    }
+
+    pub fn get(&self, id: usize) -> Option<&T> {
+        let (bucket, position) = self.positions[id];
+        self.buckets.get(bucket as usize).map(|bucket| &bucket[position as usize])
+    }
+
+    /// Drops all items and resets the minimum key, so the next keys may be arbitrary again.
+    pub fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            for element in bucket.iter() {
+                self.positions[element.as_index()] = (INVALID_BUCKET, 0);
+            }
+            bucket.clear();
+        }
+        self.min_key = 0;
+        self.len = 0;
+    }
+
+    /// Returns one of the elements with the smallest key, or None if it is empty.
+    pub fn peek(&self) -> Option<&T> {
+        let bucket = self.first_non_empty_bucket()?;
+        if bucket < 1 << self.digit_bits {
+            self.buckets[bucket].last()
+        } else {
+            self.buckets[bucket].iter().min_by_key(|element| element.integer_key())
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let mut bucket = self.first_non_empty_bucket()?;
+        if bucket >= 1 << self.digit_bits {
+            bucket = self.redistribute(bucket);
+        }
+        let element = self.buckets[bucket].pop().unwrap();
+        self.positions[element.as_index()] = (INVALID_BUCKET, 0);
+        self.min_key = element.integer_key();
+        self.len -= 1;
+        Some(element)
+    }
+
+    /// Pushes an item onto the queue.
+    /// Panics if an element with the same index already exists.
+    /// The key may not be smaller than the key of the last popped element.
+    pub fn push(&mut self, element: T) {
+        assert!(!self.contains_index(element.as_index()));
+        self.insert(element);
+        self.len += 1;
+    }
+
+    /// Updates the key of an element to a smaller one, which still may not be smaller than the key of the last popped element.
+    /// Undefined if the element is not part of the queue.
+    pub fn decrease_key(&mut self, element: T) {
+        let (bucket, position) = self.positions[element.as_index()];
+        if bucket as usize == self.bucket(element.integer_key()) {
+            self.buckets[bucket as usize][position as usize] = element;
+        } else {
+            self.remove(bucket as usize, position as usize);
+            self.insert(element);
+        }
+    }
+
+    // The level is the highest digit in which the key differs from the minimum, the bucket in the level is the value of that digit.
+    // Keys which only differ in the lowest digit (or not at all) are in level 0.
+    #[inline]
+    fn bucket(&self, key: u32) -> usize {
+        debug_assert!(key >= self.min_key, "keys have to be monotone");
+        let differing_bits = key ^ self.min_key;
+        let level = if differing_bits == 0 {
+            0
+        } else {
+            (KEY_BITS - 1 - differing_bits.leading_zeros()) / self.digit_bits
+        };
+        let digit = (key >> (level * self.digit_bits)) & ((1 << self.digit_bits) - 1);
+        ((level << self.digit_bits) + digit) as usize
+    }
+
+    fn insert(&mut self, element: T) {
+        let bucket = self.bucket(element.integer_key());
+        self.positions[element.as_index()] = (bucket as u32, self.buckets[bucket].len() as u32);
+        self.buckets[bucket].push(element);
+    }
+
+    fn remove(&mut self, bucket: usize, position: usize) {
+        self.buckets[bucket].swap_remove(position);
+        if let Some(moved) = self.buckets[bucket].get(position) {
+            self.positions[moved.as_index()].1 = position as u32;
+        }
+    }
+
+    // Buckets below the one of `min_key` only contain smaller keys and are therefore always empty.
+    // So the first non-empty bucket contains the elements with the smallest key.
+    fn first_non_empty_bucket(&self) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+        (self.bucket(self.min_key)..self.buckets.len()).find(|&bucket| !self.buckets[bucket].is_empty())
+    }
+
+    // A bucket in level 0 contains only one key, a bucket in a higher level has to be split up
+    // by making its smallest key the new minimum and reinserting its elements.
+    // They all end up in lower buckets, the other elements stay where they are.
+    fn redistribute(&mut self, bucket: usize) -> usize {
+        let elements = std::mem::take(&mut self.buckets[bucket]);
+        self.min_key = elements.iter().map(IntegerKey::integer_key).min().unwrap();
+        for element in elements {
+            self.insert(element);
+        }
+        self.bucket(self.min_key)
+    }
+}
+
+impl<T: IntegerKey + Indexing> PriorityQueue<T> for MultiLevelBucketQueue<T> {
+    fn new(max_id: usize) -> Self {
+        MultiLevelBucketQueue::new(max_id)
+    }
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn contains_index(&self, id: usize) -> bool {
+        self.contains_index(id)
+    }
+    fn get(&self, id: usize) -> Option<&T> {
+        self.get(id)
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+    fn peek(&self) -> Option<&T> {
+        self.peek()
+    }
+    fn pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+    fn push(&mut self, element: T) {
+        self.push(element)
+    }
+    fn decrease_key(&mut self, element: T) {
+        self.decrease_key(element)
+    }
+}
+
+/// Radix heap, a `MultiLevelBucketQueue` with single bit digits, see module docs.
+#[derive(Debug)]
+pub struct RadixHeap<T>(MultiLevelBucketQueue<T>);
+
+impl<T: IntegerKey + Indexing> RadixHeap<T> {
+    /// Creates an empty queue.
+    /// The indices (as defined by the `Indexing` trait) of all inserted elements
+    /// will have to be in `[0, max_id)`
+    pub fn new(max_id: usize) -> Self {
+        RadixHeap(MultiLevelBucketQueue::with_digit_bits(max_id, 1))
+    }
+}
+
+impl<T: IntegerKey + Indexing> PriorityQueue<T> for RadixHeap<T> {
+    fn new(max_id: usize) -> Self {
+        RadixHeap::new(max_id)
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn contains_index(&self, id: usize) -> bool {
+        self.0.contains_index(id)
+    }
+    fn get(&self, id: usize) -> Option<&T> {
+        self.0.get(id)
+    }
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+    fn peek(&self) -> Option<&T> {
+        self.0.peek()
+    }
+    fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+    fn push(&mut self, element: T) {
+        self.0.push(element)
+    }
+    fn decrease_key(&mut self, element: T) {
+        self.0.decrease_key(element)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Ord, PartialOrd)]
+    struct State {
+        key: u32,
+        node: usize,
+    }
+
+    impl Indexing for State {
+        fn as_index(&self) -> usize {
+            self.node
+        }
+    }
+
+    impl IntegerKey for State {
+        fn integer_key(&self) -> u32 {
+            self.key
+        }
+    }
+
+    // Random monotone operations, popped keys have to be the same as for the binary heap.
+    fn same_keys_as_heap<Q: PriorityQueue<State>>() {
+        let mut rng = StdRng::from_seed(Default::default());
+        let n = 1000;
+        let mut queue = Q::new(n);
+        let mut heap = IndexdMinHeap::new(n);
+
+        for _ in 0..3 {
+            queue.clear();
+            heap.clear();
+            let mut min_key = 0;
+
+            for _ in 0..10_000 {
+                let node = rng.gen_range(0, n);
+                let max_diff = if rng.gen_bool(0.5) { 100 } else { 100_000 };
+                let key = min_key + rng.gen_range(0, max_diff);
+                match heap.get(node).copied() {
+                    Some(State { key: old, .. }) if key < old => {
+                        heap.decrease_key(State { key, node });
+                        queue.decrease_key(State { key, node });
+                    }
+                    Some(_) => (),
+                    None => {
+                        heap.push(State { key, node });
+                        queue.push(State { key, node });
+                    }
+                }
+                assert_eq!(queue.len(), heap.len());
+                assert_eq!(queue.get(node), heap.get(node));
+
+                if rng.gen_bool(0.4) {
+                    assert_eq!(queue.peek().map(|state| state.key), heap.peek().map(|state| state.key));
+                    let popped = queue.pop().unwrap();
+                    let expected = heap.get(popped.node).copied();
+                    assert_eq!(Some(popped), expected);
+                    heap.decrease_key(State { key: 0, node: popped.node });
+                    heap.pop();
+                    assert!(!queue.contains_index(popped.node));
+                    min_key = popped.key;
+                }
+            }
+
+            while let Some(State { key, .. }) = heap.pop() {
+                assert_eq!(queue.pop().map(|state| state.key), Some(key));
+            }
+            assert!(queue.is_empty());
+            assert_eq!(queue.pop(), None);
+        }
+    }
+
+    #[test]
+    fn multi_level_bucket_queue() {
+        same_keys_as_heap::<MultiLevelBucketQueue<State>>();
+    }
+
+    #[test]
+    fn radix_heap() {
+        same_keys_as_heap::<RadixHeap<State>>();
+    }
+}
@@ -5,6 +5,8 @@
 use crate::datastr::node_order::NodeOrder;
 use std::ops::Range;
 
+pub mod cleanup;
+pub mod compressed;
 pub mod first_out_graph;
 pub mod floating_time_dependent;
 pub mod link_id_to_tail_mapper;
@@ -0,0 +1,437 @@
+//! Cleanup of imported graphs before running queries on them.
+//!
+//! Each step produces a `CleanedGraph`, which contains the new graph and maps its nodes and arcs back to the input graph.
+//! Steps can be chained with `CleanedGraph::then`, so the mapping always refers to the original graph.
+//! `clean` runs the usual pipeline: extract the largest strongly connected component, remove self-loops and parallel arcs
+//! and optionally compress chains of degree two nodes.
+//!
+//! ```
+//! # use rust_road_router::datastr::graph::{cleanup::*, *};
+//! // 0 -> 1 -> 2 -> 0 with a parallel arc 0 -> 1 and an unreachable node 3
+//! let graph = OwnedGraph::new(vec![0, 2, 3, 4, 5], vec![1, 1, 2, 0, 0], vec![5, 3, 4, 2, 1]);
+//! let cleaned = clean(&graph, true);
+//! assert_eq!(cleaned.graph().num_nodes(), 1);
+//! assert_eq!(cleaned.graph().num_arcs(), 0);
+//! let cleaned = clean(&graph, false);
+//! assert_eq!(cleaned.graph().num_arcs(), 3);
+//! assert_eq!(cleaned.expand_path(&[0, 1]), vec![1, 2]);
+//! ```
+
+use super::*;
+
+/// A graph derived from an original graph by removing nodes and arcs or replacing paths by single arcs.
+#[derive(Debug, Clone)]
+pub struct CleanedGraph {
+    graph: OwnedGraph,
+    original_node: Vec<NodeId>,
+    first_original_arc: Vec<EdgeId>,
+    original_arcs: Vec<EdgeId>,
+}
+
+impl CleanedGraph {
+    // Build from the nodes to keep and for each new arc the sequence of original arcs it represents.
+    fn new<G: RandomLinkAccessGraph>(original: &G, original_node: Vec<NodeId>, arcs: Vec<(NodeId, Weight, Vec<EdgeId>)>, first_out: Vec<EdgeId>) -> Self {
+        debug_assert_eq!(*first_out.last().unwrap() as usize, arcs.len());
+        debug_assert!(arcs
+            .iter()
+            .all(|(_, _, path)| !path.is_empty() && path.iter().all(|&arc| (arc as usize) < original.num_arcs())));
+        let mut head = Vec::with_capacity(arcs.len());
+        let mut weight = Vec::with_capacity(arcs.len());
+        let mut first_original_arc = Vec::with_capacity(arcs.len() + 1);
+        first_original_arc.push(0);
+        let mut original_arcs = Vec::with_capacity(arcs.len());
+        for (arc_head, arc_weight, path) in arcs {
+            head.push(arc_head);
+            weight.push(arc_weight);
+            original_arcs.extend(path);
+            first_original_arc.push(original_arcs.len() as EdgeId);
+        }
+
+        CleanedGraph {
+            graph: OwnedGraph::new(first_out, head, weight),
+            original_node,
+            first_original_arc,
+            original_arcs,
+        }
+    }
+
+    /// The cleaned graph
+    pub fn graph(&self) -> &OwnedGraph {
+        &self.graph
+    }
+
+    pub fn into_graph(self) -> OwnedGraph {
+        self.graph
+    }
+
+    /// The id of a node of the cleaned graph in the original graph
+    pub fn original_node(&self, node: NodeId) -> NodeId {
+        self.original_node[node as usize]
+    }
+
+    /// The ids of all nodes of the cleaned graph in the original graph, suitable to select node attributes
+    pub fn original_nodes(&self) -> &[NodeId] {
+        &self.original_node
+    }
+
+    /// The path of original arcs represented by an arc of the cleaned graph
+    pub fn original_arcs(&self, edge_id: EdgeId) -> &[EdgeId] {
+        &self.original_arcs[self.first_original_arc[edge_id as usize] as usize..self.first_original_arc[edge_id as usize + 1] as usize]
+    }
+
+    /// Expand a path given as arcs of the cleaned graph into arcs of the original graph.
+    pub fn expand_path(&self, path: &[EdgeId]) -> Vec<EdgeId> {
+        path.iter().flat_map(|&edge_id| self.original_arcs(edge_id).iter().cloned()).collect()
+    }
+
+    /// Expand a path given as nodes of the cleaned graph into nodes of the original graph.
+    /// Consecutive nodes are connected by the arc with the lowest weight.
+    pub fn expand_node_path<G: RandomLinkAccessGraph>(&self, original: &G, path: &[NodeId]) -> Vec<NodeId> {
+        let mut expanded: Vec<NodeId> = path.first().map(|&node| self.original_node(node)).into_iter().collect();
+        for nodes in path.windows(2) {
+            let edge_id = self
+                .graph
+                .neighbor_edge_indices(nodes[0])
+                .filter(|&edge_id| self.graph.link(edge_id).node == nodes[1])
+                .min_by_key(|&edge_id| self.graph.link(edge_id).weight)
+                .expect("path contains nodes which are not adjacent");
+            expanded.extend(self.original_arcs(edge_id).iter().map(|&arc| original.link(arc).node));
+        }
+        expanded
+    }
+
+    /// Apply another cleanup step to the cleaned graph, the mapping of the result refers to the original graph again.
+    pub fn then(self, step: impl FnOnce(&OwnedGraph) -> CleanedGraph) -> CleanedGraph {
+        let next = step(&self.graph);
+        let mut first_original_arc = Vec::with_capacity(next.first_original_arc.len());
+        first_original_arc.push(0);
+        let mut original_arcs = Vec::with_capacity(next.original_arcs.len());
+        for edge_id in 0..next.graph.num_arcs() {
+            for &arc in next.original_arcs(edge_id as EdgeId) {
+                original_arcs.extend_from_slice(self.original_arcs(arc));
+            }
+            first_original_arc.push(original_arcs.len() as EdgeId);
+        }
+
+        CleanedGraph {
+            original_node: next.original_node.iter().map(|&node| self.original_node(node)).collect(),
+            graph: next.graph,
+            first_original_arc,
+            original_arcs,
+        }
+    }
+}
+
+/// Extract the largest strongly connected component, remove self-loops and parallel arcs and, if desired, compress chains of degree two nodes.
+pub fn clean<G: RandomLinkAccessGraph + for<'a> LinkIterable<'a, NodeId>>(graph: &G, compress_degree_two_chains: bool) -> CleanedGraph {
+    let cleaned = largest_strongly_connected_component(graph).then(remove_loops_and_parallel_arcs);
+    if compress_degree_two_chains {
+        // compression may create new loops and parallel arcs
+        cleaned.then(compress_chains).then(remove_loops_and_parallel_arcs)
+    } else {
+        cleaned
+    }
+}
+
+/// Component id for each node, computed with an iterative version of Tarjan's algorithm.
+pub fn strongly_connected_components<G: for<'a> LinkIterable<'a, NodeId>>(graph: &G) -> Vec<u32> {
+    const UNVISITED: u32 = u32::MAX;
+    let num_nodes = graph.num_nodes();
+
+    let mut dfs_idx = vec![UNVISITED; num_nodes];
+    let mut low_link = vec![UNVISITED; num_nodes];
+    let mut component = vec![UNVISITED; num_nodes];
+    let mut on_stack = vec![false; num_nodes];
+    let mut stack = Vec::new();
+    let mut next_dfs_idx = 0;
+    let mut num_components = 0;
+
+    for root in 0..num_nodes {
+        if dfs_idx[root] != UNVISITED {
+            continue;
+        }
+
+        dfs_idx[root] = next_dfs_idx;
+        low_link[root] = next_dfs_idx;
+        next_dfs_idx += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        // node and its remaining neighbors for each node on the DFS path
+        let mut path = vec![(root, graph.link_iter(root as NodeId))];
+
+        while let Some(&mut (node, ref mut neighbors)) = path.last_mut() {
+            if let Some(head) = neighbors.next() {
+                let head = head as usize;
+                if dfs_idx[head] == UNVISITED {
+                    dfs_idx[head] = next_dfs_idx;
+                    low_link[head] = next_dfs_idx;
+                    next_dfs_idx += 1;
+                    stack.push(head);
+                    on_stack[head] = true;
+                    path.push((head, graph.link_iter(head as NodeId)));
+                } else if on_stack[head] {
+                    low_link[node] = std::cmp::min(low_link[node], dfs_idx[head]);
+                }
+            } else {
+                path.pop();
+                if let Some(&(parent, _)) = path.last() {
+                    low_link[parent] = std::cmp::min(low_link[parent], low_link[node]);
+                }
+                if low_link[node] == dfs_idx[node] {
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component[member] = num_components;
+                        if member == node {
+                            break;
+                        }
+                    }
+                    num_components += 1;
+                }
+            }
+        }
+    }
+
+    component
+}
+
+/// The nodes of the largest strongly connected component in ascending order.
+pub fn largest_strongly_connected_component_nodes<G: for<'a> LinkIterable<'a, NodeId>>(graph: &G) -> Vec<NodeId> {
+    let components = strongly_connected_components(graph);
+    let mut sizes = Vec::new();
+    for &component in &components {
+        if sizes.len() <= component as usize {
+            sizes.resize(component as usize + 1, 0);
+        }
+        sizes[component as usize] += 1;
+    }
+    // ties are broken towards the component found first
+    let largest = (0..sizes.len()).rev().max_by_key(|&component| sizes[component]).unwrap_or(0) as u32;
+    (0..graph.num_nodes() as NodeId).filter(|&node| components[node as usize] == largest).collect()
+}
+
+/// The subgraph induced by the largest strongly connected component, node ids keep their relative order.
+pub fn largest_strongly_connected_component<G: RandomLinkAccessGraph + for<'a> LinkIterable<'a, NodeId>>(graph: &G) -> CleanedGraph {
+    induced_subgraph(graph, largest_strongly_connected_component_nodes(graph))
+}
+
+/// The subgraph induced by the given nodes, where node `new_to_old[i]` becomes node `i`.
+pub fn induced_subgraph<G: RandomLinkAccessGraph>(graph: &G, new_to_old: Vec<NodeId>) -> CleanedGraph {
+    let mut old_to_new = vec![None; graph.num_nodes()];
+    for (new, &old) in new_to_old.iter().enumerate() {
+        old_to_new[old as usize] = Some(new as NodeId);
+    }
+
+    let mut first_out = Vec::with_capacity(new_to_old.len() + 1);
+    first_out.push(0);
+    let mut arcs = Vec::new();
+    for &old in &new_to_old {
+        for edge_id in graph.neighbor_edge_indices(old) {
+            let Link { node, weight } = graph.link(edge_id);
+            if let Some(new_head) = old_to_new[node as usize] {
+                arcs.push((new_head, weight, vec![edge_id]));
+            }
+        }
+        first_out.push(arcs.len() as EdgeId);
+    }
+
+    CleanedGraph::new(graph, new_to_old, arcs, first_out)
+}
+
+/// Remove all self-loops and of several parallel arcs keep only the one with the lowest weight.
+/// In contrast to `unify_parallel_edges` the arcs are actually removed, which also helps algorithms which do not look at the weights.
+pub fn remove_loops_and_parallel_arcs<G: RandomLinkAccessGraph>(graph: &G) -> CleanedGraph {
+    // index of the kept arc for each head of the current node
+    let mut best: Vec<Option<usize>> = vec![None; graph.num_nodes()];
+    let mut first_out = Vec::with_capacity(graph.num_nodes() + 1);
+    first_out.push(0);
+    let mut arcs: Vec<(NodeId, Weight, Vec<EdgeId>)> = Vec::with_capacity(graph.num_arcs());
+
+    for node in 0..graph.num_nodes() as NodeId {
+        let node_arcs_start = arcs.len();
+        for edge_id in graph.neighbor_edge_indices(node) {
+            let Link { node: head, weight } = graph.link(edge_id);
+            if head == node {
+                continue;
+            }
+            match best[head as usize] {
+                Some(idx) if arcs[idx].1 <= weight => (),
+                Some(idx) => arcs[idx] = (head, weight, vec![edge_id]),
+                None => {
+                    best[head as usize] = Some(arcs.len());
+                    arcs.push((head, weight, vec![edge_id]));
+                }
+            }
+        }
+        for &(head, _, _) in &arcs[node_arcs_start..] {
+            best[head as usize] = None;
+        }
+        first_out.push(arcs.len() as EdgeId);
+    }
+
+    CleanedGraph::new(graph, (0..graph.num_nodes() as NodeId).collect(), arcs, first_out)
+}
+
+/// Replace chains of degree two nodes by single arcs.
+///
+/// A node is part of a chain if it has exactly two distinct neighbors `u` and `w` and can only be passed through,
+/// that is its arcs are either `u -> v -> w` or `u <-> v <-> w`.
+/// All other nodes are kept, chains are replaced by an arc between their end nodes with the sum of the weights.
+/// Cycles consisting only of chain nodes keep one of their nodes.
+/// The result may contain self-loops and parallel arcs, which `clean` removes afterwards.
+pub fn compress_chains<G: RandomLinkAccessGraph>(graph: &G) -> CleanedGraph {
+    let n = graph.num_nodes();
+
+    // up to two tails of incoming arcs per node, enough to recognize chain nodes
+    let mut in_degree = vec![0u32; n];
+    let mut in_tails = vec![[0 as NodeId; 2]; n];
+    for tail in 0..n as NodeId {
+        for edge_id in graph.neighbor_edge_indices(tail) {
+            let head = graph.link(edge_id).node as usize;
+            if in_degree[head] < 2 {
+                in_tails[head][in_degree[head] as usize] = tail;
+            }
+            in_degree[head] += 1;
+        }
+    }
+
+    let is_chain_node = |node: usize| {
+        let heads: Vec<NodeId> = graph.neighbor_edge_indices(node as NodeId).map(|edge_id| graph.link(edge_id).node).collect();
+        if heads.contains(&(node as NodeId)) || heads.len() as u32 != in_degree[node] {
+            return false;
+        }
+        let tails = &in_tails[node];
+        match heads[..] {
+            [head] => head != tails[0],
+            [first, second] => first != second && ((first == tails[0] && second == tails[1]) || (first == tails[1] && second == tails[0])),
+            _ => false,
+        }
+    };
+    let mut keep: Vec<bool> = (0..n).map(|node| !is_chain_node(node)).collect();
+
+    // the arc leaving a chain node which does not lead back to where we came from
+    let continue_chain = |node: NodeId, previous: NodeId| graph.neighbor_edge_indices(node).find(|&edge_id| graph.link(edge_id).node != previous).unwrap();
+    // follow the chain starting with the given arc until a kept node is reached
+    let follow = |keep: &[bool], tail: NodeId, edge_id: EdgeId| {
+        let (mut previous, mut edge_id) = (tail, edge_id);
+        let mut path = vec![edge_id];
+        let mut weight = graph.link(edge_id).weight;
+        loop {
+            let node = graph.link(edge_id).node;
+            if keep[node as usize] {
+                return (node, weight, path);
+            }
+            let next = continue_chain(node, previous);
+            previous = node;
+            edge_id = next;
+            path.push(edge_id);
+            weight = std::cmp::min(weight.saturating_add(graph.link(edge_id).weight), INFINITY);
+        }
+    };
+
+    // mark the chain nodes reachable from a kept node, the remaining ones form cycles without any kept node
+    let cover = |keep: &[bool], covered: &mut [bool], root: NodeId| {
+        for edge_id in graph.neighbor_edge_indices(root) {
+            let mut previous = root;
+            let mut node = graph.link(edge_id).node;
+            while !keep[node as usize] {
+                covered[node as usize] = true;
+                let next = graph.link(continue_chain(node, previous)).node;
+                previous = node;
+                node = next;
+            }
+        }
+    };
+    let mut covered = keep.clone();
+    for node in 0..n {
+        if keep[node] {
+            cover(&keep, &mut covered, node as NodeId);
+        }
+    }
+    for node in 0..n {
+        if !covered[node] {
+            keep[node] = true;
+            covered[node] = true;
+            cover(&keep, &mut covered, node as NodeId);
+        }
+    }
+
+    let mut new_id = vec![0 as NodeId; n];
+    let mut original_node = Vec::new();
+    for node in 0..n {
+        if keep[node] {
+            new_id[node] = original_node.len() as NodeId;
+            original_node.push(node as NodeId);
+        }
+    }
+
+    let mut first_out = Vec::with_capacity(original_node.len() + 1);
+    first_out.push(0);
+    let mut arcs = Vec::new();
+    for &node in &original_node {
+        for edge_id in graph.neighbor_edge_indices(node) {
+            let (head, weight, path) = follow(&keep, node, edge_id);
+            arcs.push((new_id[head as usize], weight, path));
+        }
+        first_out.push(arcs.len() as EdgeId);
+    }
+
+    CleanedGraph::new(graph, original_node, arcs, first_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scc() {
+        // 0 <-> 1 -> 2 <-> 3 <-> 4, 5 isolated
+        let graph = UnweightedOwnedGraph::new(vec![0, 1, 3, 4, 6, 7, 7], vec![1, 0, 2, 3, 2, 4, 3]);
+        let components = strongly_connected_components(&graph);
+        assert_eq!(components[0], components[1]);
+        assert_eq!(components[2], components[3]);
+        assert_eq!(components[3], components[4]);
+        assert_ne!(components[1], components[2]);
+        assert_ne!(components[5], components[0]);
+        assert_ne!(components[5], components[2]);
+        assert_eq!(largest_strongly_connected_component_nodes(&graph), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn loops_and_parallel_arcs() {
+        // 0 -> 0, 0 -> 1 (3), 0 -> 1 (2), 0 -> 2, 1 -> 0
+        let graph = OwnedGraph::new(vec![0, 4, 5, 5], vec![0, 1, 1, 2, 0], vec![1, 3, 2, 4, 5]);
+        let cleaned = remove_loops_and_parallel_arcs(&graph);
+        assert_eq!(cleaned.graph().head(), &[1, 2, 0]);
+        assert_eq!(cleaned.graph().weight(), &[2, 4, 5]);
+        assert_eq!(cleaned.expand_path(&[0, 2]), vec![2, 4]);
+    }
+
+    #[test]
+    fn chains() {
+        // 0 <-> 1 <-> 2 <-> 3 with 3 -> 4 -> 5 -> 3 and 5 -> 6 -> 0, plus a separate cycle 7 -> 8 -> 9 -> 7
+        let first_out = vec![0, 1, 3, 5, 7, 8, 10, 11, 12, 13, 14];
+        let head = vec![1, 0, 2, 1, 3, 2, 4, 5, 3, 6, 0, 8, 9, 7];
+        let weight = vec![1, 1, 2, 2, 3, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+        let graph = OwnedGraph::new(first_out, head, weight);
+        let compressed = compress_chains(&graph);
+        // 0 is kept because of the incoming arc from 6, 3 and 5 have three neighbors, 7 anchors its cycle
+        assert_eq!(compressed.original_nodes(), &[0, 3, 5, 7]);
+        let compressed_graph = compressed.graph();
+        let arc_0_3 = compressed_graph.edge_index(0, 1).unwrap();
+        assert_eq!(compressed_graph.link(arc_0_3).weight, 6);
+        assert_eq!(compressed.original_arcs(arc_0_3), &[0, 2, 4]);
+        let arc_3_5 = compressed_graph.edge_index(1, 2).unwrap();
+        assert_eq!(compressed_graph.link(arc_3_5).weight, 9);
+        let arc_5_0 = compressed_graph.edge_index(2, 0).unwrap();
+        assert_eq!(compressed.expand_node_path(&graph, &[1, 2, 0, 1]), vec![3, 4, 5, 6, 0, 1, 2, 3]);
+        assert_eq!(compressed.expand_path(&[arc_5_0]), vec![9, 10]);
+        // the cycle 7 -> 8 -> 9 -> 7 becomes a loop
+        let arc_7 = compressed_graph.edge_index(3, 3).unwrap();
+        assert_eq!(compressed_graph.link(arc_7).weight, 30);
+
+        let cleaned = clean(&graph, true);
+        assert_eq!(cleaned.original_nodes(), &[0, 3, 5]);
+        assert_eq!(cleaned.graph().num_arcs(), 5);
+        assert_eq!(cleaned.expand_path(&[0]), vec![0, 2, 4]);
+    }
+}
@@ -0,0 +1,439 @@
+//! Compressed static graph representation for large networks.
+//!
+//! The layout is the same adjacency array as in `first_out_graph`, so node and arc ids do not change.
+//! But instead of plain `u32` node ids, `head` stores the difference to the tail node, encoded in as few bytes as possible (zig-zag and LEB128).
+//! Weights are stored with the smallest unsigned integer type which can represent all of them.
+//!
+//! The differences are only small when adjacent nodes have close ids.
+//! Road graphs should thus be reordered for locality before compressing them, for example with `rrr read DIR reorder --by hilbert write OUT_DIR`, see `algo::node_reordering`.
+//!
+//! Byte offsets into the encoded heads are only stored for every `BLOCK_SIZE`th node.
+//! Accessing the arcs of a node requires skipping over the encoded heads of the preceding nodes in the block.
+//!
+//! ```
+//! # use rust_road_router::datastr::graph::{compressed::CompressedGraph, *};
+//! let graph = OwnedGraph::new(vec![0, 2, 3, 4], vec![1, 2, 2, 0], vec![5, 3, 4, 2]);
+//! let compressed = CompressedGraph::new(&graph);
+//! assert_eq!(compressed.weight_bytes(), 1);
+//! assert_eq!(LinkIterable::<NodeId>::link_iter(&compressed, 0).collect::<Vec<_>>(), vec![1, 2]);
+//! assert_eq!(compressed.link(3).node, 0);
+//! assert_eq!(compressed.edge_index(1, 2), Some(2));
+//! ```
+
+use super::*;
+use crate::io::*;
+
+/// Number of nodes for which one byte offset into the encoded heads is stored.
+pub const BLOCK_SIZE: usize = 16;
+
+#[derive(Debug, Clone)]
+enum Weights {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Weights {
+    fn new(weights: &[Weight]) -> Self {
+        match weights.iter().max() {
+            Some(&max) if max > Weight::from(u16::MAX) => Weights::U32(weights.to_vec()),
+            Some(&max) if max > Weight::from(u8::MAX) => Weights::U16(weights.iter().map(|&weight| weight as u16).collect()),
+            _ => Weights::U8(weights.iter().map(|&weight| weight as u8).collect()),
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, edge_id: usize) -> Weight {
+        match self {
+            Weights::U8(weights) => Weight::from(weights[edge_id]),
+            Weights::U16(weights) => Weight::from(weights[edge_id]),
+            Weights::U32(weights) => weights[edge_id],
+        }
+    }
+
+    fn bytes_per_weight(&self) -> usize {
+        match self {
+            Weights::U8(_) => 1,
+            Weights::U16(_) => 2,
+            Weights::U32(_) => 4,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Weights::U8(weights) => weights.len(),
+            Weights::U16(weights) => weights.len(),
+            Weights::U32(weights) => weights.len(),
+        }
+    }
+}
+
+/// Graph with delta encoded head node ids and narrow weights, see module docs.
+#[derive(Debug, Clone)]
+pub struct CompressedGraph {
+    // index of first edge of each node +1 entry in the end
+    first_out: Vec<EdgeId>,
+    // byte offset of the first encoded head of every BLOCK_SIZEth node +1 entry in the end
+    block_first_byte: Vec<u64>,
+    // the encoded differences between head and tail of each edge
+    head_bytes: Vec<u8>,
+    weights: Weights,
+}
+
+impl CompressedGraph {
+    /// Compress a graph, arc ids are the ones from `graph.neighbor_edge_indices`.
+    pub fn new<G: RandomLinkAccessGraph>(graph: &G) -> Self {
+        let mut first_out = Vec::with_capacity(graph.num_nodes() + 1);
+        first_out.push(0);
+        let mut block_first_byte = Vec::with_capacity(graph.num_nodes() / BLOCK_SIZE + 2);
+        let mut head_bytes = Vec::with_capacity(graph.num_arcs());
+        let mut weights = Vec::with_capacity(graph.num_arcs());
+
+        for node in 0..graph.num_nodes() {
+            if node % BLOCK_SIZE == 0 {
+                block_first_byte.push(head_bytes.len() as u64);
+            }
+            let range = graph.neighbor_edge_indices(node as NodeId);
+            assert_eq!(range.start, *first_out.last().unwrap(), "arcs have to be ordered by tail");
+            for edge_id in range.clone() {
+                let link = graph.link(edge_id);
+                encode(i64::from(link.node) - node as i64, &mut head_bytes);
+                weights.push(link.weight);
+            }
+            first_out.push(range.end);
+        }
+        block_first_byte.push(head_bytes.len() as u64);
+        head_bytes.shrink_to_fit();
+
+        CompressedGraph {
+            first_out,
+            block_first_byte,
+            head_bytes,
+            weights: Weights::new(&weights),
+        }
+    }
+
+    /// Borrow a slice of the first_out data
+    pub fn first_out(&self) -> &[EdgeId] {
+        &self.first_out
+    }
+
+    /// Number of bytes used for each weight, 1, 2 or 4.
+    pub fn weight_bytes(&self) -> usize {
+        self.weights.bytes_per_weight()
+    }
+
+    /// Total size of the graph data in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.first_out.len() * std::mem::size_of::<EdgeId>()
+            + self.block_first_byte.len() * std::mem::size_of::<u64>()
+            + self.head_bytes.len()
+            + self.weights.len() * self.weights.bytes_per_weight()
+    }
+
+    /// Decompress into a plain `OwnedGraph` with the same node and arc ids.
+    pub fn decompress(&self) -> OwnedGraph {
+        let mut head = Vec::with_capacity(self.num_arcs());
+        for node in 0..self.num_nodes() {
+            head.extend(self.heads(node as NodeId));
+        }
+        let weight = (0..self.num_arcs()).map(|edge_id| self.weights.get(edge_id)).collect();
+        OwnedGraph::new(self.first_out.clone(), head, weight)
+    }
+
+    /// Iterator over the decoded heads of the outgoing arcs of `node`.
+    pub fn heads(&self, node: NodeId) -> HeadIter<'_> {
+        let block_start = node as usize / BLOCK_SIZE * BLOCK_SIZE;
+        let mut bytes = &self.head_bytes[self.block_first_byte[node as usize / BLOCK_SIZE] as usize..];
+        // skip the encoded heads of the preceding nodes in the block, each ends with a byte without the continuation bit
+        let mut to_skip = (self.first_out[node as usize] - self.first_out[block_start]) as usize;
+        while to_skip > 0 {
+            if bytes[0] & CONTINUATION_BIT == 0 {
+                to_skip -= 1;
+            }
+            bytes = &bytes[1..];
+        }
+
+        HeadIter {
+            bytes,
+            tail: node,
+            remaining: self.degree(node),
+        }
+    }
+}
+
+const CONTINUATION_BIT: u8 = 0x80;
+
+// zig-zag encoding, so small negative differences also get small values, followed by LEB128
+fn encode(delta: i64, bytes: &mut Vec<u8>) {
+    let mut value = ((delta << 1) ^ (delta >> 63)) as u64;
+    while value >= u64::from(CONTINUATION_BIT) {
+        bytes.push(value as u8 | CONTINUATION_BIT);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+// decoding with bounds checks, for validating untrusted data
+fn decode_checked(bytes: &[u8], pos: &mut usize) -> Option<i64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        if shift >= 64 {
+            return None;
+        }
+        value |= u64::from(byte & !CONTINUATION_BIT) << shift;
+        if byte & CONTINUATION_BIT == 0 {
+            return Some((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+        shift += 7;
+    }
+}
+
+/// Iterator decoding the heads of a node in a `CompressedGraph`.
+#[derive(Debug, Clone)]
+pub struct HeadIter<'a> {
+    bytes: &'a [u8],
+    tail: NodeId,
+    remaining: usize,
+}
+
+impl<'a> Iterator for HeadIter<'a> {
+    type Item = NodeId;
+
+    #[inline]
+    fn next(&mut self) -> Option<NodeId> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.bytes[0];
+            self.bytes = &self.bytes[1..];
+            value |= u64::from(byte & !CONTINUATION_BIT) << shift;
+            if byte & CONTINUATION_BIT == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        let delta = (value >> 1) as i64 ^ -((value & 1) as i64);
+        Some((i64::from(self.tail) + delta) as NodeId)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for HeadIter<'a> {}
+
+/// Iterator over the outgoing links of a node in a `CompressedGraph`.
+#[derive(Debug, Clone)]
+pub struct LinkIter<'a> {
+    heads: HeadIter<'a>,
+    weights: &'a Weights,
+    edge_id: usize,
+}
+
+impl<'a> Iterator for LinkIter<'a> {
+    type Item = Link;
+
+    #[inline]
+    fn next(&mut self) -> Option<Link> {
+        self.heads.next().map(|node| {
+            let weight = self.weights.get(self.edge_id);
+            self.edge_id += 1;
+            Link { node, weight }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.heads.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for LinkIter<'a> {}
+
+impl Graph for CompressedGraph {
+    fn num_nodes(&self) -> usize {
+        self.first_out.len() - 1
+    }
+
+    fn num_arcs(&self) -> usize {
+        *self.first_out.last().unwrap() as usize
+    }
+
+    fn degree(&self, node: NodeId) -> usize {
+        let node = node as usize;
+        (self.first_out[node + 1] - self.first_out[node]) as usize
+    }
+}
+
+impl<'a> LinkIterable<'a, Link> for CompressedGraph {
+    type Iter = LinkIter<'a>;
+
+    #[inline]
+    fn link_iter(&'a self, node: NodeId) -> Self::Iter {
+        LinkIter {
+            heads: self.heads(node),
+            weights: &self.weights,
+            edge_id: self.first_out[node as usize] as usize,
+        }
+    }
+}
+
+impl<'a> LinkIterable<'a, NodeId> for CompressedGraph {
+    type Iter = HeadIter<'a>;
+
+    #[inline]
+    fn link_iter(&'a self, node: NodeId) -> Self::Iter {
+        self.heads(node)
+    }
+}
+
+impl RandomLinkAccessGraph for CompressedGraph {
+    fn link(&self, edge_id: EdgeId) -> Link {
+        assert!((edge_id as usize) < self.num_arcs());
+        // the last node with its first edge at or before edge_id, nodes without edges share it with their successor
+        let tail = self.first_out.partition_point(|&first_edge| first_edge <= edge_id) - 1;
+        Link {
+            node: self.heads(tail as NodeId).nth((edge_id - self.first_out[tail]) as usize).unwrap(),
+            weight: self.weights.get(edge_id as usize),
+        }
+    }
+
+    fn edge_index(&self, from: NodeId, to: NodeId) -> Option<EdgeId> {
+        self.heads(from)
+            .position(|head| head == to)
+            .map(|pos| pos as EdgeId + self.first_out[from as usize])
+    }
+
+    #[inline]
+    fn neighbor_edge_indices(&self, node: NodeId) -> Range<EdgeId> {
+        self.first_out[node as usize]..self.first_out[node as usize + 1]
+    }
+}
+
+impl Deconstruct for CompressedGraph {
+    fn store_each(&self, store: &dyn Fn(&str, &dyn Store) -> std::io::Result<()>) -> std::io::Result<()> {
+        store("first_out", &self.first_out)?;
+        store("compressed_head_block_first_byte", &self.block_first_byte)?;
+        store("compressed_head", &self.head_bytes)?;
+        match &self.weights {
+            Weights::U8(weights) => store("compressed_weight", weights)?,
+            Weights::U16(weights) => store("compressed_weight", weights)?,
+            Weights::U32(weights) => store("compressed_weight", weights)?,
+        }
+        Ok(())
+    }
+}
+
+impl Reconstruct for CompressedGraph {
+    fn reconstruct_with(loader: Loader) -> std::io::Result<Self> {
+        let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("inconsistent compressed graph data: {}", msg));
+
+        let first_out: Vec<EdgeId> = loader.load("first_out")?;
+        if first_out.first() != Some(&0) || first_out.windows(2).any(|edges| edges[0] > edges[1]) {
+            return Err(invalid("first_out is not a prefix sum"));
+        }
+        let n = first_out.len() - 1;
+        let m = *first_out.last().unwrap() as usize;
+
+        let head_bytes: Vec<u8> = loader.load("compressed_head")?;
+        let block_first_byte: Vec<u64> = loader.load("compressed_head_block_first_byte")?;
+        if block_first_byte.len() != n.div_ceil(BLOCK_SIZE) + 1 || block_first_byte.last() != Some(&(head_bytes.len() as u64)) {
+            return Err(invalid("heads do not match first_out"));
+        }
+        // decode everything once, so queries can rely on valid heads and block offsets at arc boundaries
+        for (block, offsets) in block_first_byte.windows(2).enumerate() {
+            let mut pos = offsets[0] as usize;
+            for tail in block * BLOCK_SIZE..std::cmp::min((block + 1) * BLOCK_SIZE, n) {
+                for _ in first_out[tail]..first_out[tail + 1] {
+                    let head = decode_checked(&head_bytes, &mut pos)
+                        .map(|delta| tail as i64 + delta)
+                        .ok_or_else(|| invalid("truncated heads"))?;
+                    if head < 0 || head >= n as i64 {
+                        return Err(invalid("head out of range"));
+                    }
+                }
+            }
+            if pos as u64 != offsets[1] {
+                return Err(invalid("block offsets do not match the encoded heads"));
+            }
+        }
+
+        let weight_bytes: Vec<u8> = loader.load("compressed_weight")?;
+        let weights = match (weight_bytes.len(), m) {
+            (0, 0) => Weights::U8(Vec::new()),
+            (len, m) if len == m => Weights::U8(weight_bytes),
+            (len, m) if len == 2 * m => Weights::U16(weight_bytes.chunks_exact(2).map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]])).collect()),
+            (len, m) if len == 4 * m => Weights::U32(
+                weight_bytes
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect(),
+            ),
+            _ => return Err(invalid("number of weights does not match first_out")),
+        };
+
+        Ok(CompressedGraph {
+            first_out,
+            block_first_byte,
+            head_bytes,
+            weights,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_links_as_uncompressed() {
+        // node 0 and 17 have no arcs, heads far away and in both directions, one arc with an unrepresentable weight
+        let mut adjacency_lists = vec![Vec::new(); 40];
+        for node in 1..40 {
+            if node == 17 {
+                continue;
+            }
+            for &(head, weight) in &[(node - 1, 3), ((node * 7919) % 40, 300), (39 - node, node as Weight * 10)] {
+                adjacency_lists[node].push(Link { node: head as NodeId, weight });
+            }
+        }
+        adjacency_lists[5].push(Link { node: 0, weight: 70_000 });
+        let graph = OwnedGraph::from_adjancecy_lists(adjacency_lists);
+
+        let compressed = CompressedGraph::new(&graph);
+        assert_eq!(compressed.weight_bytes(), 4);
+        assert_eq!(compressed.num_nodes(), graph.num_nodes());
+        assert_eq!(compressed.num_arcs(), graph.num_arcs());
+
+        for node in 0..graph.num_nodes() as NodeId {
+            assert_eq!(compressed.degree(node), graph.degree(node));
+            assert_eq!(compressed.neighbor_edge_indices(node), graph.neighbor_edge_indices(node));
+            let links: Vec<_> = LinkIterable::<Link>::link_iter(&compressed, node).map(|l| (l.node, l.weight)).collect();
+            let expected: Vec<_> = LinkIterable::<Link>::link_iter(&graph, node).map(|l| (l.node, l.weight)).collect();
+            assert_eq!(links, expected);
+            for &(head, _) in &expected {
+                assert_eq!(compressed.edge_index(node, head), graph.edge_index(node, head));
+            }
+        }
+        for edge_id in 0..graph.num_arcs() as EdgeId {
+            assert_eq!(compressed.link(edge_id).node, graph.link(edge_id).node);
+            assert_eq!(compressed.link(edge_id).weight, graph.link(edge_id).weight);
+        }
+
+        let decompressed = compressed.decompress();
+        assert_eq!(decompressed.head(), graph.head());
+        assert_eq!(decompressed.weight(), graph.weight());
+
+        let small_weights = OwnedGraph::new(graph.first_out().to_vec(), graph.head().to_vec(), vec![1000; graph.num_arcs()]);
+        assert_eq!(CompressedGraph::new(&small_weights).weight_bytes(), 2);
+    }
+}
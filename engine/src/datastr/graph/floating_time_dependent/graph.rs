@@ -97,24 +97,25 @@ impl Graph {
 
     /// Assert that a time annotated path is valid and that the times of the path match the edge weights at the time.
     pub fn check_path(&self, path: Vec<(NodeId, Timestamp)>) {
-        let mut iter = path.into_iter();
-        let mut prev = iter.next().unwrap();
-        for (node, t) in iter {
-            let (prev_node, prev_t) = prev;
-            let edge = self.edge_index(prev_node, node).expect("path contained nonexisting edge");
+        if let Err(msg) = self.validate_path(&path) {
+            panic!("{}", msg);
+        }
+    }
+
+    /// Same checks as `check_path`, but returns a description of the first problem instead of panicking.
+    pub fn validate_path(&self, path: &[(NodeId, Timestamp)]) -> Result<(), String> {
+        for hop in path.windows(2) {
+            let ((prev_node, prev_t), (node, t)) = (hop[0], hop[1]);
+            let edge = self.edge_index(prev_node, node).ok_or("path contained nonexisting edge")?;
             let evaled = prev_t + self.travel_time_function(edge).evaluate(prev_t);
-            assert!(
-                t.fuzzy_eq(evaled),
-                "expected {:?} - got {:?} at edge {} from {} (at {:?}) to {}",
-                evaled,
-                t,
-                edge,
-                prev_node,
-                prev_t,
-                node
-            );
-            prev = (node, t);
+            if !t.fuzzy_eq(evaled) {
+                return Err(format!(
+                    "expected {:?} - got {:?} at edge {} from {} (at {:?}) to {}",
+                    evaled, t, edge, prev_node, prev_t, node
+                ));
+            }
         }
+        Ok(())
     }
 
     /// Total number of interpolation points
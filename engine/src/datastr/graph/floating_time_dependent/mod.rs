@@ -315,12 +315,23 @@ pub fn period() -> Timestamp {
     TEST_PERIOD_MOCK.with(|period_cell| period_cell.get().expect("period() used but not set"))
 }
 
+// The integer period converted to seconds, stored as the bits of an `f64` so `period()` needs no conversion.
+#[cfg(not(test))]
+static PERIOD_SECONDS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new((crate::datastr::graph::time_dependent::DAY as f64 / 1000.0).to_bits());
+
+// Called by `time_dependent::configure_period` to keep both periods in sync.
+#[cfg(not(test))]
+pub(crate) fn configure_period(period: crate::datastr::graph::time_dependent::Timestamp) {
+    PERIOD_SECONDS.store((f64::from(period) / 1000.0).to_bits(), std::sync::atomic::Ordering::Relaxed);
+}
+
 /// Travel time functions are periodic. This value is the wraparound value.
-/// Hardcoded to `86400s`, that is 1 day.
+/// Derived from the integer `period` (which is in ms), so it can be configured with `time_dependent::configure_period`.
+/// Defaults to `86400s`, that is 1 day.
 #[cfg(not(test))]
 #[inline]
 pub fn period() -> Timestamp {
-    Timestamp::new(86_400.0)
+    Timestamp::new(f64::from_bits(PERIOD_SECONDS.load(std::sync::atomic::Ordering::Relaxed)))
 }
 
 use std::sync::atomic::{AtomicIsize, AtomicUsize};
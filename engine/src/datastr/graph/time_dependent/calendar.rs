@@ -0,0 +1,71 @@
+use super::*;
+use crate::io::*;
+use std::path::Path;
+
+/// Length of a day [ms]
+pub const DAY: Timestamp = 24 * 60 * 60 * 1000;
+/// Length of a week [ms]
+pub const WEEK: Timestamp = 7 * DAY;
+
+/// Maps unix timestamps to points in time within the period.
+///
+/// For a daily period this is just the time of day, for a weekly period the time since the beginning of the week.
+/// The calendar stores which unix timestamp corresponds to the beginning of a period.
+/// Since all periods are aligned, any one of them will do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Calendar {
+    period_start: u64,
+}
+
+impl Calendar {
+    /// Calendar where the period starts at the given unix timestamp [ms] (and every multiple of the period before or after it).
+    pub fn new(period_start: u64) -> Self {
+        Calendar { period_start }
+    }
+
+    /// Calendar where periods start on Monday 0:00 local time.
+    /// `utc_offset` is the offset of the local timezone to UTC [ms], for example one hour for CET.
+    pub fn weekly(utc_offset: i64) -> Self {
+        // 1970-01-05 was the first Monday after the epoch
+        let first_monday = i64::from(4 * DAY);
+        Calendar {
+            period_start: (first_monday - utc_offset).rem_euclid(i64::from(WEEK)) as u64,
+        }
+    }
+
+    pub fn period_start(&self) -> u64 {
+        self.period_start
+    }
+
+    /// The departure time within the period for a unix timestamp [ms]
+    pub fn departure(&self, unix_timestamp: u64) -> Timestamp {
+        let period = u64::from(period());
+        ((unix_timestamp % period + period - self.period_start % period) % period) as Timestamp
+    }
+
+    /// Day of the period of a unix timestamp, that is the weekday (0 for Monday) for a weekly calendar.
+    pub fn day(&self, unix_timestamp: u64) -> u32 {
+        self.departure(unix_timestamp) / DAY
+    }
+}
+
+/// Configure the period from the `period` file of a graph directory and load the calendar from the `period_start` file.
+/// Both files are optional, without them the period stays at one day and is aligned with the unix epoch.
+/// Needs to be called before any time-dependent data of the graph is used.
+pub fn load_period_config<P: AsRef<Path>>(dir: P) -> std::io::Result<Calendar> {
+    let dir = dir.as_ref();
+    if dir.join("period").exists() {
+        configure_period(Vec::<Timestamp>::load_from(dir.join("period"))?[0]);
+    }
+    if dir.join("period_start").exists() {
+        return Ok(Calendar::new(Vec::<u64>::load_from(dir.join("period_start"))?[0]));
+    }
+    Ok(Calendar::default())
+}
+
+/// Store the current period and the calendar to a graph directory, so `load_period_config` can restore them.
+pub fn store_period_config<P: AsRef<Path>>(dir: P, calendar: &Calendar) -> std::io::Result<()> {
+    let dir = dir.as_ref();
+    vec![period()].write_to(&dir.join("period"))?;
+    vec![calendar.period_start()].write_to(&dir.join("period_start"))
+}
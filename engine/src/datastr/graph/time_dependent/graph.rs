@@ -243,6 +243,27 @@ impl LiveTDGraph {
         LiveTDGraph { graph, soon, live }
     }
 
+    /// Replace all live travel times.
+    pub fn set_live(&mut self, soon: Timestamp, live: Vec<InRangeOption<Weight>>) {
+        assert_eq!(live.len(), self.live.len());
+        self.soon = soon;
+        self.live = live;
+    }
+
+    /// The underlying predicted travel times
+    pub fn predicted(&self) -> &Graph {
+        &self.graph
+    }
+
+    pub fn soon(&self) -> Timestamp {
+        self.soon
+    }
+
+    /// The current live travel times of all arcs
+    pub fn live(&self) -> &[InRangeOption<Weight>] {
+        &self.live
+    }
+
     pub fn eval(&self, edge_id: EdgeId, t: Timestamp) -> Weight {
         let ttf = self.graph.travel_time_function(edge_id);
         let predicted = ttf.eval(t);
@@ -274,6 +295,14 @@ impl LiveTDGraph {
     }
 }
 
+impl From<Graph> for LiveTDGraph {
+    /// A graph without any live traffic.
+    fn from(graph: Graph) -> Self {
+        let live = vec![InRangeOption::new(None); graph.num_arcs()];
+        LiveTDGraph { graph, soon: 0, live }
+    }
+}
+
 impl crate::datastr::graph::Graph for LiveTDGraph {
     fn num_nodes(&self) -> usize {
         self.graph.num_nodes()
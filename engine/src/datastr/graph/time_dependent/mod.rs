@@ -16,6 +16,9 @@ mod graph;
 pub use self::graph::Graph as TDGraph;
 pub use self::graph::LiveTDGraph;
 
+mod calendar;
+pub use self::calendar::*;
+
 mod geometry;
 use self::geometry::*;
 
@@ -64,11 +67,31 @@ pub fn period() -> Timestamp {
     TEST_PERIOD_MOCK.with(|period_cell| period_cell.get().expect("period() used but not set"))
 }
 
+#[cfg(not(test))]
+static PERIOD: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(DAY);
+
 /// Travel time functions are periodic.
 /// This value is the wraparound value.
-/// Hardcoded to `86400s`, that is 1 day.
+/// Defaults to `86400s`, that is 1 day, and can be changed with `configure_period`.
 #[cfg(not(test))]
 #[inline]
-pub const fn period() -> Timestamp {
-    86_400_000
+pub fn period() -> Timestamp {
+    PERIOD.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Change the period of all travel time functions, for example to a `WEEK` to have separate profiles for each weekday.
+/// This affects both the integer and the floating point time-dependent graphs.
+/// Has to be called before any time-dependent data is loaded.
+pub fn configure_period(period: Timestamp) {
+    // arrival times are computed by adding travel times to departures, this must not overflow
+    assert!(period > 0 && period < INFINITY / 2, "invalid period {}", period);
+    #[cfg(not(test))]
+    {
+        PERIOD.store(period, std::sync::atomic::Ordering::Relaxed);
+        super::floating_time_dependent::configure_period(period);
+    }
+    #[cfg(test)]
+    unsafe {
+        set_period(period)
+    };
 }
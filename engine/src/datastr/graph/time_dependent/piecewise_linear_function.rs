@@ -1,6 +1,10 @@
 use super::math::*;
 use super::sorted_search_slice_ext::*;
 use super::*;
+use std::{
+    cmp::{max, min},
+    iter::once,
+};
 
 /// A struct borrowing data of a single PLF and exposing some methods to work with it.
 #[derive(Debug)]
@@ -54,6 +58,27 @@ impl<'a> PiecewiseLinearFunction<'a> {
         *self.travel_time.iter().min().unwrap()
     }
 
+    /// Find the lowest and highest value of the function within a given time range
+    pub fn bounds_in(&self, range: WrappingRange) -> (Weight, Weight) {
+        if self.departure_time.len() <= 2 {
+            return (self.travel_time[0], self.travel_time[0]);
+        }
+
+        let (first_range, second_range) = range.monotonize().split(period());
+        let second_range = second_range.start - period()..second_range.end - period();
+
+        let mut bounds = (INFINITY, 0);
+        for range in [first_range, second_range].iter().filter(|range| range.start < range.end) {
+            // the extrema of a PLF are either at the borders or at one of the breakpoints in between
+            let inner_ipps = self.departure_time.iter().cloned().filter(|&dt| dt > range.start && dt < range.end);
+            for t in once(range.start).chain(inner_ipps).chain(once(range.end)) {
+                let value = self.evaluate(t);
+                bounds = (min(bounds.0, value), max(bounds.1, value));
+            }
+        }
+        bounds
+    }
+
     /// Evaluate for a point in time within period!
     #[inline(always)]
     pub(super) fn evaluate(&self, departure: Timestamp) -> Weight {
@@ -184,4 +209,16 @@ mod tests {
             assert_eq!(all_ipps, vec![seg]);
         });
     }
+
+    #[test]
+    fn test_bounds_in_wrapping_range() {
+        run_test_with_periodicity(24, || {
+            let departure_time = vec![0, 5, 9, 14, 17, 20, 24];
+            let travel_time = vec![2, 1, 3, 2, 4, 1, 2];
+            let ttf = PiecewiseLinearFunction::new(&departure_time, &travel_time);
+            assert_eq!(ttf.bounds_in(WrappingRange::new(10..16)), (2, 3));
+            assert_eq!(ttf.bounds_in(WrappingRange::new(22..3)), (1, 2));
+            assert_eq!(ttf.bounds_in(WrappingRange::new(0..0)), (1, 4));
+        });
+    }
 }
@@ -42,6 +42,29 @@ pub trait Indexing {
     fn as_index(&self) -> usize;
 }
 
+/// The operations Dijkstra needs from a priority queue, so searches can be generic over the queue implementation.
+/// Elements are identified by the index from the `Indexing` trait, so each index can only be in the queue once.
+/// Besides `IndexdMinHeap`, there are monotone queues for integer keys in `datastr::bucket_queue`.
+pub trait PriorityQueue<T> {
+    /// Creates an empty queue for elements with indices in `[0, max_id)`
+    fn new(max_id: usize) -> Self;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn contains_index(&self, id: usize) -> bool;
+    fn get(&self, id: usize) -> Option<&T>;
+    fn clear(&mut self);
+    /// The element with the smallest key, or None if the queue is empty.
+    fn peek(&self) -> Option<&T>;
+    fn pop(&mut self) -> Option<T>;
+    /// Panics if an element with the same index already exists.
+    fn push(&mut self, element: T);
+    /// Updates the key of an element, the new key has to be smaller.
+    /// Undefined if the element is not part of the queue.
+    fn decrease_key(&mut self, element: T);
+}
+
 /// A priority queue where the elements are IDs from 0 to id_count-1 where id_count is a number that is set in the constructor.
 /// The elements are sorted ascending by the ordering defined by the `Ord` trait.
 /// The interface mirros the standard library BinaryHeap (except for the reversed order).
@@ -220,6 +243,36 @@ impl<T: Ord + Indexing> IndexdMinHeap<T> {
     }
 }
 
+impl<T: Ord + Indexing> PriorityQueue<T> for IndexdMinHeap<T> {
+    fn new(max_id: usize) -> Self {
+        IndexdMinHeap::new(max_id)
+    }
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn contains_index(&self, id: usize) -> bool {
+        self.contains_index(id)
+    }
+    fn get(&self, id: usize) -> Option<&T> {
+        self.get(id)
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+    fn peek(&self) -> Option<&T> {
+        self.peek()
+    }
+    fn pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+    fn push(&mut self, element: T) {
+        self.push(element)
+    }
+    fn decrease_key(&mut self, element: T) {
+        self.decrease_key(element)
+    }
+}
+
 // This is an optimization copied straight from the rust stdlib binary heap
 // it allows to avoid always swapping elements pairwise and rather
 // move each element only once.
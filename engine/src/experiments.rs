@@ -7,3 +7,6 @@ pub const NUM_DIJKSTRA_QUERIES: usize = include!(concat!(env!("OUT_DIR"), "/NUM_
 
 pub mod a_star;
 pub mod chpot;
+pub mod harness;
+pub mod queries;
+pub mod verification;
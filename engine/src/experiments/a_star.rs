@@ -118,10 +118,8 @@ pub fn run(
     let mut query_count = 0;
     let mut total_query_time = Duration::zero();
 
-    for _i in 0..NUM_QUERIES {
+    for Query { from, to } in super::queries::uniform(graph.num_nodes(), NUM_QUERIES, &mut rng) {
         let _query_ctxt = algo_runs_ctxt.push_collection_item();
-        let from: NodeId = rng.gen_range(0, graph.num_nodes() as NodeId);
-        let to: NodeId = rng.gen_range(0, graph.num_nodes() as NodeId);
 
         #[cfg(feature = "chpot-oracle")]
         {
@@ -1,8 +1,23 @@
+//! Experiments with CH potentials on a modified metric.
+//!
+//! The potentials are built on the unmodified travel times and used for queries on the modified ones.
+//! The potential is selected with the `chpot-cch`, `chpot-alt` and `chpot-only-topo` features, default are CH potentials from the `lower_bound_ch` directory.
+//! With the `chpot-bidir` feature, CH or CCH potentials are used in both directions of a bidirectional A*.
+
+#[cfg(all(feature = "chpot-bidir", any(feature = "chpot-alt", feature = "chpot-only-topo", feature = "chpot-visualize")))]
+compile_error!("chpot-bidir only works with CH or CCH potentials and without visualization");
+
+#[cfg(feature = "chpot-alt")]
+use crate::algo::ch_potentials::alt::*;
+#[cfg(feature = "chpot-bidir")]
+use crate::algo::ch_potentials::bidirectional_query::Server as BidirServer;
+#[cfg(not(feature = "chpot-bidir"))]
+use crate::algo::ch_potentials::query::Server as TopoServer;
 #[cfg(feature = "chpot-cch")]
 use crate::algo::customizable_contraction_hierarchy::*;
 use crate::{
     algo::{
-        ch_potentials::{query::Server as TopoServer, *},
+        ch_potentials::*,
         dijkstra::{generic_dijkstra::DefaultOps, query::dijkstra::Server as DijkServer},
         *,
     },
@@ -22,6 +37,12 @@ pub const NUM_QUERIES: usize = 10000;
 #[cfg(override_chpot_num_queries)]
 pub const NUM_QUERIES: usize = include!(concat!(env!("OUT_DIR"), "/CHPOT_NUM_QUERIES"));
 
+/// Number of landmarks selected when running with ALT instead of CH potentials (feature `chpot-alt`).
+/// The landmarks are stored in the `alt` subdirectory of the graph and reused in later runs.
+pub const NUM_LANDMARKS: usize = 16;
+/// Number of landmarks used in each query.
+pub const NUM_ACTIVE_LANDMARKS: usize = 4;
+
 pub fn run(
     path: &Path,
     modify_travel_time: impl FnOnce(&FirstOutGraph<&[EdgeId], &[NodeId], &[Weight]>, &mut StdRng, &mut [Weight]) -> Result<(), Box<dyn Error>>,
@@ -82,25 +103,43 @@ pub fn run(
         }
         #[cfg(not(feature = "chpot-only-topo"))]
         {
-            #[cfg(feature = "chpot-cch")]
+            #[cfg(feature = "chpot-alt")]
+            {
+                let landmarks_dir = path.join("alt");
+                let landmarks = if landmarks_dir.exists() {
+                    let landmarks = Landmarks::reconstruct_from(&landmarks_dir)?;
+                    if landmarks.num_nodes() != graph.num_nodes() {
+                        return Err(format!(
+                            "landmarks in {} are for a graph with {} nodes, but the graph has {} nodes, remove them to recompute",
+                            landmarks_dir.display(),
+                            landmarks.num_nodes(),
+                            graph.num_nodes()
+                        )
+                        .into());
+                    }
+                    landmarks
+                } else {
+                    let _landmarks_ctxt = algo_runs_ctxt.push_collection_item();
+                    // separate rng, so the queries are the same as for the other potentials
+                    let mut landmark_rng = StdRng::from_seed(seed);
+                    let landmarks = report_time_with_key("ALT preprocessing", "alt_prepro", || {
+                        Landmarks::new(&graph, NUM_LANDMARKS, LandmarkSelection::Avoid, &[], &[], &mut landmark_rng)
+                    });
+                    std::fs::create_dir(&landmarks_dir)?;
+                    landmarks.deconstruct_to(&landmarks_dir)?;
+                    landmarks
+                };
+                ALTPotential::new(landmarks, NUM_ACTIVE_LANDMARKS)
+            }
+            #[cfg(all(feature = "chpot-cch", not(feature = "chpot-alt")))]
             {
                 let _potential_ctxt = algo_runs_ctxt.push_collection_item();
                 CCHPotential::new(&cch, &graph)
             }
-            #[cfg(not(feature = "chpot-cch"))]
+            #[cfg(not(any(feature = "chpot-cch", feature = "chpot-alt")))]
             {
-                let forward_first_out = Vec::<EdgeId>::load_from(path.join("lower_bound_ch/forward_first_out"))?;
-                let forward_head = Vec::<NodeId>::load_from(path.join("lower_bound_ch/forward_head"))?;
-                let forward_weight = Vec::<Weight>::load_from(path.join("lower_bound_ch/forward_weight"))?;
-                let backward_first_out = Vec::<EdgeId>::load_from(path.join("lower_bound_ch/backward_first_out"))?;
-                let backward_head = Vec::<NodeId>::load_from(path.join("lower_bound_ch/backward_head"))?;
-                let backward_weight = Vec::<Weight>::load_from(path.join("lower_bound_ch/backward_weight"))?;
-                let order = NodeOrder::from_node_order(Vec::<NodeId>::load_from(path.join("lower_bound_ch/order"))?);
-                CHPotential::new(
-                    OwnedGraph::new(forward_first_out, forward_head, forward_weight),
-                    OwnedGraph::new(backward_first_out, backward_head, backward_weight),
-                    order,
-                )
+                let (forward, backward, order) = load_lower_bound_ch(path)?;
+                CHPotential::new(forward, backward, order)
             }
         }
     };
@@ -115,8 +154,33 @@ pub fn run(
         }
     };
 
+    #[cfg(feature = "chpot-bidir")]
+    let mut topocore = {
+        // lower bounds from the source for the backward search
+        let backward_potential = {
+            #[cfg(feature = "chpot-cch")]
+            {
+                let _potential_ctxt = algo_runs_ctxt.push_collection_item();
+                CCHPotential::new_reversed(&cch, &graph)
+            }
+            #[cfg(not(feature = "chpot-cch"))]
+            {
+                let (forward, backward, order) = load_lower_bound_ch(path)?;
+                CHPotential::new(backward, forward, order)
+            }
+        };
+        #[cfg(feature = "chpot-oracle")]
+        let backward_potential = RecyclingPotential::new(backward_potential);
+
+        let _bidir_ctxt = algo_runs_ctxt.push_collection_item();
+        BidirServer::new(InfinityFilteringGraph(modified_graph.clone()), potential, backward_potential)
+    };
+
+    #[cfg(not(feature = "chpot-bidir"))]
     let virtual_topocore_ctxt = algo_runs_ctxt.push_collection_item();
+    #[cfg(not(feature = "chpot-bidir"))]
     let infinity_filtered_graph = InfinityFilteringGraph(modified_graph);
+    #[cfg(not(feature = "chpot-bidir"))]
     let mut topocore: TopoServer<_, _, OwnedGraph> = {
         #[cfg(feature = "chpot-visualize")]
         {
@@ -127,16 +191,16 @@ pub fn run(
             TopoServer::new(&infinity_filtered_graph, potential, DefaultOps::default())
         }
     };
+    #[cfg(not(feature = "chpot-bidir"))]
     let InfinityFilteringGraph(modified_graph) = infinity_filtered_graph;
+    #[cfg(not(feature = "chpot-bidir"))]
     drop(virtual_topocore_ctxt);
 
     let mut query_count = 0;
     let mut total_query_time = Duration::zero();
 
-    for _i in 0..NUM_QUERIES {
+    for Query { from, to } in super::queries::uniform(graph.num_nodes(), NUM_QUERIES, &mut rng) {
         let _query_ctxt = algo_runs_ctxt.push_collection_item();
-        let from: NodeId = rng.gen_range(0, graph.num_nodes() as NodeId);
-        let to: NodeId = rng.gen_range(0, graph.num_nodes() as NodeId);
 
         #[cfg(feature = "chpot-oracle")]
         {
@@ -164,10 +228,8 @@ pub fn run(
 
     let mut server = DijkServer::<DefaultOps, _, _>::new(modified_graph);
 
-    for _i in 0..super::NUM_DIJKSTRA_QUERIES {
+    for Query { from, to } in super::queries::uniform(graph.num_nodes(), super::NUM_DIJKSTRA_QUERIES, &mut rng) {
         let _query_ctxt = algo_runs_ctxt.push_collection_item();
-        let from: NodeId = rng.gen_range(0, graph.num_nodes() as NodeId);
-        let to: NodeId = rng.gen_range(0, graph.num_nodes() as NodeId);
 
         report!("from", from);
         report!("to", to);
@@ -182,3 +244,20 @@ pub fn run(
 
     Ok(())
 }
+
+// Load the CH on the lower bound metric from the `lower_bound_ch` subdirectory, returns the forward and backward graph and the order.
+#[cfg(not(any(feature = "chpot-cch", feature = "chpot-alt", feature = "chpot-only-topo")))]
+fn load_lower_bound_ch(path: &Path) -> Result<(OwnedGraph, OwnedGraph, NodeOrder), Box<dyn Error>> {
+    let forward_first_out = Vec::<EdgeId>::load_from(path.join("lower_bound_ch/forward_first_out"))?;
+    let forward_head = Vec::<NodeId>::load_from(path.join("lower_bound_ch/forward_head"))?;
+    let forward_weight = Vec::<Weight>::load_from(path.join("lower_bound_ch/forward_weight"))?;
+    let backward_first_out = Vec::<EdgeId>::load_from(path.join("lower_bound_ch/backward_first_out"))?;
+    let backward_head = Vec::<NodeId>::load_from(path.join("lower_bound_ch/backward_head"))?;
+    let backward_weight = Vec::<Weight>::load_from(path.join("lower_bound_ch/backward_weight"))?;
+    let order = NodeOrder::from_node_order(Vec::<NodeId>::load_from(path.join("lower_bound_ch/order"))?);
+    Ok((
+        OwnedGraph::new(forward_first_out, forward_head, forward_weight),
+        OwnedGraph::new(backward_first_out, backward_head, backward_weight),
+        order,
+    ))
+}
@@ -0,0 +1,379 @@
+//! Declarative benchmarking harness.
+//!
+//! An `Experiment` describes what to run: the graph directory, the algorithm, how the metric should be modified,
+//! the queries and how often each query should be repeated.
+//! Experiments can be built in code or parsed from JSON, for example
+//!
+//! ```json
+//! {
+//!   "name": "alt_scaled",
+//!   "graph": "/data/ger",
+//!   "algorithm": { "alt": { "landmarks": 16, "active": 4 } },
+//!   "modification": { "scale": 1.05 },
+//!   "queries": { "random": 1000 },
+//!   "repetitions": 3,
+//!   "unify_parallel_edges": true
+//! }
+//! ```
+//!
+//! Running an experiment loads the graph, performs the preprocessing and then all queries.
+//! Everything is reported with the `report` infrastructure in the following schema (version `SCHEMA_VERSION`):
+//! `schema_version`, `experiment`, `algorithm`, `modification`, `repetitions`, `graph` (`num_nodes`, `num_arcs`),
+//! `preprocessing_running_time_ms`, `algo_runs` (one item per query run with `repetition`, `from`, `to`, `running_time_ms` and `result`,
+//! where `result` is `null` for unreachable targets), and finally `num_queries` and `avg_running_time_ms`.
+//!
+//! Two flags change the measured graph and the output and are off by default:
+//! `unify_parallel_edges` keeps only the shortest of several parallel arcs before anything else happens
+//! (reflected in the reported `num_arcs`), and `unreachable_as_infinity` reports `INFINITY` instead of `null` as `result`
+//! of unreachable queries, which is what the standalone benchmark binaries did before the harness existed.
+//!
+//! For potential based algorithms, the potential is always built on the unmodified metric and the queries run on the modified one.
+//! Since potentials need lower bounds, modifications for these algorithms should only increase weights.
+
+use crate::{
+    algo::{
+        ch_potentials::{alt::*, query::Server as TopoServer, *},
+        customizable_contraction_hierarchy::{query::Server as CCHServer, *},
+        dijkstra::{
+            query::{bidirectional_dijkstra::Server as BiDijkServer, dijkstra::Server as DijkServer},
+            DefaultOps,
+        },
+        topocore::{self, query::Server as TopocoreServer},
+        *,
+    },
+    cli::CliErr,
+    datastr::graph::*,
+    io::*,
+    report::*,
+    util::True,
+};
+use rand::prelude::*;
+use serde_json::Value;
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+/// Version of the reported JSON schema, will be increased on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The algorithm to benchmark
+#[derive(Debug, Clone, PartialEq)]
+pub enum Algorithm {
+    Dijkstra,
+    BidirectionalDijkstra,
+    /// Requires a `cch_perm` file with a nested dissection order in the graph directory
+    CCH,
+    /// Bidirectional Dijkstra on the core after removing nodes of low degree and other biconnected components
+    Topocore,
+    /// A* with CCH potentials on the topocore, requires `cch_perm`
+    CHPotentials,
+    /// A* with landmarks on the topocore
+    ALT {
+        landmarks: usize,
+        active: usize,
+    },
+    /// A* with the geometric potential on the topocore, requires `latitude` and `longitude`
+    Geo,
+}
+
+/// Modification of the metric before the queries are run
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricModification {
+    None,
+    /// Multiply all weights by a factor
+    Scale(f64),
+    /// Block all arcs where the `arc_category` file has one of the given bits set
+    BlockCategories(u8),
+}
+
+/// Where the queries come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuerySet {
+    /// Uniformly random source target pairs, generated from the seed of the experiment
+    Random(usize),
+    /// `source` and `target` files in the given directory
+    Stored(PathBuf),
+}
+
+/// A complete description of an experiment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Experiment {
+    pub name: String,
+    pub graph_dir: PathBuf,
+    pub algorithm: Algorithm,
+    pub modification: MetricModification,
+    pub queries: QuerySet,
+    pub repetitions: usize,
+    pub seed: [u8; 32],
+    /// Remove all but the shortest of parallel arcs before preprocessing
+    pub unify_parallel_edges: bool,
+    /// Report unreachable results as `INFINITY` instead of `null`
+    pub unreachable_as_infinity: bool,
+}
+
+/// The outcome of an experiment, the same data which gets reported, but directly accessible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentResult {
+    /// Distances of each query (of the first repetition)
+    pub results: Vec<Option<Weight>>,
+    pub num_query_runs: usize,
+    pub total_query_time_ms: f64,
+}
+
+impl Experiment {
+    /// Experiment with a single run of 1000 random queries without metric modifications on the graph as it is stored
+    pub fn new(name: impl Into<String>, graph_dir: impl Into<PathBuf>, algorithm: Algorithm) -> Self {
+        Experiment {
+            name: name.into(),
+            graph_dir: graph_dir.into(),
+            algorithm,
+            modification: MetricModification::None,
+            queries: QuerySet::Random(1000),
+            repetitions: 1,
+            seed: Default::default(),
+            unify_parallel_edges: false,
+            unreachable_as_infinity: false,
+        }
+    }
+
+    /// Parse an experiment from its JSON description.
+    /// Only `graph` and `algorithm` are mandatory.
+    /// Relative paths are resolved relative to `base_dir`.
+    pub fn from_json(config: &Value, base_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let graph_dir = config["graph"].as_str().ok_or(CliErr("experiment without graph"))?;
+        let algorithm = match &config["algorithm"] {
+            Value::String(name) => match name.as_str() {
+                "dijkstra" => Algorithm::Dijkstra,
+                "bidir_dijkstra" => Algorithm::BidirectionalDijkstra,
+                "cch" => Algorithm::CCH,
+                "topocore" => Algorithm::Topocore,
+                "chpot" => Algorithm::CHPotentials,
+                "alt" => Algorithm::ALT { landmarks: 16, active: 4 },
+                "geo" => Algorithm::Geo,
+                _ => return Err(format!("unknown algorithm {}", name).into()),
+            },
+            Value::Object(params) if params.contains_key("alt") => Algorithm::ALT {
+                landmarks: params["alt"]["landmarks"].as_u64().unwrap_or(16) as usize,
+                active: params["alt"]["active"].as_u64().unwrap_or(4) as usize,
+            },
+            _ => return Err(Box::new(CliErr("invalid or missing algorithm"))),
+        };
+        let modification = match &config["modification"] {
+            Value::Null => MetricModification::None,
+            Value::Object(params) if params.contains_key("scale") => {
+                MetricModification::Scale(params["scale"].as_f64().ok_or(CliErr("scale factor has to be a number"))?)
+            }
+            Value::Object(params) if params.contains_key("block_categories") => {
+                MetricModification::BlockCategories(params["block_categories"].as_u64().ok_or(CliErr("blocked categories have to be a bitmask"))? as u8)
+            }
+            _ => return Err(Box::new(CliErr("invalid metric modification"))),
+        };
+        let queries = match &config["queries"] {
+            Value::Null => QuerySet::Random(1000),
+            Value::Object(params) if params.contains_key("random") => {
+                QuerySet::Random(params["random"].as_u64().ok_or(CliErr("number of queries has to be a number"))? as usize)
+            }
+            Value::Object(params) if params.contains_key("dir") => {
+                QuerySet::Stored(base_dir.join(params["dir"].as_str().ok_or(CliErr("query dir has to be a path"))?))
+            }
+            _ => return Err(Box::new(CliErr("invalid query set"))),
+        };
+        let mut seed: [u8; 32] = Default::default();
+        if let Some(seed_value) = config["seed"].as_u64() {
+            seed[..8].copy_from_slice(&seed_value.to_le_bytes());
+        }
+
+        Ok(Experiment {
+            name: config["name"].as_str().unwrap_or("").to_string(),
+            graph_dir: base_dir.join(graph_dir),
+            algorithm,
+            modification,
+            queries,
+            repetitions: config["repetitions"].as_u64().unwrap_or(1) as usize,
+            seed,
+            unify_parallel_edges: config["unify_parallel_edges"].as_bool().unwrap_or(false),
+            unreachable_as_infinity: config["unreachable_as_infinity"].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Load a JSON file with either a single experiment or an array of experiments.
+    pub fn load_all(path: &Path) -> Result<Vec<Self>, Box<dyn Error>> {
+        let config: Value = serde_json::from_reader(std::fs::File::open(path)?)?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        match config {
+            Value::Array(experiments) => experiments.iter().map(|experiment| Self::from_json(experiment, base_dir)).collect(),
+            experiment => Ok(vec![Self::from_json(&experiment, base_dir)?]),
+        }
+    }
+
+    /// Run the experiment and report everything.
+    pub fn run(&self) -> Result<ExperimentResult, Box<dyn Error>> {
+        report!("schema_version", SCHEMA_VERSION);
+        report!("experiment", self.name);
+        report!("algorithm", format!("{:?}", self.algorithm));
+        report!("modification", format!("{:?}", self.modification));
+        report!("repetitions", self.repetitions);
+        report!("seed", self.seed);
+        report!("unify_parallel_edges", self.unify_parallel_edges);
+
+        let graph_dir = GraphDirectory::open(&self.graph_dir)?;
+        let first_out = graph_dir.first_out()?;
+        let head = graph_dir.head()?;
+        let mut travel_time = graph_dir.travel_time()?.to_vec();
+        if self.unify_parallel_edges {
+            unify_parallel_edges(&mut FirstOutGraph::new(&first_out[..], &head[..], &mut travel_time[..]));
+        }
+        let graph = FirstOutGraph::new(&first_out[..], &head[..], &travel_time[..]);
+        report!("graph", { "num_nodes": graph.num_nodes(), "num_arcs": graph.num_arcs() });
+
+        let mut modified_travel_time = travel_time.clone();
+        self.modify(&graph_dir, &mut modified_travel_time)?;
+        let modified_graph = FirstOutGraph::new(&first_out[..], &head[..], &modified_travel_time[..]);
+
+        let queries = self.queries(graph.num_nodes())?;
+
+        match &self.algorithm {
+            Algorithm::Dijkstra => {
+                let mut server = report_time_with_key("preprocessing", "preprocessing", || DijkServer::<DefaultOps, _, _>::new(modified_graph));
+                self.run_queries(&queries, |query| QueryServer::query(&mut server, query).map(|res| res.distance()))
+            }
+            Algorithm::BidirectionalDijkstra => {
+                let mut server = report_time_with_key("preprocessing", "preprocessing", || BiDijkServer::new(modified_graph));
+                self.run_queries(&queries, |query| server.query(query).map(|res| res.distance()))
+            }
+            Algorithm::CCH => {
+                let order = graph_dir.cch_order()?;
+                let cch = report_time_with_key("preprocessing", "preprocessing", || contract(&graph, order));
+                let mut server = report_time_with_key("customization", "customization", || CCHServer::new(customize(&cch, &modified_graph)));
+                self.run_queries(&queries, |query| server.query(query).map(|res| res.distance()))
+            }
+            Algorithm::Topocore => {
+                let mut server = report_time_with_key("preprocessing", "preprocessing", || {
+                    TopocoreServer::new(topocore::preprocess::<_, True, True, True, True>(&modified_graph))
+                });
+                self.run_queries(&queries, |query| server.query(query).map(|res| res.distance()))
+            }
+            Algorithm::CHPotentials => {
+                let order = graph_dir.cch_order()?;
+                let cch = report_time_with_key("preprocessing", "preprocessing", || contract(&graph, order));
+                let potential = report_time_with_key("customization", "customization", || CCHPotential::new(&cch, &graph));
+                self.run_potential_queries(&queries, &modified_graph, potential)
+            }
+            Algorithm::ALT { landmarks, active } => {
+                let mut rng = StdRng::from_seed(self.seed);
+                let potential = report_time_with_key("preprocessing", "preprocessing", || {
+                    ALTPotential::new(Landmarks::new(&graph, *landmarks, LandmarkSelection::Avoid, &[], &[], &mut rng), *active)
+                });
+                self.run_potential_queries(&queries, &modified_graph, potential)
+            }
+            Algorithm::Geo => {
+                let lat = graph_dir.latitude()?;
+                let lng = graph_dir.longitude()?;
+                let potential = report_time_with_key("preprocessing", "preprocessing", || GeoPotential::new(&graph, &lat, &lng));
+                self.run_potential_queries(&queries, &modified_graph, potential)
+            }
+        }
+    }
+
+    fn modify(&self, graph_dir: &GraphDirectory, travel_time: &mut [Weight]) -> Result<(), Box<dyn Error>> {
+        match self.modification {
+            MetricModification::None => (),
+            MetricModification::Scale(factor) => {
+                for weight in travel_time.iter_mut() {
+                    *weight = std::cmp::min((f64::from(*weight) * factor) as Weight, INFINITY);
+                }
+            }
+            MetricModification::BlockCategories(bits) => {
+                let arc_category = graph_dir.arc_category()?;
+                for (weight, &category) in travel_time.iter_mut().zip(arc_category.iter()) {
+                    if (category & bits) != 0 {
+                        *weight = INFINITY;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn queries(&self, num_nodes: usize) -> Result<Vec<Query>, Box<dyn Error>> {
+        match &self.queries {
+            QuerySet::Random(num_queries) => Ok(super::queries::uniform(num_nodes, *num_queries, &mut StdRng::from_seed(self.seed))),
+            QuerySet::Stored(dir) => Ok(super::queries::load_queries(dir)?.collect()),
+        }
+    }
+
+    fn run_potential_queries<P: Potential>(
+        &self,
+        queries: &[Query],
+        modified_graph: &FirstOutGraph<&[EdgeId], &[NodeId], &[Weight]>,
+        potential: P,
+    ) -> Result<ExperimentResult, Box<dyn Error>> {
+        let infinity_filtered_graph = InfinityFilteringGraph(modified_graph.clone());
+        let mut server: TopoServer<_, _, OwnedGraph> = report_time_with_key("topocore preprocessing", "topocore", || {
+            TopoServer::new(&infinity_filtered_graph, potential, DefaultOps::default())
+        });
+        self.run_queries(queries, |query| QueryServer::query(&mut server, query).map(|res| res.distance()))
+    }
+
+    fn run_queries(&self, queries: &[Query], mut query_server: impl FnMut(Query) -> Option<Weight>) -> Result<ExperimentResult, Box<dyn Error>> {
+        let mut results = Vec::with_capacity(queries.len());
+        let mut total_query_time_ms = 0.0;
+        let mut num_query_runs = 0;
+
+        {
+            let mut algo_runs_ctxt = push_collection_context("algo_runs".to_string());
+            for repetition in 0..self.repetitions {
+                for query in queries {
+                    let _query_ctxt = algo_runs_ctxt.push_collection_item();
+                    report!("repetition", repetition);
+                    report!("from", query.from);
+                    report!("to", query.to);
+
+                    let (result, time) = measure(|| query_server(*query));
+                    let running_time_ms = time.to_std().unwrap().as_nanos() as f64 / 1_000_000.0;
+                    report!("running_time_ms", running_time_ms);
+                    if self.unreachable_as_infinity {
+                        report!("result", result.unwrap_or(INFINITY));
+                    } else {
+                        report!("result", result);
+                    }
+
+                    total_query_time_ms += running_time_ms;
+                    num_query_runs += 1;
+                    if repetition == 0 {
+                        results.push(result);
+                    }
+                }
+            }
+        }
+
+        report!("num_queries", num_query_runs);
+        report!(
+            "avg_running_time_ms",
+            if num_query_runs > 0 {
+                total_query_time_ms / num_query_runs as f64
+            } else {
+                0.0
+            }
+        );
+
+        Ok(ExperimentResult {
+            results,
+            num_query_runs,
+            total_query_time_ms,
+        })
+    }
+}
+
+/// Run several experiments, each reported as one item of an `experiments` collection.
+pub fn run_all(experiments: &[Experiment]) -> Result<Vec<ExperimentResult>, Box<dyn Error>> {
+    let mut exps_ctxt = push_collection_context("experiments".to_string());
+    experiments
+        .iter()
+        .map(|experiment| {
+            let _exp_ctxt = exps_ctxt.push_collection_item();
+            experiment.run()
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+//! Generation and persistence of query sets.
+//!
+//! All generators are deterministic for a given rng state, so an experiment can be repeated with the same seed
+//! or the generated queries can be stored once and then used for all algorithms.
+//! Queries are stored in a directory with `source`, `target` and optionally `departure` and `rank` files.
+
+use crate::{
+    algo::{
+        dijkstra::{query::dijkstra::Server as DijkServer, DefaultOps},
+        *,
+    },
+    datastr::graph::{
+        time_dependent::{period, Timestamp},
+        *,
+    },
+    io::*,
+    util::{great_circle_distance, NonNan, EARTH_RADIUS},
+};
+use rand::prelude::*;
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+/// Source and target drawn uniformly at random
+pub fn uniform<R: Rng>(num_nodes: usize, num_queries: usize, rng: &mut R) -> Vec<Query> {
+    (0..num_queries)
+        .map(|_| Query {
+            from: rng.gen_range(0, num_nodes as NodeId),
+            to: rng.gen_range(0, num_nodes as NodeId),
+        })
+        .collect()
+}
+
+/// Dijkstra rank queries: for each of `num_sources` random sources, a Dijkstra search is run
+/// and the nodes settled as the 2^i-th node become targets.
+/// Returns the queries together with their rank i.
+pub fn dijkstra_rank<G, R>(graph: G, num_sources: usize, rng: &mut R) -> Vec<(Query, u32)>
+where
+    G: for<'a> LinkIterable<'a, Link>,
+    R: Rng,
+{
+    let num_nodes = graph.num_nodes();
+    let mut server = DijkServer::<DefaultOps, _, _>::new(graph);
+    let mut queries = Vec::new();
+
+    for _ in 0..num_sources {
+        let from = rng.gen_range(0, num_nodes as NodeId);
+        server.ranks(from, |to, _dist, rank| queries.push((Query { from, to }, rank as u32)));
+    }
+
+    queries
+}
+
+/// Geographically local queries: the source is drawn uniformly at random,
+/// the target uniformly from all nodes within `radius` meters of the source.
+/// Coordinates are in degrees.
+///
+/// Nodes are sorted by latitude once, so each query only has to check the nodes in the latitude band around its source
+/// instead of all nodes. The band still spans all longitudes, so large radii or graphs stretching mostly east to west
+/// degrade towards a full scan per query.
+pub fn local<R: Rng>(lat: &[f32], lng: &[f32], radius: f64, num_queries: usize, rng: &mut R) -> Vec<Query> {
+    assert_eq!(lat.len(), lng.len());
+    let mut by_lat: Vec<NodeId> = (0..lat.len() as NodeId).collect();
+    by_lat.sort_by_key(|&node| NonNan::new(lat[node as usize]).expect("latitude must not be NaN"));
+    let sorted_lat: Vec<f64> = by_lat.iter().map(|&node| f64::from(lat[node as usize])).collect();
+    // no node further than this from the source latitude can be within the radius, a little slack for rounding errors
+    let max_lat_diff = (radius / EARTH_RADIUS).to_degrees() + 1e-6;
+    let mut candidates = Vec::new();
+
+    (0..num_queries)
+        .map(|_| {
+            let from = rng.gen_range(0, lat.len() as NodeId);
+            let source = (lat[from as usize], lng[from as usize]);
+            let band_start = sorted_lat.partition_point(|&node_lat| node_lat < f64::from(source.0) - max_lat_diff);
+            let band_end = sorted_lat.partition_point(|&node_lat| node_lat <= f64::from(source.0) + max_lat_diff);
+            candidates.clear();
+            candidates.extend(
+                by_lat[band_start..band_end]
+                    .iter()
+                    .filter(|&&node| great_circle_distance(source, (lat[node as usize], lng[node as usize])) <= radius),
+            );
+            // same candidate order as a scan over all node ids, so the generated queries do not depend on the index
+            candidates.sort_unstable();
+            // the source itself is always a candidate
+            let to = candidates[rng.gen_range(0, candidates.len())];
+            Query { from, to }
+        })
+        .collect()
+}
+
+/// Turn static queries into time-dependent ones with departure times drawn uniformly from the current period.
+pub fn with_departures<R: Rng>(queries: impl IntoIterator<Item = Query>, rng: &mut R) -> Vec<TDQuery<Timestamp>> {
+    queries
+        .into_iter()
+        .map(|Query { from, to }| TDQuery {
+            from,
+            to,
+            departure: rng.gen_range(0, period()),
+        })
+        .collect()
+}
+
+/// Store queries as `source` and `target` files.
+pub fn store_queries(dir: &Path, queries: &[Query]) -> Result<()> {
+    store_endpoints(dir, queries.iter().map(|query| (query.from, query.to)))
+}
+
+/// Store time-dependent queries as `source`, `target` and `departure` files.
+pub fn store_td_queries(dir: &Path, queries: &[TDQuery<Timestamp>]) -> Result<()> {
+    store_endpoints(dir, queries.iter().map(|query| (query.from, query.to)))?;
+    queries.iter().map(|query| query.departure).collect::<Vec<_>>().write_to(&dir.join("departure"))
+}
+
+/// Store rank queries as `source`, `target` and `rank` files.
+pub fn store_rank_queries(dir: &Path, queries: &[(Query, u32)]) -> Result<()> {
+    store_endpoints(dir, queries.iter().map(|(query, _)| (query.from, query.to)))?;
+    queries.iter().map(|&(_, rank)| rank).collect::<Vec<_>>().write_to(&dir.join("rank"))
+}
+
+fn store_endpoints(dir: &Path, endpoints: impl Iterator<Item = (NodeId, NodeId)>) -> Result<()> {
+    let (source, target): (Vec<NodeId>, Vec<NodeId>) = endpoints.unzip();
+    source.write_to(&dir.join("source"))?;
+    target.write_to(&dir.join("target"))
+}
+
+/// Load queries from `source` and `target` files.
+pub fn load_queries(dir: &Path) -> Result<impl Iterator<Item = Query>> {
+    let source = Vec::<NodeId>::load_from(dir.join("source"))?;
+    let target = Vec::<NodeId>::load_from(dir.join("target"))?;
+    check_len(&source, target.len())?;
+    Ok(source.into_iter().zip(target).map(|(from, to)| Query { from, to }))
+}
+
+/// Load time-dependent queries from `source`, `target` and `departure` files.
+pub fn load_td_queries(dir: &Path) -> Result<impl Iterator<Item = TDQuery<Timestamp>>> {
+    let departure = Vec::<Timestamp>::load_from(dir.join("departure"))?;
+    let queries = load_queries(dir)?;
+    check_len(&departure, queries.size_hint().0)?;
+    Ok(queries.zip(departure).map(|(Query { from, to }, departure)| TDQuery { from, to, departure }))
+}
+
+/// Load the `rank` file of rank queries.
+pub fn load_ranks(dir: &Path) -> Result<Vec<u32>> {
+    Vec::load_from(dir.join("rank"))
+}
+
+fn check_len<T>(data: &[T], expected: usize) -> Result<()> {
+    if data.len() != expected {
+        return Err(Error::new(ErrorKind::InvalidData, "query files of different length"));
+    }
+    Ok(())
+}
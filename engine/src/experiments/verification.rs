@@ -0,0 +1,340 @@
+//! Cross-checking query algorithms against each other.
+//!
+//! A reference server (usually some Dijkstra variant) and a candidate server answer the same queries.
+//! For each query, the distances have to match, the paths of both servers have to exist in the graph,
+//! connect source and target and have a length equal to the reported distance.
+//! Instead of panicking at the first problem, all mismatches are collected, so they can be reported together.
+//! Each mismatch contains the query, so it can be reproduced in isolation.
+//!
+//! The `verify*` functions run complete query sets.
+//! Servers which borrow their data (like CATCHUp) do not fulfill the trait bounds of these functions,
+//! in that case, run the queries yourself and pass the results to the `check*` methods of `Verification`.
+
+use crate::{
+    algo::*,
+    datastr::graph::{floating_time_dependent as floating, time_dependent::*, *},
+};
+use std::fmt::{Debug, Display, Formatter};
+
+/// What went wrong for a single query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatch<W> {
+    /// The servers disagree on the distance
+    Distance { reference: Option<W>, candidate: Option<W> },
+    /// A path does not start at the source, end at the target or contains an arc which does not exist
+    InvalidPath { server: &'static str, path: Vec<NodeId>, reason: String },
+    /// The length of a path does not match the distance reported for it
+    PathLength {
+        server: &'static str,
+        path: Vec<NodeId>,
+        distance: W,
+        path_length: W,
+    },
+}
+
+/// A mismatch together with the query to reproduce it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure<Q, W> {
+    pub query: Q,
+    pub mismatch: Mismatch<W>,
+}
+
+impl<Q: Debug, W: Debug> Display for Failure<Q, W> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match &self.mismatch {
+            Mismatch::Distance { reference, candidate } => write!(f, "{:?}: expected distance {:?}, got {:?}", self.query, reference, candidate),
+            Mismatch::InvalidPath { server, path, reason } => write!(f, "{:?}: invalid {} path {:?}: {}", self.query, server, path, reason),
+            Mismatch::PathLength {
+                server,
+                path,
+                distance,
+                path_length,
+            } => write!(
+                f,
+                "{:?}: {} distance {:?} but path {:?} has length {:?}",
+                self.query, server, distance, path, path_length
+            ),
+        }
+    }
+}
+
+/// Outcome of a verification run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification<Q, W> {
+    pub num_queries: usize,
+    pub failures: Vec<Failure<Q, W>>,
+}
+
+impl<Q: Debug + Copy, W: Debug + PartialEq> Verification<Q, W> {
+    pub fn new() -> Self {
+        Verification {
+            num_queries: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Panic with a list of all failures, if there were any.
+    pub fn assert_ok(&self) {
+        if !self.is_ok() {
+            let failures: Vec<String> = self.failures.iter().map(ToString::to_string).collect();
+            panic!("{} of {} queries failed:\n{}", self.failures.len(), self.num_queries, failures.join("\n"));
+        }
+    }
+
+    fn fail(&mut self, query: Q, mismatch: Mismatch<W>) {
+        self.failures.push(Failure { query, mismatch });
+    }
+
+    fn check_distances(&mut self, query: Q, reference: Option<W>, candidate: Option<W>, eq: impl Fn(&W, &W) -> bool) {
+        self.num_queries += 1;
+        let distances_match = match (&reference, &candidate) {
+            (Some(reference), Some(candidate)) => eq(reference, candidate),
+            (None, None) => true,
+            _ => false,
+        };
+        if !distances_match {
+            self.fail(query, Mismatch::Distance { reference, candidate });
+        }
+    }
+
+    fn check_path(&mut self, query: Q, server: &'static str, path: Vec<NodeId>, distance: W, path_length: W, invalid: Option<String>) {
+        if let Some(reason) = invalid {
+            self.fail(query, Mismatch::InvalidPath { server, path, reason });
+        } else if path_length != distance {
+            self.fail(
+                query,
+                Mismatch::PathLength {
+                    server,
+                    path,
+                    distance,
+                    path_length,
+                },
+            );
+        }
+    }
+}
+
+impl<Q: Debug + Copy, W: Debug + PartialEq> Default for Verification<Q, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a time-dependent server reports distances.
+/// Most servers report travel times, some report earliest arrival times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TDDistance {
+    TravelTime,
+    Arrival,
+}
+
+impl TDDistance {
+    /// Convert a reported distance into a travel time
+    pub fn travel_time(self, distance: Weight, departure: Timestamp) -> Weight {
+        match self {
+            TDDistance::TravelTime => distance,
+            TDDistance::Arrival => distance - departure,
+        }
+    }
+}
+
+impl Verification<Query, Weight> {
+    /// Check the distances and paths two static servers returned for a query.
+    pub fn check<G: RandomLinkAccessGraph>(
+        &mut self,
+        graph: &G,
+        query: Query,
+        reference: Option<(Weight, Vec<NodeId>)>,
+        candidate: Option<(Weight, Vec<NodeId>)>,
+    ) {
+        self.check_distances(
+            query,
+            reference.as_ref().map(|(dist, _)| *dist),
+            candidate.as_ref().map(|(dist, _)| *dist),
+            Weight::eq,
+        );
+
+        for (server, result) in [("reference", reference), ("candidate", candidate)] {
+            if let Some((distance, path)) = result {
+                let mut path_length = 0;
+                let mismatch = check_endpoints(&path, query.from, query.to).or_else(|| {
+                    for hop in path.windows(2) {
+                        let weight = graph
+                            .neighbor_edge_indices(hop[0])
+                            .map(|edge_id| graph.link(edge_id))
+                            .filter(|link| link.node == hop[1])
+                            .map(|link| link.weight)
+                            .min();
+                        match weight {
+                            Some(weight) => path_length += weight,
+                            None => return Some(missing_arc(hop[0], hop[1])),
+                        }
+                    }
+                    None
+                });
+                self.check_path(query, server, path, distance, path_length, mismatch);
+            }
+        }
+    }
+}
+
+impl Verification<TDQuery<Timestamp>, Weight> {
+    /// Check the travel times and paths two time-dependent servers returned for a query.
+    pub fn check_td(&mut self, graph: &TDGraph, query: TDQuery<Timestamp>, reference: Option<(Weight, Vec<NodeId>)>, candidate: Option<(Weight, Vec<NodeId>)>) {
+        self.check_distances(
+            query,
+            reference.as_ref().map(|(dist, _)| *dist),
+            candidate.as_ref().map(|(dist, _)| *dist),
+            Weight::eq,
+        );
+
+        for (server, result) in [("reference", reference), ("candidate", candidate)] {
+            if let Some((travel_time, path)) = result {
+                let mut t = query.departure;
+                let mismatch = check_endpoints(&path, query.from, query.to).or_else(|| {
+                    for hop in path.windows(2) {
+                        let arrival = graph
+                            .neighbor_and_edge_id_iter(hop[0])
+                            .filter(|&(head, _)| head == hop[1])
+                            .map(|(_, edge_id)| t + graph.travel_time_function(edge_id).eval(t))
+                            .min();
+                        match arrival {
+                            Some(arrival) => t = arrival,
+                            None => return Some(missing_arc(hop[0], hop[1])),
+                        }
+                    }
+                    None
+                });
+                self.check_path(query, server, path, travel_time, t - query.departure, mismatch);
+            }
+        }
+    }
+}
+
+impl Verification<TDQuery<floating::Timestamp>, floating::FlWeight> {
+    /// Check the travel times and time annotated paths two time-dependent servers with floating point weights returned for a query.
+    /// Distances are compared with the usual epsilon and paths checked like `floating_time_dependent::Graph::check_path` does.
+    pub fn check_floating_td(
+        &mut self,
+        graph: &floating::TDGraph,
+        query: TDQuery<floating::Timestamp>,
+        reference: Option<(floating::FlWeight, Vec<(NodeId, floating::Timestamp)>)>,
+        candidate: Option<(floating::FlWeight, Vec<(NodeId, floating::Timestamp)>)>,
+    ) {
+        self.check_distances(
+            query,
+            reference.as_ref().map(|(dist, _)| *dist),
+            candidate.as_ref().map(|(dist, _)| *dist),
+            |a, b| a.fuzzy_eq(*b),
+        );
+
+        for (server, result) in [("reference", reference), ("candidate", candidate)] {
+            if let Some((travel_time, path)) = result {
+                let nodes: Vec<NodeId> = path.iter().map(|&(node, _)| node).collect();
+                let mismatch = check_endpoints(&nodes, query.from, query.to)
+                    .or_else(|| {
+                        if path[0].1.fuzzy_eq(query.departure) {
+                            None
+                        } else {
+                            Some(format!("departure at {:?} instead of {:?}", path[0].1, query.departure))
+                        }
+                    })
+                    .or_else(|| graph.validate_path(&path).err());
+                if let Some(reason) = mismatch {
+                    self.fail(query, Mismatch::InvalidPath { server, path: nodes, reason });
+                    continue;
+                }
+                let path_length = path.last().unwrap().1 - query.departure;
+                if !path_length.fuzzy_eq(travel_time) {
+                    self.fail(
+                        query,
+                        Mismatch::PathLength {
+                            server,
+                            path: nodes,
+                            distance: travel_time,
+                            path_length,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Compare two static servers on the given queries.
+pub fn verify<G, R, C>(graph: &G, reference: &mut R, candidate: &mut C, queries: impl IntoIterator<Item = Query>) -> Verification<Query, Weight>
+where
+    G: RandomLinkAccessGraph,
+    R: for<'s> QueryServer<'s>,
+    C: for<'s> QueryServer<'s>,
+    for<'s> <R as QueryServer<'s>>::P: PathServer<NodeInfo = NodeId>,
+    for<'s> <C as QueryServer<'s>>::P: PathServer<NodeInfo = NodeId>,
+{
+    let mut verification = Verification::new();
+    for query in queries {
+        let reference = QueryServer::query(reference, query).map(|mut res| (res.distance(), res.path()));
+        let candidate = QueryServer::query(candidate, query).map(|mut res| (res.distance(), res.path()));
+        verification.check(graph, query, reference, candidate);
+    }
+    verification
+}
+
+/// Compare two time-dependent servers with integer weights on the given queries.
+/// Distances are compared as travel times.
+pub fn verify_td<R, C>(
+    graph: &TDGraph,
+    (reference, reference_distance): (&mut R, TDDistance),
+    (candidate, candidate_distance): (&mut C, TDDistance),
+    queries: impl IntoIterator<Item = TDQuery<Timestamp>>,
+) -> Verification<TDQuery<Timestamp>, Weight>
+where
+    R: for<'s> TDQueryServer<'s, Timestamp, Weight>,
+    C: for<'s> TDQueryServer<'s, Timestamp, Weight>,
+    for<'s> <R as TDQueryServer<'s, Timestamp, Weight>>::P: PathServer<NodeInfo = NodeId>,
+    for<'s> <C as TDQueryServer<'s, Timestamp, Weight>>::P: PathServer<NodeInfo = NodeId>,
+{
+    let mut verification = Verification::new();
+    for query in queries {
+        let reference = TDQueryServer::query(reference, query).map(|mut res| (reference_distance.travel_time(res.distance(), query.departure), res.path()));
+        let candidate = TDQueryServer::query(candidate, query).map(|mut res| (candidate_distance.travel_time(res.distance(), query.departure), res.path()));
+        verification.check_td(graph, query, reference, candidate);
+    }
+    verification
+}
+
+/// Compare two time-dependent servers with floating point weights on the given queries.
+pub fn verify_floating_td<R, C>(
+    graph: &floating::TDGraph,
+    reference: &mut R,
+    candidate: &mut C,
+    queries: impl IntoIterator<Item = TDQuery<floating::Timestamp>>,
+) -> Verification<TDQuery<floating::Timestamp>, floating::FlWeight>
+where
+    R: for<'s> TDQueryServer<'s, floating::Timestamp, floating::FlWeight>,
+    C: for<'s> TDQueryServer<'s, floating::Timestamp, floating::FlWeight>,
+    for<'s> <R as TDQueryServer<'s, floating::Timestamp, floating::FlWeight>>::P: PathServer<NodeInfo = (NodeId, floating::Timestamp)>,
+    for<'s> <C as TDQueryServer<'s, floating::Timestamp, floating::FlWeight>>::P: PathServer<NodeInfo = (NodeId, floating::Timestamp)>,
+{
+    let mut verification = Verification::new();
+    for query in queries {
+        let reference = TDQueryServer::query(reference, query).map(|mut res| (res.distance(), res.path()));
+        let candidate = TDQueryServer::query(candidate, query).map(|mut res| (res.distance(), res.path()));
+        verification.check_floating_td(graph, query, reference, candidate);
+    }
+    verification
+}
+
+fn check_endpoints(path: &[NodeId], from: NodeId, to: NodeId) -> Option<String> {
+    match (path.first(), path.last()) {
+        (Some(&first), Some(&last)) if first == from && last == to => None,
+        _ => Some(format!("path has to lead from {} to {}", from, to)),
+    }
+}
+
+fn missing_arc(tail: NodeId, head: NodeId) -> String {
+    format!("no arc from {} to {}", tail, head)
+}
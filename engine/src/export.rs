@@ -19,12 +19,19 @@ pub fn write_graph_to_gr<G: for<'a> LinkIterGraph<'a>>(graph: &G, filename: &str
 }
 
 /// Export geocoordinates in DIMACs .co format.
+/// As in the challenge instances, the x coordinate (longitude) comes first.
 pub fn write_coords_to_co(lat: &[f32], lng: &[f32], filename: &str) -> Result<()> {
     assert_eq!(lat.len(), lng.len());
     let mut file = File::create(filename)?;
     writeln!(&mut file, "p aux sp co {}", lat.len())?;
     for (i, (lat, lng)) in lat.iter().zip(lng.iter()).enumerate() {
-        writeln!(&mut file, "v {} {} {}", i + 1, (lat * 1_000_000.0) as i32, (lng * 1_000_000.0) as i32)?;
+        writeln!(
+            &mut file,
+            "v {} {} {}",
+            i + 1,
+            (f64::from(*lng) * 1_000_000.0).round() as i32,
+            (f64::from(*lat) * 1_000_000.0).round() as i32
+        )?;
     }
     Ok(())
 }
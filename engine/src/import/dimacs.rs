@@ -0,0 +1,131 @@
+//! Import of graphs and coordinates in the format of the 9th DIMACS implementation challenge.
+//!
+//! Graphs (`.gr`) consist of a problem line `p sp NODES ARCS` followed by one line `a TAIL HEAD WEIGHT` per arc.
+//! Coordinates (`.co`) consist of a problem line `p aux sp co NODES` followed by one line `v NODE LNG LAT` per node,
+//! with the coordinates in millionths of degrees.
+//! Node ids start at 1 and lines starting with `c` are comments.
+//! Arcs may be given in any order, the adjacency array keeps the input order of the arcs of each node.
+
+use super::group_by_tail;
+use crate::datastr::graph::*;
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    str::{FromStr, SplitWhitespace},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Read a DIMACS `.gr` file.
+pub fn read_graph<P: AsRef<Path>>(path: P) -> Result<OwnedGraph> {
+    let mut header = None;
+    let mut tails = Vec::new();
+    let mut links = Vec::new();
+
+    for_each_line(path.as_ref(), |kind, fields| {
+        match kind {
+            "p" => {
+                if header.is_some() {
+                    return Err("duplicate problem line".into());
+                }
+                if fields.next() != Some("sp") {
+                    return Err("expected problem line p sp NODES ARCS".into());
+                }
+                let num_nodes: usize = parse_field(fields, "number of nodes")?;
+                let num_arcs: usize = parse_field(fields, "number of arcs")?;
+                tails.reserve(num_arcs);
+                links.reserve(num_arcs);
+                header = Some((num_nodes, num_arcs));
+            }
+            "a" => {
+                let (num_nodes, _) = header.ok_or("arc before the problem line")?;
+                let tail = parse_node(fields, num_nodes)?;
+                let node = parse_node(fields, num_nodes)?;
+                let weight = parse_field(fields, "weight")?;
+                tails.push(tail);
+                links.push(Link { node, weight });
+            }
+            _ => return Err(format!("unknown line type {}", kind).into()),
+        }
+        Ok(())
+    })?;
+
+    let (num_nodes, num_arcs) = header.ok_or("missing problem line")?;
+    if links.len() != num_arcs {
+        return Err(format!("problem line announces {} arcs but {} were given", num_arcs, links.len()).into());
+    }
+
+    let (first_out, input_arcs) = group_by_tail(num_nodes, &tails);
+    let (head, weight) = input_arcs.iter().map(|&arc| (links[arc].node, links[arc].weight)).unzip();
+    Ok(OwnedGraph::new(first_out, head, weight))
+}
+
+/// Read a DIMACS `.co` file, returns latitudes and longitudes in degrees.
+pub fn read_coords<P: AsRef<Path>>(path: P) -> Result<(Vec<f32>, Vec<f32>)> {
+    let mut coords: Option<(Vec<f32>, Vec<f32>)> = None;
+    let mut seen = Vec::new();
+
+    for_each_line(path.as_ref(), |kind, fields| {
+        match kind {
+            "p" => {
+                if coords.is_some() {
+                    return Err("duplicate problem line".into());
+                }
+                if fields.next() != Some("aux") || fields.next() != Some("sp") || fields.next() != Some("co") {
+                    return Err("expected problem line p aux sp co NODES".into());
+                }
+                let num_nodes: usize = parse_field(fields, "number of nodes")?;
+                coords = Some((vec![0.0; num_nodes], vec![0.0; num_nodes]));
+                seen = vec![false; num_nodes];
+            }
+            "v" => {
+                let (lat, lng) = coords.as_mut().ok_or("node before the problem line")?;
+                let node = parse_node(fields, lat.len())? as usize;
+                lng[node] = (parse_field::<i64>(fields, "longitude")? as f64 / 1_000_000.0) as f32;
+                lat[node] = (parse_field::<i64>(fields, "latitude")? as f64 / 1_000_000.0) as f32;
+                if seen[node] {
+                    return Err(format!("duplicate coordinates for node {}", node + 1).into());
+                }
+                seen[node] = true;
+            }
+            _ => return Err(format!("unknown line type {}", kind).into()),
+        }
+        Ok(())
+    })?;
+
+    if let Some(node) = seen.iter().position(|&seen| !seen) {
+        return Err(format!("missing coordinates for node {}", node + 1).into());
+    }
+    coords.ok_or_else(|| "missing problem line".into())
+}
+
+// Call `f` with the line type and the remaining fields of each non-comment line.
+// Errors are annotated with the file and line.
+fn for_each_line(path: &Path, mut f: impl FnMut(&str, &mut SplitWhitespace) -> Result<()>) -> Result<()> {
+    let file = File::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            None | Some("c") => continue,
+            Some(kind) => f(kind, &mut fields).map_err(|e| format!("{}:{}: {}", path.display(), idx + 1, e))?,
+        }
+    }
+    Ok(())
+}
+
+fn parse_field<T: FromStr>(fields: &mut SplitWhitespace, name: &str) -> Result<T> {
+    let field = fields.next().ok_or_else(|| format!("missing {}", name))?;
+    field.parse().map_err(|_| format!("invalid {} {}", name, field).into())
+}
+
+// one based in the file, zero based in the result
+fn parse_node(fields: &mut SplitWhitespace, num_nodes: usize) -> Result<NodeId> {
+    let node: usize = parse_field(fields, "node id")?;
+    if node == 0 || node > num_nodes {
+        return Err(format!("node id {} out of range 1..={}", node, num_nodes).into());
+    }
+    Ok((node - 1) as NodeId)
+}
@@ -0,0 +1,338 @@
+//! Ingestion of live traffic snapshots.
+//!
+//! A snapshot is a CSV file without headers with one line per observed link: `link_id,from_ref,speed,observed_at`.
+//! `from_ref` is `true` or `false` and selects the direction of the link, `speed` is the current speed in km/h
+//! (`0` marks a closed link) and `observed_at` a unix timestamp in ms.
+//! Snapshots can be dropped as `*.csv` files into a directory, which gets polled periodically,
+//! or be written into a local unix socket, one snapshot per connection.
+//! Files should be moved into the directory atomically, otherwise partially written snapshots might be picked up.
+//!
+//! `LiveTraffic` keeps the latest value for each arc and discards values once they are older than `LiveTrafficParams::max_age`.
+//! From the current state either a static metric for (re-)customization or a `LiveTDGraph` can be derived.
+//! `run_ingestion` ties everything together and notifies a callback whenever a new metric should be customized,
+//! but not more often than `LiveTrafficParams::min_update_interval`.
+
+use crate::datastr::graph::{time_dependent::*, *};
+use crate::util::in_range_option::*;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{self, File},
+    io::{prelude::*, BufReader},
+    os::unix::net::UnixListener,
+    path::PathBuf,
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// A single line of a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiveRecord {
+    pub link_id: u64,
+    pub from_ref: bool,
+    /// Current speed [km/h], zero for closed links
+    pub speed: u32,
+    /// Unix timestamp [ms]
+    pub observed_at: u64,
+}
+
+/// Parse the records of a snapshot.
+pub fn parse_snapshot<R: Read>(reader: R) -> impl Iterator<Item = Result<LiveRecord, Box<dyn Error>>> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(reader)
+        .into_records()
+        .map(|record| parse_record(&record?))
+}
+
+fn parse_record(record: &csv::StringRecord) -> Result<LiveRecord, Box<dyn Error>> {
+    let field = |idx: usize| record.get(idx).map(str::trim).ok_or("missing column");
+    Ok(LiveRecord {
+        link_id: field(0)?.parse()?,
+        from_ref: field(1)?.parse()?,
+        speed: field(2)?.parse()?,
+        observed_at: field(3)?.parse()?,
+    })
+}
+
+/// Current unix time in ms
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Tuning parameters for live traffic ingestion.
+#[derive(Debug, Clone, Copy)]
+pub struct LiveTrafficParams {
+    /// Live values older than this will be discarded [ms]
+    pub max_age: u64,
+    /// Minimum wall clock time between two updates of the metric
+    pub min_update_interval: Duration,
+    /// How often snapshot directories are checked for new files
+    pub poll_interval: Duration,
+}
+
+impl Default for LiveTrafficParams {
+    fn default() -> Self {
+        LiveTrafficParams {
+            max_age: 15 * 60 * 1000,
+            min_update_interval: Duration::from_secs(30),
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Counters for a batch of ingested records.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IngestStats {
+    pub num_records: usize,
+    pub num_matched: usize,
+    /// Records which were older than the current value of the arc or already expired
+    pub num_outdated: usize,
+}
+
+/// The latest live travel times for all arcs of a graph.
+#[derive(Debug, Clone)]
+pub struct LiveTraffic {
+    geo_distance: Vec<Weight>,
+    max_age: u64,
+    travel_time: Vec<InRangeOption<Weight>>,
+    observed_at: Vec<u64>,
+    num_live: usize,
+}
+
+impl LiveTraffic {
+    /// `geo_distance` contains the arc lengths in meters and is used to convert speeds into travel times.
+    pub fn new(geo_distance: Vec<Weight>, max_age: u64) -> Self {
+        let n = geo_distance.len();
+        LiveTraffic {
+            geo_distance,
+            max_age,
+            travel_time: vec![InRangeOption::new(None); n],
+            observed_at: vec![0; n],
+            num_live: 0,
+        }
+    }
+
+    /// Set the speed of an arc.
+    /// Returns `false` when the arc already has a more recent value, which will then be kept.
+    pub fn update(&mut self, edge: EdgeId, speed: u32, observed_at: u64) -> bool {
+        let edge = edge as usize;
+        if self.travel_time[edge].value().is_some() {
+            if self.observed_at[edge] > observed_at {
+                return false;
+            }
+        } else {
+            self.num_live += 1;
+        }
+
+        let travel_time = if speed == 0 {
+            INFINITY
+        } else {
+            std::cmp::min(u64::from(self.geo_distance[edge]) * 3600 / u64::from(speed), u64::from(INFINITY)) as Weight
+        };
+        self.travel_time[edge] = InRangeOption::new(Some(travel_time));
+        self.observed_at[edge] = observed_at;
+        true
+    }
+
+    /// Update with all given records.
+    /// `link_to_edge` maps the link id and direction of each record to an arc, records where it returns `None` are skipped.
+    pub fn ingest(&mut self, records: impl IntoIterator<Item = LiveRecord>, now: u64, link_to_edge: impl Fn(u64, bool) -> Option<EdgeId>) -> IngestStats {
+        let mut stats = IngestStats::default();
+        for record in records {
+            stats.num_records += 1;
+            if let Some(edge) = link_to_edge(record.link_id, record.from_ref) {
+                stats.num_matched += 1;
+                if record.observed_at + self.max_age < now || !self.update(edge, record.speed, record.observed_at) {
+                    stats.num_outdated += 1;
+                }
+            }
+        }
+        stats
+    }
+
+    /// Discard all values which are older than the configured max age.
+    /// Returns the number of removed values.
+    pub fn expire(&mut self, now: u64) -> usize {
+        let deadline = now.saturating_sub(self.max_age);
+        let mut expired = 0;
+        for (travel_time, &observed_at) in self.travel_time.iter_mut().zip(self.observed_at.iter()) {
+            if travel_time.value().is_some() && observed_at < deadline {
+                *travel_time = InRangeOption::new(None);
+                expired += 1;
+            }
+        }
+        self.num_live -= expired;
+        expired
+    }
+
+    /// Live travel time of an arc, if there currently is one
+    pub fn travel_time(&self, edge: EdgeId) -> Option<Weight> {
+        self.travel_time[edge as usize].value()
+    }
+
+    /// Number of arcs with a live value
+    pub fn num_live(&self) -> usize {
+        self.num_live
+    }
+
+    /// The live travel times in the form `LiveTDGraph` expects.
+    pub fn live_weights(&self) -> &[InRangeOption<Weight>] {
+        &self.travel_time
+    }
+
+    /// A static metric where the base travel times are replaced with the live ones where available.
+    pub fn apply_to_metric(&self, base: &[Weight]) -> Vec<Weight> {
+        assert_eq!(base.len(), self.travel_time.len());
+        base.iter()
+            .zip(self.travel_time.iter())
+            .map(|(&base, live)| live.value().unwrap_or(base))
+            .collect()
+    }
+
+    /// Combine with predicted travel times.
+    /// Live values will be used for departures up to `soon` and blended into the prediction afterwards.
+    pub fn live_td_graph(&self, predicted: TDGraph, soon: Timestamp) -> LiveTDGraph {
+        assert_eq!(predicted.num_arcs(), self.travel_time.len());
+        LiveTDGraph::new(predicted, soon, self.travel_time.clone())
+    }
+}
+
+/// Where snapshots come from.
+#[derive(Debug, Clone)]
+pub enum LiveFeed {
+    /// Poll a directory for new or modified `*.csv` files
+    Directory(PathBuf),
+    /// Listen on a unix socket, each connection delivers one snapshot
+    Socket(PathBuf),
+}
+
+impl LiveFeed {
+    /// Existing directories will be polled, everything else is used as a socket path.
+    pub fn from_path(path: PathBuf) -> Self {
+        if path.is_dir() {
+            LiveFeed::Directory(path)
+        } else {
+            LiveFeed::Socket(path)
+        }
+    }
+
+    /// Start a background thread which sends all snapshots arriving on this feed into `tx`.
+    /// The thread terminates once the receiving side hangs up.
+    pub fn spawn(self, poll_interval: Duration, tx: Sender<Vec<LiveRecord>>) -> thread::JoinHandle<()> {
+        match self {
+            LiveFeed::Directory(dir) => thread::spawn(move || poll_directory(dir, poll_interval, tx)),
+            LiveFeed::Socket(path) => thread::spawn(move || listen_socket(path, tx)),
+        }
+    }
+}
+
+fn read_snapshot<R: Read>(reader: R) -> Vec<LiveRecord> {
+    let mut num_invalid = 0;
+    let records: Vec<LiveRecord> = parse_snapshot(reader).filter_map(|record| record.map_err(|_| num_invalid += 1).ok()).collect();
+    if num_invalid > 0 {
+        eprintln!("live traffic: skipped {} invalid lines", num_invalid);
+    }
+    records
+}
+
+fn poll_directory(dir: PathBuf, poll_interval: Duration, tx: Sender<Vec<LiveRecord>>) {
+    let mut seen: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    loop {
+        let mut files: Vec<(PathBuf, SystemTime)> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map(|ext| ext == "csv").unwrap_or(false))
+                .filter_map(|path| fs::metadata(&path).and_then(|meta| meta.modified()).ok().map(|modified| (path, modified)))
+                .filter(|(path, modified)| seen.get(path) != Some(modified))
+                .collect(),
+            Err(e) => {
+                eprintln!("live traffic: could not read {}: {}", dir.display(), e);
+                Vec::new()
+            }
+        };
+        files.sort_by_key(|&(_, modified)| modified);
+
+        for (path, modified) in files {
+            if let Ok(file) = File::open(&path) {
+                if tx.send(read_snapshot(file)).is_err() {
+                    return;
+                }
+                seen.insert(path, modified);
+            }
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+fn listen_socket(path: PathBuf, tx: Sender<Vec<LiveRecord>>) {
+    // a stale socket file from a previous run would make bind fail
+    let _ = fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("live traffic: could not bind {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if tx.send(read_snapshot(BufReader::new(stream))).is_err() {
+                    return;
+                }
+            }
+            Err(e) => eprintln!("live traffic: connection failed: {}", e),
+        }
+    }
+}
+
+/// Consume snapshots from `rx` until all senders hung up.
+/// Stale values are expired regularly.
+/// Whenever the live values changed, `on_update` is called with the new state, but at most once per `min_update_interval`.
+/// Updates which arrive in the meantime are batched.
+/// A pending batch is published when the senders hang up, regardless of the interval.
+pub fn run_ingestion(
+    rx: Receiver<Vec<LiveRecord>>,
+    mut traffic: LiveTraffic,
+    params: LiveTrafficParams,
+    link_to_edge: impl Fn(u64, bool) -> Option<EdgeId>,
+    mut on_update: impl FnMut(&LiveTraffic),
+) {
+    let mut dirty = false;
+    let mut last_update: Option<Instant> = None;
+
+    loop {
+        let wait = last_update
+            .and_then(|last| params.min_update_interval.checked_sub(last.elapsed()))
+            .unwrap_or_default();
+        let wait = if dirty { wait } else { params.min_update_interval };
+
+        match rx.recv_timeout(wait) {
+            Ok(records) => {
+                let stats = traffic.ingest(records, unix_now(), &link_to_edge);
+                dirty |= stats.num_matched > stats.num_outdated;
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => {
+                if dirty {
+                    on_update(&traffic);
+                }
+                return;
+            }
+        }
+
+        dirty |= traffic.expire(unix_now()) > 0;
+
+        if dirty && last_update.map(|last| last.elapsed() >= params.min_update_interval).unwrap_or(true) {
+            on_update(&traffic);
+            dirty = false;
+            last_update = Some(Instant::now());
+        }
+    }
+}
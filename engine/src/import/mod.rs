@@ -1,3 +1,29 @@
 //! Import routing data from different sources.
 
+use crate::datastr::graph::*;
+
+pub mod dimacs;
 pub mod here;
+pub mod live_traffic;
+pub mod time_dependent;
+
+/// Group arcs given in arbitrary order by their tail node with a stable counting sort.
+/// Returns the `first_out` array and for each position of the resulting adjacency array the index of the input arc.
+pub(crate) fn group_by_tail(num_nodes: usize, tails: &[NodeId]) -> (Vec<EdgeId>, Vec<usize>) {
+    let mut first_out = vec![0 as EdgeId; num_nodes + 1];
+    for &tail in tails {
+        first_out[tail as usize + 1] += 1;
+    }
+    for node in 0..num_nodes {
+        first_out[node + 1] += first_out[node];
+    }
+
+    let mut next = first_out.clone();
+    let mut input_arcs = vec![0; tails.len()];
+    for (arc, &tail) in tails.iter().enumerate() {
+        input_arcs[next[tail as usize] as usize] = arc;
+        next[tail as usize] += 1;
+    }
+
+    (first_out, input_arcs)
+}
@@ -0,0 +1,239 @@
+//! Import of time-dependent graphs from the text format of KaTCH (`.tpgr`) and the binary format of TD-CRP (`.tbgr`).
+//!
+//! Both formats store a piecewise linear travel time profile for each arc, given by interpolation points.
+//! The result uses the layout of RoutingKit graph directories, so it can be stored with `deconstruct_to`
+//! and later loaded as a `TDGraph`.
+
+use super::group_by_tail;
+use crate::{
+    datastr::graph::{time_dependent::*, *},
+    io::*,
+};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Time values in tpgr files are in tenths of a second
+const TPGR_UNIT: f64 = 100.0;
+/// The only tbgr version we can read
+const TBGR_VERSION: i32 = 2;
+
+/// A time-dependent graph with travel time profiles as interpolation points.
+#[derive(Debug, Clone)]
+pub struct TDGraphData {
+    pub first_out: Vec<EdgeId>,
+    pub head: Vec<NodeId>,
+    pub first_ipp_of_arc: Vec<u32>,
+    pub ipp_departure_time: Vec<Timestamp>,
+    pub ipp_travel_time: Vec<Weight>,
+    /// Period of the profiles [ms]
+    pub period: Timestamp,
+}
+
+impl TDGraphData {
+    pub fn num_nodes(&self) -> usize {
+        self.first_out.len() - 1
+    }
+
+    pub fn num_arcs(&self) -> usize {
+        self.head.len()
+    }
+
+    // Check the profiles of all arcs, so constructing a `TDGraph` from the data will not fail.
+    fn validate(&self) -> Result<()> {
+        if self.period == 0 || self.period >= INFINITY / 2 {
+            return Err(format!("invalid period {}", self.period).into());
+        }
+        for (arc, ipps) in self.first_ipp_of_arc.windows(2).enumerate() {
+            if ipps[0] >= ipps[1] {
+                return Err(format!("arc {} has no interpolation points", arc).into());
+            }
+            let departures = &self.ipp_departure_time[ipps[0] as usize..ipps[1] as usize];
+            if departures.windows(2).any(|w| w[0] >= w[1]) || *departures.last().unwrap() > self.period {
+                return Err(format!("interpolation points of arc {} are not strictly increasing within the period", arc).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deconstruct for TDGraphData {
+    fn store_each(&self, store: &dyn Fn(&str, &dyn Store) -> std::io::Result<()>) -> std::io::Result<()> {
+        store("first_out", &self.first_out)?;
+        store("head", &self.head)?;
+        store("first_ipp_of_arc", &self.first_ipp_of_arc)?;
+        store("ipp_departure_time", &self.ipp_departure_time)?;
+        store("ipp_travel_time", &self.ipp_travel_time)?;
+        store("period", &vec![self.period])?;
+        Ok(())
+    }
+}
+
+/// Read a KaTCH `.tpgr` file.
+///
+/// The first line contains the number of nodes, arcs and interpolation points and the period.
+/// It is followed by one line per arc with the tail, the head, the number of interpolation points
+/// and then departure time and travel time of each interpolation point.
+/// Node ids start at 0, times are in tenths of a second and will be converted to milliseconds.
+pub fn read_tpgr<P: AsRef<Path>>(path: P) -> Result<TDGraphData> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+    let mut lines = BufReader::new(file).lines().enumerate().filter(|(_, line)| match line {
+        Ok(line) => !line.trim().is_empty(),
+        Err(_) => true,
+    });
+    let invalid = |line_number: usize, msg: &str| format!("{}:{}: {}", path.display(), line_number + 1, msg);
+
+    let (_, header) = lines.next().ok_or_else(|| format!("{} is empty", path.display()))?;
+    let header = header?;
+    let header: Vec<f64> = header
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| invalid(0, "invalid header"))?;
+    if header.len() != 4 || header[..3].iter().any(|&count| count < 0.0 || count.fract() != 0.0) {
+        return Err(invalid(0, "expected header NODES ARCS INTERPOLATION_POINTS PERIOD").into());
+    }
+    let (num_nodes, num_arcs, num_ipps) = (header[0] as usize, header[1] as usize, header[2] as usize);
+    let period = (header[3] * TPGR_UNIT).round() as Timestamp;
+
+    let mut tails = Vec::with_capacity(num_arcs);
+    let mut heads = Vec::with_capacity(num_arcs);
+    let mut profiles = Vec::with_capacity(num_arcs);
+
+    for (line_number, line) in lines {
+        let line = line?;
+        let mut fields = line.split_whitespace();
+        let mut node = || -> Result<NodeId> {
+            match fields.next().map(str::parse::<usize>) {
+                Some(Ok(node)) if node < num_nodes => Ok(node as NodeId),
+                _ => Err(invalid(line_number, "missing or invalid node id").into()),
+            }
+        };
+        let tail = node()?;
+        let head = node()?;
+        let num_points: usize = fields
+            .next()
+            .and_then(|field| field.parse().ok())
+            .ok_or_else(|| invalid(line_number, "missing number of interpolation points"))?;
+        let values: Vec<f64> = fields
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| invalid(line_number, "invalid interpolation point"))?;
+        if values.len() != 2 * num_points || values.iter().any(|&value| value < 0.0) {
+            return Err(invalid(line_number, &format!("expected {} non negative interpolation points", num_points)).into());
+        }
+
+        tails.push(tail);
+        heads.push(head);
+        profiles.push(
+            values
+                .chunks(2)
+                .map(|point| ((point[0] * TPGR_UNIT).round() as Timestamp, (point[1] * TPGR_UNIT).round() as Weight))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    if heads.len() != num_arcs {
+        return Err(format!("{}: header announces {} arcs but {} were given", path.display(), num_arcs, heads.len()).into());
+    }
+    let given_ipps: usize = profiles.iter().map(Vec::len).sum();
+    if given_ipps != num_ipps {
+        return Err(format!(
+            "{}: header announces {} interpolation points but {} were given",
+            path.display(),
+            num_ipps,
+            given_ipps
+        )
+        .into());
+    }
+
+    let (first_out, input_arcs) = group_by_tail(num_nodes, &tails);
+    let mut data = TDGraphData {
+        first_out,
+        head: input_arcs.iter().map(|&arc| heads[arc]).collect(),
+        first_ipp_of_arc: Vec::with_capacity(num_arcs + 1),
+        ipp_departure_time: Vec::with_capacity(num_ipps),
+        ipp_travel_time: Vec::with_capacity(num_ipps),
+        period,
+    };
+    data.first_ipp_of_arc.push(0);
+    for &arc in &input_arcs {
+        for &(departure, travel_time) in &profiles[arc] {
+            data.ipp_departure_time.push(departure);
+            data.ipp_travel_time.push(travel_time);
+        }
+        data.first_ipp_of_arc.push(data.ipp_departure_time.len() as u32);
+    }
+
+    data.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(data)
+}
+
+/// Read a TD-CRP `.tbgr` file.
+///
+/// The file starts with the version (2), the number of nodes, arcs and interpolation points and the period as 32 bit integers.
+/// It is followed by the index of the first arc of each node, the head and the index of the first interpolation point of each arc
+/// and finally the departure time and travel time of each interpolation point, all in native byte order.
+/// Times are in milliseconds.
+pub fn read_tbgr<P: AsRef<Path>>(path: P) -> Result<TDGraphData> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+    let invalid = |msg: String| format!("{}: {}", path.display(), msg);
+
+    let mut words = bytes.chunks_exact(4).map(|word| [word[0], word[1], word[2], word[3]]);
+    let mut header = [0u32; 5];
+    for value in &mut header {
+        *value = u32::from_ne_bytes(words.next().ok_or_else(|| invalid("incomplete header".to_string()))?);
+    }
+    if header[0] as i32 != TBGR_VERSION {
+        return Err(invalid(format!("unsupported version {}", header[0] as i32)).into());
+    }
+    let (num_nodes, num_arcs, num_ipps) = (header[1] as usize, header[2] as usize, header[3] as usize);
+    let period = header[4];
+
+    let expected_bytes = 4 * (header.len() + num_nodes + 2 * num_arcs + 2 * num_ipps);
+    if bytes.len() != expected_bytes {
+        return Err(invalid(format!("file has {} bytes but the header announces {}", bytes.len(), expected_bytes)).into());
+    }
+    let mut next = || u32::from_ne_bytes(words.next().unwrap());
+
+    let mut first_out: Vec<EdgeId> = (0..num_nodes).map(|_| next()).collect();
+    first_out.push(num_arcs as EdgeId);
+    let mut head = Vec::with_capacity(num_arcs);
+    let mut first_ipp_of_arc = Vec::with_capacity(num_arcs + 1);
+    for _ in 0..num_arcs {
+        head.push(next());
+        first_ipp_of_arc.push(next());
+    }
+    first_ipp_of_arc.push(num_ipps as u32);
+    let mut ipp_departure_time = Vec::with_capacity(num_ipps);
+    let mut ipp_travel_time = Vec::with_capacity(num_ipps);
+    for _ in 0..num_ipps {
+        ipp_departure_time.push(next());
+        ipp_travel_time.push(next());
+    }
+
+    if first_out[0] != 0 || first_out.windows(2).any(|w| w[0] > w[1]) || head.iter().any(|&node| node as usize >= num_nodes) {
+        return Err(invalid("invalid adjacency array".to_string()).into());
+    }
+    if first_ipp_of_arc[0] != 0 || first_ipp_of_arc.windows(2).any(|w| w[0] > w[1]) {
+        return Err(invalid("invalid interpolation point indices".to_string()).into());
+    }
+
+    let data = TDGraphData {
+        first_out,
+        head,
+        first_ipp_of_arc,
+        ipp_departure_time,
+        ipp_travel_time,
+        period,
+    };
+    data.validate().map_err(|e| invalid(e.to_string()))?;
+    Ok(data)
+}
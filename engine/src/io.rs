@@ -25,6 +25,9 @@ use std::{
     slice,
 };
 
+pub mod graph_directory;
+pub use self::graph_directory::GraphDirectory;
+
 /// A trait which allows accessing the data of an object as a slice of bytes.
 /// The bytes should represent a serialization of the object and allow
 /// recreating it when reading these bytes again from the disk.
@@ -0,0 +1,261 @@
+//! Typed and lazy access to the files of a graph directory in the RoutingKit format.
+//!
+//! `GraphDirectory` knows the names, element types and lengths of the common attributes.
+//! Attributes are loaded on first access, validated against the number of nodes or arcs and then cached,
+//! so passing the directory around and accessing the same attribute several times is cheap.
+//! Cached attributes are shared through `Arc`s, so graphs can be built on top of them without copying the data.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rust_road_router::{datastr::graph::*, io::GraphDirectory};
+//! let dir = GraphDirectory::open("graph_dir")?;
+//! let graph = dir.travel_time_graph()?;
+//! let lat = dir.latitude()?;
+//! assert_eq!(graph.num_nodes(), lat.len());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use super::Load;
+use crate::{
+    algo::customizable_contraction_hierarchy::{contract, CCH},
+    datastr::{
+        graph::{time_dependent::*, *},
+        node_order::NodeOrder,
+    },
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Number of elements an attribute file has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extent {
+    /// One per node
+    Nodes,
+    /// One per node and one additional at the end, like `first_out`
+    NodesPlusOne,
+    /// One per arc
+    Arcs,
+    /// One per arc and one additional at the end, like `first_ipp_of_arc`
+    ArcsPlusOne,
+    /// One per interpolation point of the travel time profiles
+    InterpolationPoints,
+}
+
+/// A graph directory with lazily loaded and cached attributes.
+#[derive(Debug)]
+pub struct GraphDirectory {
+    path: PathBuf,
+    cache: RefCell<HashMap<String, Arc<dyn Any + Send + Sync>>>,
+}
+
+pub type SharedGraph = FirstOutGraph<Arc<Vec<EdgeId>>, Arc<Vec<NodeId>>, Arc<Vec<Weight>>>;
+pub type SharedUnweightedGraph = UnweightedFirstOutGraph<Arc<Vec<EdgeId>>, Arc<Vec<NodeId>>>;
+
+macro_rules! attributes {
+    ($($(#[$doc:meta])* $name:ident: $t:ty, $extent:ident;)*) => {
+        /// Names and extents of all attributes known to `GraphDirectory`
+        pub const ATTRIBUTES: &[(&str, Extent)] = &[$((stringify!($name), Extent::$extent)),*];
+
+        impl GraphDirectory {
+            $(
+                $(#[$doc])*
+                pub fn $name(&self) -> Result<Arc<Vec<$t>>> {
+                    self.attribute(stringify!($name), Extent::$extent)
+                }
+            )*
+        }
+    };
+}
+
+attributes! {
+    /// Index of the first outgoing arc of each node
+    first_out: EdgeId, NodesPlusOne;
+    /// Head node of each arc
+    head: NodeId, Arcs;
+    /// Travel time of each arc [ms]
+    travel_time: Weight, Arcs;
+    /// Length of each arc [m]
+    geo_distance: Weight, Arcs;
+    /// Lower bound of the travel time profile of each arc [ms]
+    lower_bound: Weight, Arcs;
+    /// Bitmask of road categories of each arc
+    arc_category: u8, Arcs;
+    /// Latitude of each node in degrees
+    latitude: f32, Nodes;
+    /// Longitude of each node in degrees
+    longitude: f32, Nodes;
+    /// OSM id of each node
+    osm_node_ids: u64, Nodes;
+    /// Nested dissection order for CCHs, lists the node ids by rank
+    cch_perm: NodeId, Nodes;
+    /// Index of the first interpolation point of the travel time profile of each arc
+    first_ipp_of_arc: u32, ArcsPlusOne;
+    /// Departure time of each interpolation point [ms]
+    ipp_departure_time: Timestamp, InterpolationPoints;
+    /// Travel time of each interpolation point [ms]
+    ipp_travel_time: Weight, InterpolationPoints;
+}
+
+impl GraphDirectory {
+    /// Open a graph directory, nothing will be loaded yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(Error::new(ErrorKind::NotFound, format!("graph directory {} does not exist", path.display())));
+        }
+        Ok(GraphDirectory {
+            path: path.to_path_buf(),
+            cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the directory has a file for the attribute
+    pub fn has(&self, name: &str) -> bool {
+        self.path.join(name).is_file()
+    }
+
+    pub fn num_nodes(&self) -> Result<usize> {
+        Ok(self.first_out()?.len() - 1)
+    }
+
+    pub fn num_arcs(&self) -> Result<usize> {
+        Ok(*self.first_out()?.last().unwrap() as usize)
+    }
+
+    /// A custom attribute with one value per node
+    pub fn node_attribute<T: Default + Copy + Send + Sync + 'static>(&self, name: &str) -> Result<Arc<Vec<T>>> {
+        self.attribute(name, Extent::Nodes)
+    }
+
+    /// A custom attribute with one value per arc
+    pub fn arc_attribute<T: Default + Copy + Send + Sync + 'static>(&self, name: &str) -> Result<Arc<Vec<T>>> {
+        self.attribute(name, Extent::Arcs)
+    }
+
+    /// Load, validate and cache an attribute, or get it from the cache.
+    pub fn attribute<T: Default + Copy + Send + Sync + 'static>(&self, name: &str, extent: Extent) -> Result<Arc<Vec<T>>> {
+        if let Some(cached) = self.cache.borrow().get(name) {
+            return cached.clone().downcast::<Vec<T>>().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("attribute {} of {} was already loaded with a different type", name, self.path.display()),
+                )
+            });
+        }
+
+        let data = self.load::<T>(name)?;
+        self.validate(name, extent, data.len(), &data)?;
+        let data = Arc::new(data);
+        self.cache.borrow_mut().insert(name.to_string(), data.clone());
+        Ok(data)
+    }
+
+    /// Remove an attribute from the cache, for example to free memory.
+    pub fn evict(&self, name: &str) {
+        self.cache.borrow_mut().remove(name);
+    }
+
+    fn load<T: Default + Copy>(&self, name: &str) -> Result<Vec<T>> {
+        let file = self.path.join(name);
+        if !file.is_file() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("graph directory {} has no {} file", self.path.display(), name),
+            ));
+        }
+        let bytes = file.metadata()?.len() as usize;
+        if bytes / std::mem::size_of::<T>() * std::mem::size_of::<T>() != bytes {
+            return Err(self.invalid(name, format!("file size of {} bytes is not a multiple of the element size", bytes)));
+        }
+        Vec::load_from(file)
+    }
+
+    fn validate(&self, name: &str, extent: Extent, len: usize, data: &dyn Any) -> Result<()> {
+        let expected = match extent {
+            Extent::NodesPlusOne if name == "first_out" => {
+                // first_out defines the number of nodes and arcs, so it can only be checked for consistency
+                let first_out = data
+                    .downcast_ref::<Vec<EdgeId>>()
+                    .ok_or_else(|| self.invalid(name, "has to be loaded as EdgeId".to_string()))?;
+                if first_out.first() != Some(&0) || first_out.windows(2).any(|w| w[0] > w[1]) {
+                    return Err(self.invalid(name, "has to start with 0 and be non decreasing".to_string()));
+                }
+                return Ok(());
+            }
+            Extent::Nodes => self.num_nodes()?,
+            Extent::NodesPlusOne => self.num_nodes()? + 1,
+            Extent::Arcs => self.num_arcs()?,
+            Extent::ArcsPlusOne => self.num_arcs()? + 1,
+            Extent::InterpolationPoints => *self.first_ipp_of_arc()?.last().unwrap() as usize,
+        };
+        if len != expected {
+            return Err(self.invalid(name, format!("has {} elements but {} were expected", len, expected)));
+        }
+        if let (true, Some(head)) = (name == "head", data.downcast_ref::<Vec<NodeId>>()) {
+            let num_nodes = self.num_nodes()?;
+            if head.iter().any(|&node| node as usize >= num_nodes) {
+                return Err(self.invalid(name, "contains node ids out of range".to_string()));
+            }
+        }
+        if let (true, Some(first_ipp)) = (name == "first_ipp_of_arc", data.downcast_ref::<Vec<u32>>()) {
+            if first_ipp.first() != Some(&0) || first_ipp.windows(2).any(|w| w[0] > w[1]) {
+                return Err(self.invalid(name, "has to start with 0 and be non decreasing".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    fn invalid(&self, name: &str, msg: String) -> Error {
+        Error::new(ErrorKind::InvalidData, format!("{} in graph directory {} {}", name, self.path.display(), msg))
+    }
+
+    /// The graph with the given arc attribute as weights.
+    pub fn graph(&self, weight: &str) -> Result<SharedGraph> {
+        Ok(FirstOutGraph::new(self.first_out()?, self.head()?, self.arc_attribute(weight)?))
+    }
+
+    /// The graph with `travel_time` weights.
+    pub fn travel_time_graph(&self) -> Result<SharedGraph> {
+        self.graph("travel_time")
+    }
+
+    /// The graph without weights.
+    pub fn unweighted_graph(&self) -> Result<SharedUnweightedGraph> {
+        Ok(UnweightedFirstOutGraph::new(self.first_out()?, self.head()?))
+    }
+
+    /// The time-dependent graph of the travel time profiles.
+    /// Also configures the period from the directory (see `load_period_config`).
+    /// The profile data is copied, use `evict` to free the cached versions if they are not needed anymore.
+    pub fn td_graph(&self) -> Result<TDGraph> {
+        load_period_config(&self.path)?;
+        Ok(TDGraph::new(
+            self.first_out()?.to_vec(),
+            self.head()?.to_vec(),
+            self.first_ipp_of_arc()?.to_vec(),
+            self.ipp_departure_time()?.to_vec(),
+            self.ipp_travel_time()?.to_vec(),
+        ))
+    }
+
+    /// The nested dissection order from `cch_perm`.
+    pub fn cch_order(&self) -> Result<NodeOrder> {
+        Ok(NodeOrder::from_node_order(self.cch_perm()?.to_vec()))
+    }
+
+    /// Run the CCH contraction with the order from `cch_perm`.
+    pub fn cch(&self) -> Result<CCH> {
+        Ok(contract(&self.unweighted_graph()?, self.cch_order()?))
+    }
+}
@@ -3,79 +3,105 @@ use std::fmt::Debug;
 use std::iter::{empty, once};
 use std::mem::replace;
 
-trait State<'a>: Debug {
-    fn on_link(self: Box<Self>, link: &'a LinkData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData> + 'a>);
-    fn on_trace(self: Box<Self>, trace: &'a TraceData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData> + 'a>);
-    fn on_done(self: Box<Self>) -> Box<dyn Iterator<Item = LinkSpeedData> + 'a>;
+type Estimates = Box<dyn Iterator<Item = LinkSpeedData>>;
+pub(super) type Transition = Result<(Box<dyn State>, Estimates), &'static str>;
+
+/// State of the estimation for a single vehicle.
+/// Transitions return an error when the events do not form a valid sequence.
+pub(super) trait State: Debug {
+    fn on_link(self: Box<Self>, link: LinkData) -> Transition;
+    fn on_trace(self: Box<Self>, trace: TraceData) -> Transition;
+    fn on_done(self: Box<Self>) -> Result<Estimates, &'static str>;
+
+    /// Number of links without traces which are currently buffered
+    fn num_pending_links(&self) -> usize {
+        0
+    }
+}
+
+pub(super) fn initial_state() -> Box<dyn State> {
+    Box::new(Init {})
 }
 
 #[derive(Debug)]
 struct Init {}
-impl<'a> State<'a> for Init {
-    fn on_link(self: Box<Self>, link: &'a LinkData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
-        (Box::new(InitialLink { link }), Box::new(empty()))
+impl State for Init {
+    fn on_link(self: Box<Self>, link: LinkData) -> Transition {
+        Ok((Box::new(InitialLink { link }), Box::new(empty())))
     }
 
-    fn on_trace(self: Box<Self>, _trace: &'a TraceData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
-        panic!("Init: trace before initial link");
+    fn on_trace(self: Box<Self>, _trace: TraceData) -> Transition {
+        Err("Init: trace before initial link")
     }
 
-    fn on_done(self: Box<Self>) -> Box<dyn Iterator<Item = LinkSpeedData>> {
-        panic!("Init: stream ended, expected link");
+    fn on_done(self: Box<Self>) -> Result<Estimates, &'static str> {
+        Err("Init: stream ended, expected link")
     }
 }
 
 #[derive(Debug)]
-struct InitialLink<'a> {
-    link: &'a LinkData,
+struct InitialLink {
+    link: LinkData,
 }
-impl<'a> State<'a> for InitialLink<'a> {
-    fn on_link(self: Box<Self>, _link: &'a LinkData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
-        panic!("InitialLink: no trace on initial link");
+impl State for InitialLink {
+    fn on_link(self: Box<Self>, _link: LinkData) -> Transition {
+        Err("InitialLink: no trace on initial link")
     }
 
-    fn on_trace(self: Box<Self>, trace: &'a TraceData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
-        assert_eq!(self.link.link_id, trace.link_id);
-        (Box::new(InitialLinkWithTrace { link: self.link, trace }), Box::new(empty()))
+    fn on_trace(self: Box<Self>, trace: TraceData) -> Transition {
+        if self.link.link_id != trace.link_id {
+            return Err("InitialLink: trace on other link");
+        }
+        Ok((Box::new(InitialLinkWithTrace { link: self.link, trace }), Box::new(empty())))
     }
 
-    fn on_done(self: Box<Self>) -> Box<dyn Iterator<Item = LinkSpeedData>> {
-        panic!("InitialLink: stream ended, expected trace");
+    fn on_done(self: Box<Self>) -> Result<Estimates, &'static str> {
+        Err("InitialLink: stream ended, expected trace")
     }
 }
 
 #[derive(Debug)]
-struct InitialLinkWithTrace<'a> {
-    link: &'a LinkData,
-    trace: &'a TraceData,
+struct InitialLinkWithTrace {
+    link: LinkData,
+    trace: TraceData,
 }
-impl<'a> State<'a> for InitialLinkWithTrace<'a> {
-    fn on_link(self: Box<Self>, link: &'a LinkData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
-        assert_ne!(self.link.link_id, link.link_id);
+impl State for InitialLinkWithTrace {
+    fn on_link(self: Box<Self>, link: LinkData) -> Transition {
+        if self.link.link_id == link.link_id {
+            return Err("InitialLinkWithTrace: same link twice");
+        }
         let mut intermediates = Vec::new();
         intermediates.push(link);
-        (
+        Ok((
             Box::new(IntermediateLinkAfterInitial {
                 initial_link: self.link,
                 trace: self.trace,
                 intermediates,
             }),
             Box::new(empty()),
-        )
+        ))
     }
 
-    fn on_trace(self: Box<Self>, trace: &'a TraceData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
-        assert_eq!(trace.link_id, self.trace.link_id);
-        assert_eq!(trace.link_id, self.link.link_id);
+    fn on_trace(self: Box<Self>, trace: TraceData) -> Transition {
+        if trace.link_id != self.trace.link_id || trace.link_id != self.link.link_id {
+            return Err("InitialLinkWithTrace: trace on other link");
+        }
 
-        let delta_t = trace.timestamp - self.trace.timestamp;
         let delta_fraction = f64::from(trace.traversed_in_travel_direction_fraction) - f64::from(self.trace.traversed_in_travel_direction_fraction);
-        let t_pre = (delta_t as f64 * f64::from(self.trace.traversed_in_travel_direction_fraction) / delta_fraction) as u64;
+        if delta_fraction <= 0.0 {
+            // the vehicle is standing, so there is no speed to extrapolate from yet - wait for a trace which shows movement
+            return Ok((Box::new(InitialLinkWithTrace { link: self.link, trace }), Box::new(empty())));
+        }
 
-        debug_assert!(self.trace.timestamp > t_pre, "timestamp underflow");
-        let entry_timestamp = self.trace.timestamp - t_pre;
+        let delta_t = trace.timestamp - self.trace.timestamp;
+        let t_pre = (delta_t as f64 * f64::from(self.trace.traversed_in_travel_direction_fraction) / delta_fraction) as u64;
+        let entry_timestamp = self
+            .trace
+            .timestamp
+            .checked_sub(t_pre)
+            .ok_or("InitialLinkWithTrace: extrapolated entry before timestamp zero")?;
 
-        (
+        Ok((
             Box::new(LinkWithEntryTimestampAndTrace {
                 link: self.link,
                 last_trace: trace,
@@ -83,29 +109,31 @@ impl<'a> State<'a> for InitialLinkWithTrace<'a> {
                 quality: delta_fraction,
             }),
             Box::new(empty()),
-        )
+        ))
     }
 
-    fn on_done(self: Box<Self>) -> Box<dyn Iterator<Item = LinkSpeedData>> {
-        panic!("InitialLinkWithTrace: stream ended, expected link or trace");
+    fn on_done(self: Box<Self>) -> Result<Estimates, &'static str> {
+        Err("InitialLinkWithTrace: stream ended, expected link or trace")
     }
 }
 
 #[derive(Debug, Clone)]
-struct IntermediateLinkAfterInitial<'a> {
-    initial_link: &'a LinkData,
-    trace: &'a TraceData,
-    intermediates: Vec<&'a LinkData>,
+struct IntermediateLinkAfterInitial {
+    initial_link: LinkData,
+    trace: TraceData,
+    intermediates: Vec<LinkData>,
 }
-impl<'a> State<'a> for IntermediateLinkAfterInitial<'a> {
-    fn on_link(mut self: Box<Self>, link: &'a LinkData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
+impl State for IntermediateLinkAfterInitial {
+    fn on_link(mut self: Box<Self>, link: LinkData) -> Transition {
         self.intermediates.push(link);
-        (self, Box::new(empty()))
+        Ok((self, Box::new(empty())))
     }
 
-    fn on_trace(mut self: Box<Self>, trace: &'a TraceData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData> + 'a>) {
+    fn on_trace(mut self: Box<Self>, trace: TraceData) -> Transition {
         let link = self.intermediates.pop().unwrap();
-        assert_eq!(link.link_id, trace.link_id);
+        if link.link_id != trace.link_id {
+            return Err("IntermediateLinkAfterInitial: trace not on last link");
+        }
 
         let initial_timestamp = self.trace.timestamp;
         let delta_t = trace.timestamp - initial_timestamp;
@@ -115,7 +143,7 @@ impl<'a> State<'a> for IntermediateLinkAfterInitial<'a> {
         let freeflow_time_after_initial_trace = self.initial_link.free_flow_traversal_time() * fraction_after_initial_trace;
         let length_before_initial_trace = self.initial_link.length - length_after_initial_trace;
 
-        let intermediate_total_length: u32 = self.intermediates.iter().map(|&&LinkData { length, .. }| length).sum();
+        let intermediate_total_length: u32 = self.intermediates.iter().map(|&LinkData { length, .. }| length).sum();
         let intermediate_total_freeflow_time: f64 = self.intermediates.iter().map(|link| link.free_flow_traversal_time()).sum();
 
         let length_before_current_trace = (f64::from(trace.traversed_in_travel_direction_fraction) * f64::from(link.length)) as u32;
@@ -156,7 +184,7 @@ impl<'a> State<'a> for IntermediateLinkAfterInitial<'a> {
         let entry_timestamp = trace.timestamp - (f64::from(length_before_current_trace) * 3.6 / (f64::from(link.speed_limit) * velocity_factor)) as u64;
         let quality = f64::from(length_before_current_trace) / f64::from(total_length) * f64::from(trace.traversed_in_travel_direction_fraction);
 
-        (
+        Ok((
             Box::new(LinkWithEntryTimestampAndTrace {
                 link,
                 last_trace: trace,
@@ -164,68 +192,76 @@ impl<'a> State<'a> for IntermediateLinkAfterInitial<'a> {
                 quality,
             }),
             Box::new(output),
-        )
+        ))
+    }
+
+    fn on_done(self: Box<Self>) -> Result<Estimates, &'static str> {
+        Err("IntermediateLinkAfterInitial: stream ended, expected link or trace")
     }
 
-    fn on_done(self: Box<Self>) -> Box<dyn Iterator<Item = LinkSpeedData>> {
-        panic!("IntermediateLinkAfterInitial: stream ended, expected link or trace");
+    fn num_pending_links(&self) -> usize {
+        self.intermediates.len()
     }
 }
 
 #[derive(Debug, Clone)]
-struct LinkWithEntryTimestampAndTrace<'a> {
-    link: &'a LinkData,
+struct LinkWithEntryTimestampAndTrace {
+    link: LinkData,
     entry_timestamp: u64,
-    last_trace: &'a TraceData,
+    last_trace: TraceData,
     quality: f64,
 }
-impl<'a> State<'a> for LinkWithEntryTimestampAndTrace<'a> {
-    fn on_link(self: Box<Self>, link: &'a LinkData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
+impl State for LinkWithEntryTimestampAndTrace {
+    fn on_link(self: Box<Self>, link: LinkData) -> Transition {
         let mut intermediates = Vec::new();
         intermediates.push(link);
-        (
+        Ok((
             Box::new(IntermediateLink {
                 last_link_with_trace: self,
                 intermediates,
             }),
             Box::new(empty()),
-        )
+        ))
     }
 
-    fn on_trace(mut self: Box<Self>, trace: &'a TraceData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
-        assert_eq!(self.link.link_id, trace.link_id);
+    fn on_trace(mut self: Box<Self>, trace: TraceData) -> Transition {
+        if self.link.link_id != trace.link_id {
+            return Err("LinkWithEntryTimestampAndTrace: trace on other link");
+        }
         self.quality += f64::from(trace.traversed_in_travel_direction_fraction - self.last_trace.traversed_in_travel_direction_fraction);
         self.last_trace = trace;
-        (self.clone(), Box::new(empty()))
+        Ok((self, Box::new(empty())))
     }
 
-    fn on_done(self: Box<Self>) -> Box<dyn Iterator<Item = LinkSpeedData>> {
+    fn on_done(self: Box<Self>) -> Result<Estimates, &'static str> {
         let delta_t = self.last_trace.timestamp - self.entry_timestamp;
         let delta_s = f64::from(self.last_trace.traversed_in_travel_direction_fraction) * f64::from(self.link.length);
         let velocity = delta_s / delta_t as f64;
-        Box::new(once(LinkSpeedData {
+        Ok(Box::new(once(LinkSpeedData {
             link_id: self.link.link_id,
             link_entered_timestamp: self.entry_timestamp,
             estimate_quality: self.quality as f32,
             velocity: (velocity * 3.6) as f32,
-        }))
+        })))
     }
 }
 
 #[derive(Debug, Clone)]
-struct IntermediateLink<'a> {
-    last_link_with_trace: Box<LinkWithEntryTimestampAndTrace<'a>>,
-    intermediates: Vec<&'a LinkData>,
+struct IntermediateLink {
+    last_link_with_trace: Box<LinkWithEntryTimestampAndTrace>,
+    intermediates: Vec<LinkData>,
 }
-impl<'a> State<'a> for IntermediateLink<'a> {
-    fn on_link(mut self: Box<Self>, link: &'a LinkData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData>>) {
+impl State for IntermediateLink {
+    fn on_link(mut self: Box<Self>, link: LinkData) -> Transition {
         self.intermediates.push(link);
-        (self, Box::new(empty()))
+        Ok((self, Box::new(empty())))
     }
 
-    fn on_trace(mut self: Box<Self>, trace: &'a TraceData) -> (Box<dyn State + 'a>, Box<dyn Iterator<Item = LinkSpeedData> + 'a>) {
+    fn on_trace(mut self: Box<Self>, trace: TraceData) -> Transition {
         let link = self.intermediates.pop().unwrap();
-        assert_eq!(link.link_id, trace.link_id);
+        if link.link_id != trace.link_id {
+            return Err("IntermediateLink: trace not on last link");
+        }
 
         let previous_timestamp = self.last_link_with_trace.last_trace.timestamp;
         let delta_t = trace.timestamp - previous_timestamp;
@@ -234,7 +270,7 @@ impl<'a> State<'a> for IntermediateLink<'a> {
         let length_after_last_trace = (fraction_after_last_trace * f64::from(self.last_link_with_trace.link.length)) as u32;
         let freeflow_time_after_last_trace = self.last_link_with_trace.link.free_flow_traversal_time() * fraction_after_last_trace;
 
-        let intermediate_total_length: u32 = self.intermediates.iter().map(|&&LinkData { length, .. }| length).sum();
+        let intermediate_total_length: u32 = self.intermediates.iter().map(|&LinkData { length, .. }| length).sum();
         let intermediate_total_freeflow_time: f64 = self.intermediates.iter().map(|link| link.free_flow_traversal_time()).sum();
 
         let length_before_current_trace = (f64::from(trace.traversed_in_travel_direction_fraction) * f64::from(link.length)) as u32;
@@ -276,7 +312,7 @@ impl<'a> State<'a> for IntermediateLink<'a> {
         let entry_timestamp = trace.timestamp - (f64::from(length_before_current_trace) * 3.6 / (f64::from(link.speed_limit) * velocity_factor)) as u64;
         let quality = f64::from(length_before_current_trace) / f64::from(total_length) * f64::from(trace.traversed_in_travel_direction_fraction);
 
-        (
+        Ok((
             Box::new(LinkWithEntryTimestampAndTrace {
                 link,
                 last_trace: trace,
@@ -284,24 +320,28 @@ impl<'a> State<'a> for IntermediateLink<'a> {
                 quality,
             }),
             Box::new(output),
-        )
+        ))
+    }
+
+    fn on_done(self: Box<Self>) -> Result<Estimates, &'static str> {
+        Err("IntermediateLink: stream ended, expected link or trace")
     }
 
-    fn on_done(self: Box<Self>) -> Box<dyn Iterator<Item = LinkSpeedData>> {
-        panic!("IntermediateLink: stream ended, expected link or trace");
+    fn num_pending_links(&self) -> usize {
+        self.intermediates.len()
     }
 }
 
 pub struct LinkSpeedEstimator<'a> {
-    state: Option<Box<dyn State<'a> + 'a>>,
+    state: Option<Box<dyn State>>,
     event_iterator: EventIterator<'a>,
-    output_iterator: Box<dyn Iterator<Item = LinkSpeedData> + 'a>,
+    output_iterator: Estimates,
 }
 
 impl<'a> LinkSpeedEstimator<'a> {
     pub fn new(events: EventIterator<'a>) -> LinkSpeedEstimator<'a> {
         LinkSpeedEstimator {
-            state: Some(Box::new(Init {})),
+            state: Some(initial_state()),
             event_iterator: events,
             output_iterator: Box::new(empty()),
         }
@@ -320,16 +360,17 @@ impl<'a> Iterator for LinkSpeedEstimator<'a> {
 
                     match self.event_iterator.next() {
                         Some(event) => {
-                            let (state, iter) = match event {
-                                Event::Link(link) => current_state.unwrap().on_link(link),
-                                Event::Trace(trace) => current_state.unwrap().on_trace(trace),
+                            let transition = match event {
+                                Event::Link(link) => current_state.unwrap().on_link(link.clone()),
+                                Event::Trace(trace) => current_state.unwrap().on_trace(trace.clone()),
                             };
+                            let (state, iter) = transition.unwrap_or_else(|err| panic!("{}", err));
                             self.state = Some(state);
                             self.output_iterator = iter;
                         }
                         None => match current_state {
                             Some(state) => {
-                                self.output_iterator = state.on_done();
+                                self.output_iterator = state.on_done().unwrap_or_else(|err| panic!("{}", err));
                                 self.state = None;
                             }
                             None => return None,
@@ -0,0 +1,404 @@
+//! HMM based map matching of raw GPS traces onto the road graph.
+//!
+//! Follows the approach of Newson and Krumm (Hidden Markov Map Matching Through Noise and Sparseness, 2009).
+//! For each GPS fix, we collect candidate positions on arcs close to the fix.
+//! Emission probabilities model the GPS noise as a gaussian on the distance between fix and candidate.
+//! Transition probabilities compare the network distance between two consecutive candidates with the great circle distance of the fixes.
+//! The network distances are obtained through an arbitrary `QueryServer` which has to answer queries on a length metric (`geo_distance`).
+//! The most likely sequence of candidates is then determined with the Viterbi algorithm.
+//!
+//! The result can be fed into `estimate_iter` directly:
+//!
+//! ```no_run
+//! # use rust_road_router::{algo::dijkstra::{query::dijkstra::Server, DefaultOps}, datastr::graph::*, link_speed_estimates::{*, map_matching::*}};
+//! # fn run(graph: &OwnedGraph, lat: &[f32], lng: &[f32], speed_limit: &[u32], fixes: &[GpsFix]) {
+//! let server = Server::<DefaultOps, _, _>::new(graph.clone());
+//! let mut matcher = MapMatcher::new(graph, lat, lng, graph.weight(), server, MapMatchingParams::default());
+//! for matched in matcher.match_trace(fixes) {
+//!     let links = matched.links(graph.weight(), speed_limit);
+//!     let estimates: Vec<LinkSpeedData> = estimate_iter(Box::new(links.iter()), Box::new(matched.traces.iter())).unwrap().collect();
+//! }
+//! # }
+//! ```
+
+use super::*;
+use crate::algo::*;
+use crate::datastr::graph::*;
+use crate::util::EARTH_RADIUS;
+use std::collections::HashMap;
+
+/// A single raw GPS observation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsFix {
+    pub timestamp: u64, // [ms]
+    pub latitude: f32,
+    pub longitude: f32,
+}
+
+/// Tuning parameters of the HMM.
+#[derive(Debug, Clone, Copy)]
+pub struct MapMatchingParams {
+    /// Only arcs within this distance of a fix are considered as candidates [m]
+    pub search_radius: f64,
+    /// Maximum number of candidates per fix, the closest ones are kept
+    pub max_candidates: usize,
+    /// Standard deviation of the GPS noise [m]
+    pub gps_sigma: f64,
+    /// Scale of the exponential distribution of the difference between route and great circle distance [m]
+    pub transition_beta: f64,
+}
+
+impl Default for MapMatchingParams {
+    fn default() -> Self {
+        MapMatchingParams {
+            search_radius: 50.0,
+            max_candidates: 8,
+            gps_sigma: 4.07,
+            transition_beta: 3.0,
+        }
+    }
+}
+
+/// A consecutive part of a trace which could be matched onto the graph.
+#[derive(Debug)]
+pub struct MatchedTrace {
+    /// Sequence of traversed arcs, starting with the arc of the first matched fix and ending with the arc of the last one.
+    pub path: Vec<EdgeId>,
+    /// The matched fixes, ordered by timestamp, with arc ids as link ids.
+    pub traces: Vec<TraceData>,
+}
+
+impl MatchedTrace {
+    /// Build the `LinkData` sequence for the matched path.
+    /// `geo_distance` is the arc length in meters and `speed_limit` the speed limit in km/h.
+    pub fn links(&self, geo_distance: &[Weight], speed_limit: &[u32]) -> Vec<LinkData> {
+        self.path
+            .iter()
+            .map(|&edge| LinkData {
+                link_id: u64::from(edge),
+                length: geo_distance[edge as usize] * 1000,
+                speed_limit: speed_limit[edge as usize],
+            })
+            .collect()
+    }
+}
+
+/// A possible position of the vehicle for a single fix.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    edge: EdgeId,
+    fraction: f64,
+    distance: f64,
+}
+
+/// Uniform grid over the arcs of the graph to efficiently find the arcs close to a position.
+/// Coordinates are projected equirectangularly around the mean latitude which is precise enough for a few hundred meters.
+#[derive(Debug)]
+struct ArcGrid {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    cell_size: f64,
+    cos_lat: f64,
+    cells: HashMap<(i64, i64), Vec<EdgeId>>,
+}
+
+impl ArcGrid {
+    fn new<G: RandomLinkAccessGraph>(graph: &G, tail: &[NodeId], lat: &[f32], lng: &[f32], cell_size: f64) -> Self {
+        let mean_lat = lat.iter().map(|&lat| f64::from(lat)).sum::<f64>() / lat.len().max(1) as f64;
+        let cos_lat = mean_lat.to_radians().cos();
+        let x = lng.iter().map(|&lng| f64::from(lng).to_radians() * cos_lat * EARTH_RADIUS).collect::<Vec<_>>();
+        let y = lat.iter().map(|&lat| f64::from(lat).to_radians() * EARTH_RADIUS).collect::<Vec<_>>();
+
+        let mut cells: HashMap<(i64, i64), Vec<EdgeId>> = HashMap::new();
+        for (edge, &from) in tail.iter().enumerate() {
+            let from = from as usize;
+            let to = graph.link(edge as EdgeId).node as usize;
+            let (min_x, max_x) = (x[from].min(x[to]), x[from].max(x[to]));
+            let (min_y, max_y) = (y[from].min(y[to]), y[from].max(y[to]));
+
+            for cell_x in (min_x / cell_size).floor() as i64..=(max_x / cell_size).floor() as i64 {
+                for cell_y in (min_y / cell_size).floor() as i64..=(max_y / cell_size).floor() as i64 {
+                    cells.entry((cell_x, cell_y)).or_default().push(edge as EdgeId);
+                }
+            }
+        }
+
+        ArcGrid {
+            x,
+            y,
+            cell_size,
+            cos_lat,
+            cells,
+        }
+    }
+
+    fn project(&self, lat: f32, lng: f32) -> (f64, f64) {
+        (
+            f64::from(lng).to_radians() * self.cos_lat * EARTH_RADIUS,
+            f64::from(lat).to_radians() * EARTH_RADIUS,
+        )
+    }
+
+    /// All arcs which are closer than `radius` to the given projected position with distance and the fraction of the closest point.
+    fn arcs_within(&self, (x, y): (f64, f64), radius: f64, tail: &[NodeId], head: impl Fn(EdgeId) -> NodeId) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+        let cell_range = |coord: f64| ((coord - radius) / self.cell_size).floor() as i64..=((coord + radius) / self.cell_size).floor() as i64;
+
+        for cell_x in cell_range(x) {
+            for cell_y in cell_range(y) {
+                for &edge in self.cells.get(&(cell_x, cell_y)).into_iter().flatten() {
+                    let from = tail[edge as usize] as usize;
+                    let to = head(edge) as usize;
+                    let (dx, dy) = (self.x[to] - self.x[from], self.y[to] - self.y[from]);
+                    let squared_length = dx * dx + dy * dy;
+                    let fraction = if squared_length > 0.0 {
+                        (((x - self.x[from]) * dx + (y - self.y[from]) * dy) / squared_length).max(0.0).min(1.0)
+                    } else {
+                        0.0
+                    };
+                    let distance = (x - self.x[from] - fraction * dx).hypot(y - self.y[from] - fraction * dy);
+                    if distance <= radius {
+                        candidates.push(Candidate { edge, fraction, distance });
+                    }
+                }
+            }
+        }
+
+        // arcs spanning multiple cells were found multiple times
+        candidates.sort_by_key(|candidate| candidate.edge);
+        candidates.dedup_by_key(|candidate| candidate.edge);
+        candidates
+    }
+}
+
+/// A single step of the HMM: the fix, its candidates, the log probability of the best sequence ending in each candidate
+/// and the index of the predecessor candidate in the previous step.
+type Step<'f> = (&'f GpsFix, Vec<Candidate>, Vec<f64>, Vec<usize>);
+
+/// Map matcher for a fixed graph.
+/// Keeps the spatial index and the distance query server around, so it can be used for many traces.
+pub struct MapMatcher<'a, G, S> {
+    graph: &'a G,
+    geo_distance: &'a [Weight],
+    tail: Vec<NodeId>,
+    grid: ArcGrid,
+    server: S,
+    params: MapMatchingParams,
+}
+
+impl<'a, G, S> MapMatcher<'a, G, S>
+where
+    G: RandomLinkAccessGraph,
+    S: for<'s> QueryServer<'s>,
+    for<'s> <S as QueryServer<'s>>::P: PathServer<NodeInfo = NodeId>,
+{
+    /// Create a new map matcher.
+    /// `geo_distance` contains the arc lengths in meters and `server` must answer queries on exactly this metric.
+    pub fn new(graph: &'a G, lat: &[f32], lng: &[f32], geo_distance: &'a [Weight], server: S, params: MapMatchingParams) -> Self {
+        assert_eq!(lat.len(), graph.num_nodes());
+        assert_eq!(lng.len(), graph.num_nodes());
+        assert_eq!(geo_distance.len(), graph.num_arcs());
+
+        let mut tail = Vec::with_capacity(graph.num_arcs());
+        for node in 0..graph.num_nodes() {
+            for _ in graph.neighbor_edge_indices(node as NodeId) {
+                tail.push(node as NodeId);
+            }
+        }
+        let grid = ArcGrid::new(graph, &tail, lat, lng, params.search_radius.max(1.0) * 2.0);
+
+        MapMatcher {
+            graph,
+            geo_distance,
+            tail,
+            grid,
+            server,
+            params,
+        }
+    }
+
+    /// Match a sequence of fixes ordered by timestamp onto the graph.
+    /// Fixes without any close arc are dropped.
+    /// When no candidate of a fix can be reached from the previous fix or the matched path reenters an arc it already passed,
+    /// the trace is split, so each `MatchedTrace` can be processed by `estimate_iter` independently.
+    /// Parts with less than two fixes are discarded.
+    pub fn match_trace(&mut self, fixes: &[GpsFix]) -> Vec<MatchedTrace> {
+        let mut results = Vec::new();
+        let mut chain: Vec<Step> = Vec::new();
+
+        for fix in fixes {
+            let candidates = self.candidates(fix);
+            if candidates.is_empty() {
+                continue;
+            }
+            let emissions: Vec<f64> = candidates.iter().map(|candidate| self.emission_log_probability(candidate)).collect();
+
+            let step = if let Some((prev_fix, prev_candidates, prev_probabilities, _)) = chain.last() {
+                let great_circle = self.great_circle_distance(prev_fix, fix);
+                let mut probabilities = vec![f64::NEG_INFINITY; candidates.len()];
+                let mut predecessors = vec![0; candidates.len()];
+
+                for (prev_idx, prev_candidate) in prev_candidates.iter().enumerate() {
+                    for (idx, candidate) in candidates.iter().enumerate() {
+                        if let Some(route) = self.route_distance(prev_candidate, candidate) {
+                            let probability = prev_probabilities[prev_idx] + emissions[idx] - (route - great_circle).abs() / self.params.transition_beta;
+                            if probability > probabilities[idx] {
+                                probabilities[idx] = probability;
+                                predecessors[idx] = prev_idx;
+                            }
+                        }
+                    }
+                }
+
+                if probabilities.iter().all(|&probability| probability == f64::NEG_INFINITY) {
+                    // HMM break - start a new chain with this fix
+                    results.extend(self.backtrack(&chain));
+                    chain.clear();
+                    (fix, candidates, emissions, Vec::new())
+                } else {
+                    (fix, candidates, probabilities, predecessors)
+                }
+            } else {
+                (fix, candidates, emissions, Vec::new())
+            };
+
+            chain.push(step);
+        }
+
+        results.extend(self.backtrack(&chain));
+        results
+    }
+
+    fn candidates(&self, fix: &GpsFix) -> Vec<Candidate> {
+        let graph = self.graph;
+        let mut candidates = self
+            .grid
+            .arcs_within(self.grid.project(fix.latitude, fix.longitude), self.params.search_radius, &self.tail, |edge| {
+                graph.link(edge).node
+            });
+        candidates.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        candidates.truncate(self.params.max_candidates);
+        candidates
+    }
+
+    fn emission_log_probability(&self, candidate: &Candidate) -> f64 {
+        -0.5 * (candidate.distance / self.params.gps_sigma).powi(2)
+    }
+
+    fn great_circle_distance(&self, from: &GpsFix, to: &GpsFix) -> f64 {
+        let from = self.grid.project(from.latitude, from.longitude);
+        let to = self.grid.project(to.latitude, to.longitude);
+        (to.0 - from.0).hypot(to.1 - from.1)
+    }
+
+    fn length(&self, edge: EdgeId) -> f64 {
+        f64::from(self.geo_distance[edge as usize])
+    }
+
+    /// Network distance between two candidates in meters
+    fn route_distance(&mut self, from: &Candidate, to: &Candidate) -> Option<f64> {
+        if from.edge == to.edge && from.fraction <= to.fraction {
+            return Some((to.fraction - from.fraction) * self.length(from.edge));
+        }
+
+        let between = self.node_distance(self.graph.link(from.edge).node, self.tail[to.edge as usize])?;
+        Some((1.0 - from.fraction) * self.length(from.edge) + f64::from(between) + to.fraction * self.length(to.edge))
+    }
+
+    fn node_distance(&mut self, from: NodeId, to: NodeId) -> Option<Weight> {
+        if from == to {
+            return Some(0);
+        }
+        self.server.query(Query { from, to }).map(|result| result.distance())
+    }
+
+    /// The arcs between the arcs of two consecutive candidates, excluding both.
+    fn arcs_between(&mut self, from: &Candidate, to: &Candidate) -> Vec<EdgeId> {
+        if from.edge == to.edge && from.fraction <= to.fraction {
+            return Vec::new();
+        }
+
+        let from_node = self.graph.link(from.edge).node;
+        let to_node = self.tail[to.edge as usize];
+        if from_node == to_node {
+            return Vec::new();
+        }
+
+        let nodes = self.server.query(Query { from: from_node, to: to_node }).unwrap().path();
+        nodes
+            .windows(2)
+            .map(|pair| {
+                // take the shortest of possibly multiple parallel arcs
+                self.graph
+                    .neighbor_edge_indices(pair[0])
+                    .filter(|&edge| self.graph.link(edge).node == pair[1])
+                    .min_by_key(|&edge| self.geo_distance[edge as usize])
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn backtrack(&mut self, chain: &[Step]) -> Vec<MatchedTrace> {
+        let (_, _, last_probabilities, _) = match chain.last() {
+            Some(last) => last,
+            None => return Vec::new(),
+        };
+
+        let mut idx = (0..last_probabilities.len())
+            .max_by(|&a, &b| last_probabilities[a].partial_cmp(&last_probabilities[b]).unwrap())
+            .unwrap();
+        let mut matched = Vec::with_capacity(chain.len());
+        for (fix, candidates, _, predecessors) in chain.iter().rev() {
+            matched.push((*fix, candidates[idx]));
+            if !predecessors.is_empty() {
+                idx = predecessors[idx];
+            }
+        }
+        matched.reverse();
+
+        let mut results = Vec::new();
+        let mut current = MatchedTrace {
+            path: vec![matched[0].1.edge],
+            traces: Vec::new(),
+        };
+        let mut prev: Option<Candidate> = None;
+
+        for (fix, candidate) in matched {
+            if let Some(prev) = prev {
+                if prev.edge == candidate.edge && prev.fraction == candidate.fraction {
+                    // the vehicle did not move - the estimator can't use this fix
+                    continue;
+                }
+
+                let mut between = self.arcs_between(&prev, &candidate);
+                between.push(candidate.edge);
+                if prev.edge != candidate.edge || prev.fraction > candidate.fraction {
+                    if between.iter().any(|edge| current.path.contains(edge)) {
+                        // the estimator matches traces and links by id, so this has to go into a separate trace
+                        results.push(std::mem::replace(
+                            &mut current,
+                            MatchedTrace {
+                                path: vec![candidate.edge],
+                                traces: Vec::new(),
+                            },
+                        ));
+                    } else {
+                        current.path.extend(between);
+                    }
+                }
+            }
+
+            current.traces.push(TraceData {
+                timestamp: fix.timestamp,
+                link_id: u64::from(candidate.edge),
+                traversed_in_travel_direction_fraction: candidate.fraction as f32,
+            });
+            prev = Some(candidate);
+        }
+
+        results.push(current);
+        // a single fix does not allow any estimate
+        results.retain(|matched| matched.traces.len() > 1);
+        results
+    }
+}
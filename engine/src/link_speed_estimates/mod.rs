@@ -2,18 +2,21 @@
 
 mod event_iterator;
 mod link_speed_estimator;
+pub mod map_matching;
+pub mod profile_aggregation;
+pub mod streaming;
 
 use self::event_iterator::{Event, EventIterator};
 use self::link_speed_estimator::LinkSpeedEstimator;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TraceData {
     pub timestamp: u64, // [ms]
     pub link_id: u64,
     pub traversed_in_travel_direction_fraction: f32, // [0.0, 1.0]
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LinkData {
     pub link_id: u64,
     pub length: u32,      // [mm]
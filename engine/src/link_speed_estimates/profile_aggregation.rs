@@ -0,0 +1,322 @@
+//! Aggregation of individual link speed observations into periodic travel time profiles.
+//!
+//! Observations are bucketed by their departure time within the period, which the `Calendar` derives from their unix timestamp.
+//! With a weekly period, this yields separate profiles for each weekday.
+//! For each bucket with enough data we take the quality weighted median travel time, which is robust against the occasional broken estimate.
+//! Empty buckets are filled by interpolating between neighbouring buckets, then the profile is smoothed with a circular moving average.
+//! Afterwards, the FIFO property is enforced and the PLF simplified with Douglas-Peucker.
+//! The result are the `first_ipp_of_arc`, `ipp_departure_time` and `ipp_travel_time` arrays which `TDGraph` expects.
+
+use super::*;
+use crate::datastr::graph::{time_dependent::*, *};
+use crate::io::*;
+
+/// Tuning parameters of the aggregation.
+#[derive(Debug, Clone, Copy)]
+pub struct AggregationParams {
+    /// Width of a departure time bucket [ms]
+    pub bucket_width: Timestamp,
+    /// Buckets with fewer observations will be interpolated from the neighbouring buckets
+    pub min_samples: usize,
+    /// Number of buckets on each side which are averaged during smoothing
+    pub smoothing_radius: usize,
+    /// Maximum deviation of the simplified profile from the smoothed one [ms]
+    pub simplification_tolerance: Weight,
+    /// Maps the timestamps of the observations into the period
+    pub calendar: Calendar,
+}
+
+impl Default for AggregationParams {
+    fn default() -> Self {
+        AggregationParams {
+            bucket_width: 15 * 60 * 1000,
+            min_samples: 3,
+            smoothing_radius: 1,
+            simplification_tolerance: 1000,
+            calendar: Calendar::default(),
+        }
+    }
+}
+
+/// Collects observations for all arcs of a graph and turns them into profiles.
+#[derive(Debug)]
+pub struct ProfileAggregator<'a> {
+    geo_distance: &'a [Weight],
+    free_flow_travel_time: &'a [Weight],
+    params: AggregationParams,
+    // per arc: bucket index, travel time and quality of each observation
+    observations: Vec<Vec<(u32, Weight, f32)>>,
+}
+
+impl<'a> ProfileAggregator<'a> {
+    /// Create a new aggregator.
+    /// `geo_distance` contains the arc lengths in meters and is used to convert velocities into travel times.
+    /// `free_flow_travel_time` serves as a lower bound for all profiles and as the profile of arcs without observations.
+    pub fn new(geo_distance: &'a [Weight], free_flow_travel_time: &'a [Weight], params: AggregationParams) -> Self {
+        assert_eq!(geo_distance.len(), free_flow_travel_time.len());
+        assert_eq!(period() % params.bucket_width, 0, "bucket width must divide the period");
+
+        ProfileAggregator {
+            geo_distance,
+            free_flow_travel_time,
+            params,
+            observations: vec![Vec::new(); geo_distance.len()],
+        }
+    }
+
+    fn num_buckets(&self) -> usize {
+        (period() / self.params.bucket_width) as usize
+    }
+
+    /// Add a single observation for the given arc.
+    /// Observations without velocity or quality are ignored.
+    pub fn add(&mut self, edge: EdgeId, observation: &LinkSpeedData) {
+        if observation.velocity.is_nan() || observation.velocity <= 0.0 || observation.estimate_quality.is_nan() || observation.estimate_quality <= 0.0 {
+            return;
+        }
+
+        let departure = self.params.calendar.departure(observation.link_entered_timestamp);
+        let bucket = departure / self.params.bucket_width;
+        let travel_time = (f64::from(self.geo_distance[edge as usize]) * 3600.0 / f64::from(observation.velocity)) as Weight;
+        self.observations[edge as usize].push((bucket, travel_time, observation.estimate_quality));
+    }
+
+    /// Add all observations of an iterator, for example the output of `estimate_iter`.
+    /// `link_id_to_edge` maps the link ids of the observations to arcs, observations where it returns `None` are skipped.
+    pub fn extend(&mut self, observations: impl Iterator<Item = LinkSpeedData>, link_id_to_edge: impl Fn(u64) -> Option<EdgeId>) {
+        for observation in observations {
+            if let Some(edge) = link_id_to_edge(observation.link_id) {
+                self.add(edge, &observation);
+            }
+        }
+    }
+
+    /// Build the profiles for all arcs.
+    pub fn profiles(self) -> Profiles {
+        let mut first_ipp_of_arc = Vec::with_capacity(self.observations.len() + 1);
+        first_ipp_of_arc.push(0);
+        let mut ipp_departure_time = Vec::new();
+        let mut ipp_travel_time = Vec::new();
+
+        for (edge, observations) in self.observations.iter().enumerate() {
+            let lower_bound = self.free_flow_travel_time[edge];
+            let profile = self.profile(observations, lower_bound).unwrap_or_else(|| vec![(0, lower_bound)]);
+
+            for (departure, travel_time) in profile {
+                ipp_departure_time.push(departure);
+                ipp_travel_time.push(travel_time);
+            }
+            first_ipp_of_arc.push(ipp_departure_time.len() as u32);
+        }
+
+        Profiles {
+            first_ipp_of_arc,
+            ipp_departure_time,
+            ipp_travel_time,
+        }
+    }
+
+    fn profile(&self, observations: &[(u32, Weight, f32)], lower_bound: Weight) -> Option<Vec<(Timestamp, Weight)>> {
+        let num_buckets = self.num_buckets();
+        let mut buckets = vec![Vec::new(); num_buckets];
+        for &(bucket, travel_time, quality) in observations {
+            buckets[bucket as usize].push((travel_time, quality));
+        }
+
+        let medians: Vec<Option<f64>> = buckets
+            .iter_mut()
+            .map(|samples| {
+                if samples.len() < self.params.min_samples {
+                    None
+                } else {
+                    Some(f64::from(weighted_median(samples)))
+                }
+            })
+            .collect();
+
+        let filled = fill_gaps(&medians)?;
+        let smoothed = smooth(&filled, self.params.smoothing_radius);
+
+        let mut profile: Vec<(Timestamp, Weight)> = smoothed
+            .iter()
+            .enumerate()
+            .map(|(bucket, &travel_time)| {
+                (
+                    bucket as Timestamp * self.params.bucket_width,
+                    std::cmp::max(travel_time.round() as Weight, lower_bound),
+                )
+            })
+            .collect();
+        enforce_fifo(&mut profile);
+
+        let profile = simplify(&profile, self.params.simplification_tolerance);
+        if profile.iter().all(|&(_, travel_time)| travel_time == profile[0].1) {
+            return Some(vec![(0, profile[0].1)]);
+        }
+        Some(profile)
+    }
+}
+
+/// Quality weighted median of the travel times.
+/// Sorts the samples.
+fn weighted_median(samples: &mut [(Weight, f32)]) -> Weight {
+    samples.sort_unstable_by_key(|&(travel_time, _)| travel_time);
+    let total: f64 = samples.iter().map(|&(_, quality)| f64::from(quality)).sum();
+    let mut acc = 0.0;
+    for &(travel_time, quality) in samples.iter() {
+        acc += f64::from(quality);
+        if acc >= total / 2.0 {
+            return travel_time;
+        }
+    }
+    samples.last().unwrap().0
+}
+
+/// Linearly interpolate missing buckets from the closest present ones (wrapping around).
+/// Returns `None` when no bucket has a value at all.
+fn fill_gaps(values: &[Option<f64>]) -> Option<Vec<f64>> {
+    let n = values.len();
+    let present: Vec<usize> = (0..n).filter(|&i| values[i].is_some()).collect();
+    if present.is_empty() {
+        return None;
+    }
+
+    let mut filled = vec![0.0; n];
+    for (idx, &from) in present.iter().enumerate() {
+        let to = present[(idx + 1) % present.len()];
+        let distance = if to > from { to - from } else { to + n - from };
+        let (from_value, to_value) = (values[from].unwrap(), values[to].unwrap());
+        for step in 0..distance {
+            filled[(from + step) % n] = from_value + (to_value - from_value) * step as f64 / distance as f64;
+        }
+    }
+    Some(filled)
+}
+
+/// Circular moving average
+fn smooth(values: &[f64], radius: usize) -> Vec<f64> {
+    let n = values.len();
+    let window = std::cmp::min(2 * radius + 1, n);
+    (0..n)
+        .map(|i| (0..window).map(|offset| values[(i + n + offset - window / 2) % n]).sum::<f64>() / window as f64)
+        .collect()
+}
+
+/// Make sure that no departure overtakes a later one, including the wraparound at the end of the period.
+/// Lowers travel times where they decrease faster than time passes, so the free flow lower bound still holds.
+fn enforce_fifo(profile: &mut [(Timestamp, Weight)]) {
+    let n = profile.len();
+    for _ in 0..2 {
+        for i in (0..n).rev() {
+            let (next_departure, next_travel_time) = profile[(i + 1) % n];
+            let next_departure = if i + 1 == n { next_departure + period() } else { next_departure };
+            profile[i].1 = std::cmp::min(profile[i].1, next_travel_time + (next_departure - profile[i].0));
+        }
+    }
+}
+
+/// Douglas-Peucker simplification of a periodic profile.
+/// Since the slope of each kept segment is an average of the original slopes, FIFO is preserved.
+fn simplify(profile: &[(Timestamp, Weight)], tolerance: Weight) -> Vec<(Timestamp, Weight)> {
+    let mut points = profile.to_vec();
+    // close the period, so the wraparound segment is considered as well
+    points.push((period(), profile[0].1));
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+
+    while let Some((first, last)) = stack.pop() {
+        let (x0, y0) = (f64::from(points[first].0), f64::from(points[first].1));
+        let (x1, y1) = (f64::from(points[last].0), f64::from(points[last].1));
+        let (max_idx, max_deviation) = ((first + 1)..last)
+            .map(|i| {
+                let interpolated = y0 + (y1 - y0) * (f64::from(points[i].0) - x0) / (x1 - x0);
+                (i, (f64::from(points[i].1) - interpolated).abs())
+            })
+            .fold((first, 0.0), |best, current| if current.1 > best.1 { current } else { best });
+
+        if max_deviation > f64::from(tolerance) {
+            keep[max_idx] = true;
+            stack.push((first, max_idx));
+            stack.push((max_idx, last));
+        }
+    }
+
+    points.pop();
+    points.into_iter().zip(keep).filter(|&(_, keep)| keep).map(|(point, _)| point).collect()
+}
+
+/// Travel time profiles for all arcs of a graph in the layout `TDGraph` expects.
+#[derive(Debug)]
+pub struct Profiles {
+    pub first_ipp_of_arc: Vec<u32>,
+    pub ipp_departure_time: Vec<Timestamp>,
+    pub ipp_travel_time: Vec<Weight>,
+}
+
+impl Profiles {
+    /// Combine with the topology to a full time-dependent graph.
+    pub fn into_td_graph(self, first_out: Vec<EdgeId>, head: Vec<NodeId>) -> TDGraph {
+        TDGraph::new(first_out, head, self.first_ipp_of_arc, self.ipp_departure_time, self.ipp_travel_time)
+    }
+
+    /// Concatenate the profiles of each day of a multi-day period, for example the profiles of each weekday for a weekly period.
+    /// The daily profiles all have to be for the same arcs and `period()` has to be configured to `days.len()` days.
+    /// Travel times interpolate linearly from the last point of a day to the first one of the next day, FIFO is enforced across the day boundaries.
+    pub fn from_days(days: &[Profiles]) -> Profiles {
+        assert!(!days.is_empty());
+        assert_eq!(period(), days.len() as Timestamp * DAY, "period does not match the number of days");
+        let num_arcs = days[0].first_ipp_of_arc.len() - 1;
+        assert!(days.iter().all(|day| day.first_ipp_of_arc.len() == num_arcs + 1));
+
+        let mut first_ipp_of_arc = Vec::with_capacity(num_arcs + 1);
+        first_ipp_of_arc.push(0);
+        let mut ipp_departure_time = Vec::new();
+        let mut ipp_travel_time = Vec::new();
+
+        for edge in 0..num_arcs {
+            let mut profile = Vec::new();
+            for (day_idx, day) in days.iter().enumerate() {
+                let range = day.first_ipp_of_arc[edge] as usize..day.first_ipp_of_arc[edge + 1] as usize;
+                let day_start = day_idx as Timestamp * DAY;
+                if day.ipp_departure_time[range.start] != 0 {
+                    profile.push((day_start, day.ipp_travel_time[range.start]));
+                }
+                profile.extend(
+                    day.ipp_departure_time[range.clone()]
+                        .iter()
+                        .zip(day.ipp_travel_time[range].iter())
+                        .take_while(|&(&departure, _)| departure < DAY)
+                        .map(|(&departure, &travel_time)| (day_start + departure, travel_time)),
+                );
+            }
+            enforce_fifo(&mut profile);
+
+            if profile.iter().all(|&(_, travel_time)| travel_time == profile[0].1) {
+                profile.truncate(1);
+            }
+            for (departure, travel_time) in profile {
+                ipp_departure_time.push(departure);
+                ipp_travel_time.push(travel_time);
+            }
+            first_ipp_of_arc.push(ipp_departure_time.len() as u32);
+        }
+
+        Profiles {
+            first_ipp_of_arc,
+            ipp_departure_time,
+            ipp_travel_time,
+        }
+    }
+}
+
+impl Deconstruct for Profiles {
+    fn store_each(&self, store: &dyn Fn(&str, &dyn Store) -> std::io::Result<()>) -> std::io::Result<()> {
+        store("first_ipp_of_arc", &self.first_ipp_of_arc)?;
+        store("ipp_departure_time", &self.ipp_departure_time)?;
+        store("ipp_travel_time", &self.ipp_travel_time)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+//! Link speed estimation on unbounded streams of probe data.
+//!
+//! `estimate_iter` expects the links and traces of a single vehicle in two separate, complete iterators.
+//! Here, the input may contain the events of many vehicles interleaved, as they arrive from a probe data feed.
+//! Only the events of each individual vehicle have to be in order.
+//! We keep one estimation state machine per active vehicle and emit estimates as soon as they are complete.
+//! Vehicles which did not send a trace for some time are finished and dropped,
+//! and the number of buffered links per vehicle is limited, so the memory consumption stays bounded.
+//! Invalid event sequences reset the state of the affected vehicle instead of aborting the whole stream.
+//! Vehicles finished at the same time emit their estimates ordered by vehicle id, so the output is deterministic.
+//!
+//! Two line based input formats are supported.
+//! CSV with the columns `vehicle_id,link,link_id,length,speed_limit` or `vehicle_id,trace,timestamp,link_id,fraction`
+//! and line delimited JSON with objects like `{"vehicle_id":1,"type":"link","link_id":2,"length":10000,"speed_limit":50}`
+//! or `{"vehicle_id":1,"type":"trace","timestamp":100000,"link_id":2,"fraction":0.5}`.
+
+use super::link_speed_estimator::{initial_state, State, Transition};
+use super::*;
+use serde_json::Value;
+use std::{collections::BTreeMap, collections::VecDeque, error::Error, io::prelude::*, mem::replace};
+
+/// A single event of a vehicle.
+#[derive(Debug, Clone)]
+pub enum ProbeEvent {
+    Link(LinkData),
+    Trace(TraceData),
+}
+
+/// An event tagged with the vehicle it belongs to.
+#[derive(Debug, Clone)]
+pub struct VehicleEvent {
+    pub vehicle_id: u64,
+    pub event: ProbeEvent,
+}
+
+/// Limits for the per vehicle state.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingParams {
+    /// Vehicles without a trace for this long will be finished [ms]
+    pub vehicle_timeout: u64,
+    /// Vehicles with more links without traces will be reset
+    pub max_pending_links: usize,
+}
+
+impl Default for StreamingParams {
+    fn default() -> Self {
+        StreamingParams {
+            vehicle_timeout: 5 * 60 * 1000,
+            max_pending_links: 1000,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ActiveVehicle {
+    state: Box<dyn State>,
+    // timestamp of the last accepted trace, or the stream time when the vehicle appeared
+    last_seen: u64,
+    last_trace: Option<u64>,
+}
+
+/// Push based estimator for interleaved events of many vehicles.
+#[derive(Debug)]
+pub struct StreamingEstimator {
+    vehicles: BTreeMap<u64, ActiveVehicle>,
+    params: StreamingParams,
+    completed: VecDeque<LinkSpeedData>,
+    // the latest trace timestamp seen so far
+    now: u64,
+    last_expiry: u64,
+    num_resets: usize,
+}
+
+impl StreamingEstimator {
+    pub fn new(params: StreamingParams) -> Self {
+        StreamingEstimator {
+            vehicles: BTreeMap::new(),
+            params,
+            completed: VecDeque::new(),
+            now: 0,
+            last_expiry: 0,
+            num_resets: 0,
+        }
+    }
+
+    /// Process a single event.
+    /// Completed estimates can be fetched with `pop_completed`.
+    pub fn push(&mut self, VehicleEvent { vehicle_id, event }: VehicleEvent) {
+        let now = self.now;
+        let vehicle = self.vehicles.entry(vehicle_id).or_insert_with(|| ActiveVehicle {
+            state: initial_state(),
+            last_seen: now,
+            last_trace: None,
+        });
+
+        if let ProbeEvent::Trace(trace) = &event {
+            if vehicle.last_trace.map(|last| trace.timestamp <= last).unwrap_or(false) {
+                // traces have to be strictly ordered - ignore this one
+                return;
+            }
+            vehicle.last_trace = Some(trace.timestamp);
+            vehicle.last_seen = trace.timestamp;
+            self.now = std::cmp::max(self.now, trace.timestamp);
+        }
+
+        let state = replace(&mut vehicle.state, initial_state());
+        match apply(state, event.clone()) {
+            Ok((state, estimates)) => {
+                vehicle.state = state;
+                self.completed.extend(estimates);
+            }
+            Err(_) => {
+                // start over with the event which did not fit
+                self.num_resets += 1;
+                if let Ok((state, estimates)) = apply(initial_state(), event) {
+                    vehicle.state = state;
+                    self.completed.extend(estimates);
+                }
+            }
+        }
+
+        if vehicle.state.num_pending_links() > self.params.max_pending_links {
+            self.num_resets += 1;
+            vehicle.state = initial_state();
+        }
+
+        if self.now >= self.last_expiry + self.params.vehicle_timeout {
+            self.expire();
+        }
+    }
+
+    /// Finish all vehicles which did not send a trace within the timeout.
+    fn expire(&mut self) {
+        let deadline = self.now.saturating_sub(self.params.vehicle_timeout);
+        let expired: Vec<u64> = self
+            .vehicles
+            .iter()
+            .filter(|(_, vehicle)| vehicle.last_seen < deadline)
+            .map(|(&id, _)| id)
+            .collect();
+
+        for id in expired {
+            let vehicle = self.vehicles.remove(&id).unwrap();
+            if let Ok(estimates) = vehicle.state.on_done() {
+                self.completed.extend(estimates);
+            }
+        }
+        self.last_expiry = self.now;
+    }
+
+    /// Finish all active vehicles, for example at the end of the input.
+    pub fn finish(&mut self) {
+        for (_, vehicle) in std::mem::take(&mut self.vehicles) {
+            if let Ok(estimates) = vehicle.state.on_done() {
+                self.completed.extend(estimates);
+            }
+        }
+    }
+
+    /// Take the next completed estimate.
+    pub fn pop_completed(&mut self) -> Option<LinkSpeedData> {
+        self.completed.pop_front()
+    }
+
+    /// Number of vehicles with state currently in memory
+    pub fn num_active_vehicles(&self) -> usize {
+        self.vehicles.len()
+    }
+
+    /// Number of times a vehicle state had to be reset because of invalid input
+    pub fn num_resets(&self) -> usize {
+        self.num_resets
+    }
+}
+
+fn apply(state: Box<dyn State>, event: ProbeEvent) -> Transition {
+    match event {
+        ProbeEvent::Link(link) => state.on_link(link),
+        ProbeEvent::Trace(trace) => state.on_trace(trace),
+    }
+}
+
+/// Iterator adapter around `StreamingEstimator`.
+#[derive(Debug)]
+pub struct EstimateStream<I> {
+    events: I,
+    estimator: StreamingEstimator,
+    done: bool,
+}
+
+impl<I: Iterator<Item = VehicleEvent>> Iterator for EstimateStream<I> {
+    type Item = LinkSpeedData;
+
+    fn next(&mut self) -> Option<LinkSpeedData> {
+        loop {
+            if let Some(estimate) = self.estimator.pop_completed() {
+                return Some(estimate);
+            }
+            if self.done {
+                return None;
+            }
+            match self.events.next() {
+                Some(event) => self.estimator.push(event),
+                None => {
+                    self.estimator.finish();
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+/// Lazily estimate link speeds from an (possibly unbounded) stream of interleaved vehicle events.
+pub fn estimate_stream<I: Iterator<Item = VehicleEvent>>(events: I, params: StreamingParams) -> EstimateStream<I> {
+    EstimateStream {
+        events,
+        estimator: StreamingEstimator::new(params),
+        done: false,
+    }
+}
+
+/// Parse events from CSV without headers.
+pub fn csv_events<R: Read>(reader: R) -> impl Iterator<Item = Result<VehicleEvent, Box<dyn Error>>> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(reader)
+        .into_records()
+        .map(|record| parse_csv_record(&record?))
+}
+
+fn parse_csv_record(record: &csv::StringRecord) -> Result<VehicleEvent, Box<dyn Error>> {
+    let field = |idx: usize| record.get(idx).ok_or("missing column");
+    let vehicle_id = field(0)?.parse()?;
+    let event = match field(1)? {
+        "link" => ProbeEvent::Link(LinkData {
+            link_id: field(2)?.parse()?,
+            length: field(3)?.parse()?,
+            speed_limit: field(4)?.parse()?,
+        }),
+        "trace" => ProbeEvent::Trace(TraceData {
+            timestamp: field(2)?.parse()?,
+            link_id: field(3)?.parse()?,
+            traversed_in_travel_direction_fraction: field(4)?.parse()?,
+        }),
+        _ => return Err("unknown event type".into()),
+    };
+    Ok(VehicleEvent { vehicle_id, event })
+}
+
+/// Parse events from line delimited JSON.
+pub fn json_lines_events<R: BufRead>(reader: R) -> impl Iterator<Item = Result<VehicleEvent, Box<dyn Error>>> {
+    reader
+        .lines()
+        .filter(|line| line.as_ref().map(|line| !line.trim().is_empty()).unwrap_or(true))
+        .map(|line| parse_json_line(&line?))
+}
+
+fn parse_json_line(line: &str) -> Result<VehicleEvent, Box<dyn Error>> {
+    let value: Value = serde_json::from_str(line)?;
+    let uint = |key: &str| value[key].as_u64().ok_or("missing or invalid integer field");
+    let vehicle_id = uint("vehicle_id")?;
+    let event = match value["type"].as_str() {
+        Some("link") => ProbeEvent::Link(LinkData {
+            link_id: uint("link_id")?,
+            length: uint("length")? as u32,
+            speed_limit: uint("speed_limit")? as u32,
+        }),
+        Some("trace") => ProbeEvent::Trace(TraceData {
+            timestamp: uint("timestamp")?,
+            link_id: uint("link_id")?,
+            traversed_in_travel_direction_fraction: value["fraction"].as_f64().ok_or("missing or invalid fraction")? as f32,
+        }),
+        _ => return Err("unknown event type".into()),
+    };
+    Ok(VehicleEvent { vehicle_id, event })
+}
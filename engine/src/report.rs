@@ -1,14 +1,14 @@
 //! Utilities for structured reporting of experimental results.
 //!
-//! Experimental take on an API using RAII to report experimental results within context
+//! API using RAII to report experimental results within context
 //! somewhat isomorph to the callgraph and output everything as JSON.
 //!
-//! So far not really successful.
-//! While it worked quite well for the CATCHUp experiments, the API is not really robust.
-//! Keeping the ContextGuards around pollutes the algorithm code and is a bit error prone.
-//! When used in a multithreaded environment, weird stuff will happen.
-//! Not really ready for productive use.
-//! JSON output is nice though.
+//! The reporting state is thread local, values reported in other threads are lost by default.
+//! To report from other threads, obtain a `ReportingHandle` in the reporting thread and `capture` the work in the other thread.
+//! The captured values can then be sent back and merged with `report_captured` or `report_captured_collection`.
+//! Merging happens in the order the caller chooses, not in the order in which threads finish, so the output is deterministic.
+//!
+//! Reports can be flattened into a table (for example for CSV export) with `table::Table`.
 
 use crate::built_info;
 use serde_json::{Map, Value};
@@ -252,5 +252,82 @@ pub fn enable_reporting() -> ReportingGuard {
     ReportingGuard(())
 }
 
+/// Handle to continue reporting in other threads.
+/// Obtained through `reporting_handle` on the thread which has reporting enabled.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportingHandle {
+    enabled: bool,
+}
+
+/// Get a handle to capture reports in other threads.
+pub fn reporting_handle() -> ReportingHandle {
+    ReportingHandle {
+        enabled: REPORTER.with(|reporter| reporter.borrow().is_some()),
+    }
+}
+
+/// Values reported within `ReportingHandle::capture`.
+/// Can be sent to other threads and merged into their current context.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CapturedReport(Option<Map<String, Value>>);
+
+impl CapturedReport {
+    /// The captured values as a JSON object, `None` if reporting was disabled.
+    pub fn into_value(self) -> Option<Value> {
+        self.0.map(Value::Object)
+    }
+}
+
+// Restores the previous reporter of this thread, even when the captured closure panics.
+struct CaptureGuard(Option<Option<Reporter>>);
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        if let Some(prev) = self.0.take() {
+            REPORTER.with(|reporter| reporter.replace(prev));
+        }
+    }
+}
+
+impl ReportingHandle {
+    /// Run `f` with a fresh reporting context on the current thread and return everything reported within it.
+    /// Contexts pushed within `f` have to be closed before `f` returns.
+    /// Captures may be nested, also when rayon executes other tasks on this thread while `f` is blocked.
+    pub fn capture<Out>(self, f: impl FnOnce() -> Out) -> (Out, CapturedReport) {
+        let fresh = if self.enabled { Some(Reporter::default()) } else { None };
+        let mut guard = CaptureGuard(Some(REPORTER.with(|reporter| reporter.replace(fresh))));
+        let res = f();
+        let captured = REPORTER.with(|reporter| reporter.replace(guard.0.take().unwrap()));
+
+        let captured = captured.map(|r| {
+            assert!(r.context_stack.is_empty(), "unclosed reporting context in captured closure");
+            match r.current {
+                CurrentReportingContext::Object(object) => object,
+                CurrentReportingContext::Collection(_) => panic!("broken root object for reporting"),
+            }
+        });
+        (res, CapturedReport(captured))
+    }
+}
+
+/// Report all captured values in the current context, as if they had been reported here.
+pub fn report_captured(captured: CapturedReport) {
+    if let Some(object) = captured.0 {
+        for (key, val) in object {
+            report_silent(key, val);
+        }
+    }
+}
+
+/// Report several captured reports as the items of a collection under `key`, in the order given.
+pub fn report_captured_collection(key: String, captured: impl IntoIterator<Item = CapturedReport>) {
+    let mut collection_ctxt = push_collection_context(key);
+    for captured in captured {
+        let _item = collection_ctxt.push_collection_item();
+        report_captured(captured);
+    }
+}
+
 pub mod benchmark;
 pub use benchmark::*;
+pub mod table;
@@ -0,0 +1,177 @@
+//! Flattening of JSON reports into a table.
+//!
+//! Each item of a collection becomes a row.
+//! The values of the surrounding objects are repeated in every row of their collections.
+//! Nested objects become columns with dotted names, for example `graph.num_nodes`,
+//! the values of collection items are prefixed with the name of the collection, for example `algo_runs.running_time_ms`.
+//! Reports without collections result in a single row.
+//! When there are several collections, the rows of all of them are concatenated and cells for columns of other collections stay empty.
+
+use super::*;
+use std::io::{Result, Write};
+
+/// A report flattened into rows and columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    /// Cells are `Value::Null` where a row has no value for a column.
+    pub rows: Vec<Vec<Value>>,
+}
+
+type Row = Vec<(String, Value)>;
+
+impl Table {
+    /// Flatten a report, usually the JSON object written by `enable_reporting`.
+    pub fn from_report(report: &Value) -> Self {
+        let mut flat_rows = Vec::new();
+        match report {
+            Value::Object(object) => flatten_object(object, "", &Vec::new(), &mut flat_rows),
+            Value::Array(items) => flatten_collection(items, "", &Vec::new(), &mut flat_rows),
+            value => flat_rows.push(vec![("value".to_string(), value.clone())]),
+        }
+
+        let mut columns: Vec<String> = Vec::new();
+        for row in &flat_rows {
+            for (column, _) in row {
+                if !columns.contains(column) {
+                    columns.push(column.clone());
+                }
+            }
+        }
+
+        let rows = flat_rows
+            .into_iter()
+            .map(|row| {
+                let mut cells = vec![Value::Null; columns.len()];
+                for (column, val) in row {
+                    let idx = columns.iter().position(|c| *c == column).unwrap();
+                    cells[idx] = val;
+                }
+                cells
+            })
+            .collect();
+
+        Table { columns, rows }
+    }
+
+    /// Write the table as CSV with a header line.
+    pub fn write_csv(&self, mut out: impl Write) -> Result<()> {
+        let header: Vec<String> = self.columns.iter().map(|column| csv_escape(column)).collect();
+        writeln!(out, "{}", header.join(","))?;
+        for row in &self.rows {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| match cell {
+                    Value::Null => String::new(),
+                    Value::String(s) => csv_escape(s),
+                    val => csv_escape(&val.to_string()),
+                })
+                .collect();
+            writeln!(out, "{}", cells.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+fn is_collection(val: &Value) -> bool {
+    match val {
+        Value::Array(items) => !items.is_empty() && items.iter().all(Value::is_object),
+        _ => false,
+    }
+}
+
+fn flatten_object(object: &Map<String, Value>, prefix: &str, base: &Row, rows: &mut Vec<Row>) {
+    let mut row = base.clone();
+    collect_values(object, prefix, &mut row);
+
+    let mut collections = Vec::new();
+    collect_collections(object, prefix, &mut collections);
+
+    if collections.is_empty() {
+        rows.push(row);
+    } else {
+        for (name, items) in collections {
+            flatten_collection(items, &name, &row, rows);
+        }
+    }
+}
+
+fn flatten_collection(items: &[Value], name: &str, base: &Row, rows: &mut Vec<Row>) {
+    let prefix = if name.is_empty() { String::new() } else { format!("{}.", name) };
+    for item in items {
+        match item {
+            Value::Object(object) => flatten_object(object, &prefix, base, rows),
+            val => {
+                let mut row = base.clone();
+                row.push((name.to_string(), val.clone()));
+                rows.push(row);
+            }
+        }
+    }
+}
+
+// all values except collections, nested objects are flattened
+fn collect_values(object: &Map<String, Value>, prefix: &str, row: &mut Row) {
+    for (key, val) in object {
+        match val {
+            Value::Object(nested) => collect_values(nested, &format!("{}{}.", prefix, key), row),
+            val if is_collection(val) => (),
+            val => row.push((format!("{}{}", prefix, key), val.clone())),
+        }
+    }
+}
+
+fn collect_collections<'a>(object: &'a Map<String, Value>, prefix: &str, collections: &mut Vec<(String, &'a [Value])>) {
+    for (key, val) in object {
+        match val {
+            Value::Object(nested) => collect_collections(nested, &format!("{}{}.", prefix, key), collections),
+            Value::Array(items) if is_collection(val) => collections.push((format!("{}{}", prefix, key), items)),
+            _ => (),
+        }
+    }
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_report() {
+        let report = json!({
+            "program": "test",
+            "graph": { "num_nodes": 3 },
+            "algo_runs": [
+                { "from": 0, "to": 1, "result": 5 },
+                { "from": 2, "to": 0, "result": null }
+            ],
+            "args": ["a", "b,c"]
+        });
+        let table = Table::from_report(&report);
+        assert_eq!(
+            table.columns,
+            vec!["args", "graph.num_nodes", "program", "algo_runs.from", "algo_runs.result", "algo_runs.to"]
+        );
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[1][3], json!(2));
+
+        let mut csv = Vec::new();
+        table.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            vec![
+                "args,graph.num_nodes,program,algo_runs.from,algo_runs.result,algo_runs.to",
+                "\"[\"\"a\"\",\"\"b,c\"\"]\",3,test,0,5,1",
+                "\"[\"\"a\"\",\"\"b,c\"\"]\",3,test,2,,0",
+            ]
+        );
+    }
+}
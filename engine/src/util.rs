@@ -46,6 +46,22 @@ impl Ord for NonNan {
     }
 }
 
+/// Mean earth radius in meters
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Great circle distance in meters between two `(latitude, longitude)` pairs in degrees (haversine formula).
+///
+/// ```
+/// # use rust_road_router::util::great_circle_distance;
+/// assert!((great_circle_distance((0.0, 0.0), (0.0, 1.0)) - 111_194.9).abs() < 0.1);
+/// ```
+pub fn great_circle_distance((lat1, lng1): (f32, f32), (lat2, lng2): (f32, f32)) -> f64 {
+    let (lat1, lng1) = (f64::from(lat1).to_radians(), f64::from(lng1).to_radians());
+    let (lat2, lng2) = (f64::from(lat2).to_radians(), f64::from(lng2).to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lng2 - lng1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+}
+
 /// Util function to chain unchainable function calls.
 ///
 /// ```
@@ -0,0 +1,67 @@
+// Test graphs shared between the integration tests.
+// Not every test uses all of them.
+#![allow(dead_code)]
+
+use rust_road_router::datastr::graph::*;
+use std::path::{Path, PathBuf};
+
+pub const GRID_SIZE: usize = 12;
+
+// A grid of bidirectional roads with somewhat arbitrary travel times around Karlsruhe, returned with node latitudes and longitudes.
+// Large enough that the topocore server does not dismiss the targets as part of tiny components.
+pub fn grid() -> (OwnedGraph, Vec<f32>, Vec<f32>) {
+    let mut first_out = vec![0];
+    let mut head = Vec::new();
+    let mut weight = Vec::new();
+    let mut lat = Vec::new();
+    let mut lng = Vec::new();
+
+    for row in 0..GRID_SIZE {
+        for col in 0..GRID_SIZE {
+            lat.push(49.0 + row as f32 * 0.001);
+            lng.push(8.4 + col as f32 * 0.0015);
+
+            let neighbors = [(row.wrapping_sub(1), col), (row + 1, col), (row, col.wrapping_sub(1)), (row, col + 1)];
+            for &(neighbor_row, neighbor_col) in &neighbors {
+                if neighbor_row < GRID_SIZE && neighbor_col < GRID_SIZE {
+                    head.push((neighbor_row * GRID_SIZE + neighbor_col) as NodeId);
+                    weight.push(4000 + ((row * 31 + col * 17 + neighbor_row * 7) % 50) as Weight * 300);
+                }
+            }
+            first_out.push(head.len() as EdgeId);
+        }
+    }
+
+    (OwnedGraph::new(first_out, head, weight), lat, lng)
+}
+
+// A temporary directory which is removed again when dropped, also when the test fails.
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("rust_road_router_{}_test_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
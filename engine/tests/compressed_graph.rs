@@ -0,0 +1,66 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rand::prelude::*;
+use rust_road_router::{
+    algo::{
+        customizable_contraction_hierarchy::{query::Server as CCHServer, *},
+        dijkstra::{query::dijkstra::Server as DijkServer, *},
+        *,
+    },
+    datastr::{
+        graph::{compressed::CompressedGraph, *},
+        node_order::NodeOrder,
+    },
+    experiments::{queries::*, verification::*},
+    io::*,
+};
+
+#[test]
+fn same_distances_as_uncompressed() {
+    let (graph, _, _) = grid();
+    let compressed = CompressedGraph::new(&graph);
+    assert_eq!(compressed.weight_bytes(), 2);
+    assert!(compressed.size_in_bytes() < (graph.first_out().len() + 2 * graph.num_arcs()) * 4);
+
+    let dir = TempDir::new("compressed_graph");
+    compressed.deconstruct_to(&dir.0).unwrap();
+    let compressed = CompressedGraph::reconstruct_from(&dir.0).unwrap();
+
+    let queries = uniform(graph.num_nodes(), 300, &mut StdRng::from_seed(Default::default()));
+    let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(graph.clone());
+    let mut compressed_dijkstra = DijkServer::<DefaultOps, _, _>::new(compressed.clone());
+    verify(&graph, &mut dijkstra, &mut compressed_dijkstra, queries.iter().cloned()).assert_ok();
+
+    let cch = contract(&compressed, NodeOrder::identity(graph.num_nodes()));
+    let mut cch_server = CCHServer::new(customize(&cch, &compressed));
+    let mut verification = Verification::new();
+    for &query in &queries {
+        let reference = QueryServer::query(&mut dijkstra, query).map(|mut res| (res.distance(), res.path()));
+        let candidate = cch_server.query(query).map(|mut res| (res.distance(), res.path()));
+        verification.check(&graph, query, reference, candidate);
+    }
+    verification.assert_ok();
+}
+
+#[test]
+fn inconsistent_data_is_rejected() {
+    let (graph, _, _) = grid();
+    let dir = TempDir::new("inconsistent_compressed_graph");
+    let corrupted = |file: &str, corrupt: &dyn Fn(&mut Vec<u8>)| {
+        CompressedGraph::new(&graph).deconstruct_to(&dir.0).unwrap();
+        let mut bytes = std::fs::read(dir.0.join(file)).unwrap();
+        corrupt(&mut bytes);
+        std::fs::write(dir.0.join(file), bytes).unwrap();
+        CompressedGraph::reconstruct_from(&dir.0)
+    };
+
+    assert!(corrupted("compressed_head", &|_| ()).is_ok());
+    // the first arc of node 0 now points to node -1
+    assert!(corrupted("compressed_head", &|bytes| bytes[0] = 1).is_err());
+    assert!(corrupted("compressed_head", &|bytes| *bytes.last_mut().unwrap() |= 0x80).is_err());
+    // the offset of the second block is not behind the last arc of the first one anymore
+    assert!(corrupted("compressed_head_block_first_byte", &|bytes| bytes[8] += 1).is_err());
+}
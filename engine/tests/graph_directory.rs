@@ -0,0 +1,70 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rust_road_router::{
+    datastr::graph::*,
+    io::{graph_directory::Extent, *},
+};
+use std::{io::ErrorKind, sync::Arc};
+
+fn graph_dir(name: &str) -> TempDir {
+    let dir = TempDir::new(&format!("graph_directory_{}", name));
+    // 0 -> 1 -> 2 -> 0, 1 -> 0
+    vec![0u32, 1, 3, 4].write_to(&dir.join("first_out")).unwrap();
+    vec![1u32, 2, 0, 0].write_to(&dir.join("head")).unwrap();
+    vec![1u32, 2, 3, 4].write_to(&dir.join("travel_time")).unwrap();
+    vec![49.0f32, 49.1, 49.2].write_to(&dir.join("latitude")).unwrap();
+    vec![2u32, 0, 1].write_to(&dir.join("cch_perm")).unwrap();
+    dir
+}
+
+#[test]
+fn lazy_typed_access() {
+    let path = graph_dir("lazy");
+    let dir = GraphDirectory::open(&path).unwrap();
+    assert_eq!(dir.num_nodes().unwrap(), 3);
+    assert_eq!(dir.num_arcs().unwrap(), 4);
+    assert!(dir.has("latitude"));
+    assert!(!dir.has("longitude"));
+
+    let travel_time = dir.travel_time().unwrap();
+    assert_eq!(&travel_time[..], &[1, 2, 3, 4]);
+    // cached attributes are shared
+    assert!(Arc::ptr_eq(&travel_time, &dir.travel_time().unwrap()));
+    assert!(Arc::ptr_eq(&travel_time, &dir.arc_attribute::<Weight>("travel_time").unwrap()));
+    assert_eq!(dir.arc_attribute::<u16>("travel_time").unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    // changes on disk are only visible after evicting
+    vec![5u32, 6, 7, 8].write_to(&path.join("travel_time")).unwrap();
+    assert_eq!(&dir.travel_time().unwrap()[..], &[1, 2, 3, 4]);
+    dir.evict("travel_time");
+    assert_eq!(&dir.travel_time().unwrap()[..], &[5, 6, 7, 8]);
+
+    let graph = dir.travel_time_graph().unwrap();
+    assert_eq!(graph.num_nodes(), 3);
+    assert_eq!(graph.edge_index(1, 0).map(|edge| graph.link(edge).weight), Some(7));
+
+    let cch = dir.cch().unwrap();
+    assert_eq!(cch.num_nodes(), 3);
+}
+
+#[test]
+fn invalid_attributes() {
+    let path = graph_dir("invalid");
+    let dir = GraphDirectory::open(&path).unwrap();
+
+    assert_eq!(dir.longitude().unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(GraphDirectory::open(path.join("nope")).unwrap_err().kind(), ErrorKind::NotFound);
+
+    vec![1u32, 2, 3].write_to(&path.join("geo_distance")).unwrap();
+    assert_eq!(dir.geo_distance().unwrap_err().kind(), ErrorKind::InvalidData);
+    vec![1u8, 2, 3, 4, 5].write_to(&path.join("custom")).unwrap();
+    assert_eq!(dir.attribute::<u32>("custom", Extent::Arcs).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(dir.attribute::<u8>("custom", Extent::Arcs).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    vec![1u32, 2, 3, 0].write_to(&path.join("head")).unwrap();
+    assert_eq!(dir.head().unwrap_err().kind(), ErrorKind::InvalidData);
+    assert!(dir.travel_time_graph().is_err());
+}
@@ -0,0 +1,98 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rust_road_router::{datastr::graph::*, experiments::harness::*, io::*};
+use std::path::Path;
+
+// The grid of the potential tests, written to disk as a graph directory.
+fn write_grid(dir: &Path) {
+    let (graph, lat, lng) = grid();
+    // block horizontal arcs in the middle row
+    let arc_category: Vec<u8> = (0..graph.num_nodes() as NodeId)
+        .flat_map(|node| LinkIterable::<NodeId>::link_iter(&graph, node).map(move |head| (node, head)))
+        .map(|(tail, head)| (tail as usize / GRID_SIZE == GRID_SIZE / 2 && head as usize / GRID_SIZE == GRID_SIZE / 2) as u8)
+        .collect();
+
+    std::fs::create_dir_all(dir).unwrap();
+    graph.first_out().write_to(&dir.join("first_out")).unwrap();
+    graph.head().write_to(&dir.join("head")).unwrap();
+    graph.weight().write_to(&dir.join("travel_time")).unwrap();
+    arc_category.write_to(&dir.join("arc_category")).unwrap();
+    lat.write_to(&dir.join("latitude")).unwrap();
+    lng.write_to(&dir.join("longitude")).unwrap();
+    (0..(GRID_SIZE * GRID_SIZE) as NodeId)
+        .collect::<Vec<_>>()
+        .write_to(&dir.join("cch_perm"))
+        .unwrap();
+}
+
+#[test]
+fn all_algorithms_agree() {
+    let dir = TempDir::new("harness");
+    write_grid(&dir);
+
+    for modification in &[MetricModification::None, MetricModification::Scale(1.5), MetricModification::BlockCategories(1)] {
+        let run = |algorithm| {
+            let mut experiment = Experiment::new("test", &dir.0, algorithm);
+            experiment.modification = modification.clone();
+            experiment.queries = QuerySet::Random(200);
+            experiment.repetitions = 2;
+            experiment.run().unwrap()
+        };
+
+        let expected = run(Algorithm::Dijkstra);
+        assert_eq!(expected.results.len(), 200);
+        assert_eq!(expected.num_query_runs, 400);
+        assert!(expected.results.iter().all(Option::is_some));
+
+        for algorithm in vec![
+            Algorithm::BidirectionalDijkstra,
+            Algorithm::CCH,
+            Algorithm::Topocore,
+            Algorithm::CHPotentials,
+            Algorithm::ALT { landmarks: 4, active: 2 },
+            Algorithm::Geo,
+        ] {
+            assert_eq!(run(algorithm.clone()).results, expected.results, "{:?} {:?}", algorithm, modification);
+        }
+    }
+}
+
+#[test]
+fn parse_config() {
+    let config = serde_json::json!([
+        { "graph": "ger", "algorithm": "dijkstra" },
+        {
+            "name": "alt_scaled",
+            "graph": "/data/ger",
+            "algorithm": { "alt": { "landmarks": 8 } },
+            "modification": { "scale": 1.05 },
+            "queries": { "dir": "queries/uniform" },
+            "repetitions": 3,
+            "seed": 42,
+            "unify_parallel_edges": true,
+            "unreachable_as_infinity": true
+        }
+    ]);
+    let dir = TempDir::new("harness_config");
+    let path = dir.join("experiments.json");
+    std::fs::write(&path, config.to_string()).unwrap();
+    let experiments = Experiment::load_all(&path).unwrap();
+
+    assert_eq!(experiments.len(), 2);
+    assert_eq!(experiments[0], Experiment::new("", dir.join("ger"), Algorithm::Dijkstra));
+    assert_eq!(experiments[1].name, "alt_scaled");
+    assert_eq!(experiments[1].graph_dir, Path::new("/data/ger"));
+    assert_eq!(experiments[1].algorithm, Algorithm::ALT { landmarks: 8, active: 4 });
+    assert_eq!(experiments[1].modification, MetricModification::Scale(1.05));
+    assert_eq!(experiments[1].queries, QuerySet::Stored(dir.join("queries/uniform")));
+    assert_eq!(experiments[1].repetitions, 3);
+    assert_eq!(experiments[1].seed[0], 42);
+    assert!(experiments[1].unify_parallel_edges);
+    assert!(experiments[1].unreachable_as_infinity);
+
+    assert!(Experiment::from_json(&serde_json::json!({ "graph": "ger", "algorithm": "magic" }), &dir).is_err());
+    assert!(Experiment::from_json(&serde_json::json!({ "algorithm": "dijkstra" }), &dir).is_err());
+}
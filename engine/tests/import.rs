@@ -0,0 +1,93 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rust_road_router::{
+    datastr::graph::time_dependent::*,
+    import::{dimacs, time_dependent::*},
+    io::*,
+};
+use std::path::{Path, PathBuf};
+
+fn temp_file(dir: &Path, name: &str, content: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn dimacs_graph_and_coords() {
+    let dir = TempDir::new("import_dimacs");
+    let gr = temp_file(
+        &dir,
+        "graph.gr",
+        "c some comment\np sp 3 4\nc arcs are not sorted\na 2 3 5\na 1 2 3\na 3 1 7\na 2 1 4\n",
+    );
+    let graph = dimacs::read_graph(&gr).unwrap();
+    assert_eq!(graph.first_out(), &[0, 1, 3, 4]);
+    assert_eq!(graph.head(), &[1, 2, 0, 0]);
+    assert_eq!(graph.weight(), &[3, 5, 4, 7]);
+
+    let co = temp_file(
+        &dir,
+        "graph.co",
+        "p aux sp co 3\nv 3 8400000 49000000\nv 1 8000000 49500000\nv 2 -73500000 41250000\n",
+    );
+    let (lat, lng) = dimacs::read_coords(&co).unwrap();
+    assert_eq!(lat, vec![49.5, 41.25, 49.0]);
+    assert_eq!(lng, vec![8.0, -73.5, 8.4]);
+
+    assert!(dimacs::read_graph(temp_file(&dir, "too_few.gr", "p sp 3 2\na 1 2 3\n")).is_err());
+    assert!(dimacs::read_graph(temp_file(&dir, "out_of_range.gr", "p sp 3 1\na 1 4 3\n")).is_err());
+    assert!(dimacs::read_graph(temp_file(&dir, "no_header.gr", "a 1 2 3\n")).is_err());
+    assert!(dimacs::read_coords(temp_file(&dir, "missing.co", "p aux sp co 2\nv 1 1 1\n")).is_err());
+}
+
+#[test]
+fn tpgr() {
+    let dir = TempDir::new("import_tpgr");
+    let tpgr = temp_file(&dir, "graph.tpgr", "3 3 5 864000\n1 2 1 0 10\n0 1 2 0 20 432000 30.5\n2 0 2 0 5 100 6\n");
+    let graph = read_tpgr(&tpgr).unwrap();
+    assert_eq!(graph.period, DAY);
+    assert_eq!(graph.first_out, vec![0, 1, 2, 3]);
+    assert_eq!(graph.head, vec![1, 2, 0]);
+    assert_eq!(graph.first_ipp_of_arc, vec![0, 2, 3, 5]);
+    assert_eq!(graph.ipp_departure_time, vec![0, DAY / 2, 0, 0, 10_000]);
+    assert_eq!(graph.ipp_travel_time, vec![2000, 3050, 1000, 500, 600]);
+
+    let graph_dir = dir.join("graph");
+    std::fs::create_dir_all(&graph_dir).unwrap();
+    graph.deconstruct_to(&graph_dir.to_str().unwrap()).unwrap();
+    assert_eq!(Vec::<Timestamp>::load_from(graph_dir.join("period")).unwrap(), vec![DAY]);
+    let td_graph = GraphDirectory::open(&graph_dir).unwrap().td_graph().unwrap();
+    assert_eq!(td_graph.travel_time_function(0).eval(DAY / 4), 2525);
+
+    assert!(read_tpgr(temp_file(&dir, "wrong_count.tpgr", "2 1 2 864000\n0 1 1 0 10\n")).is_err());
+    assert!(read_tpgr(temp_file(&dir, "decreasing.tpgr", "2 1 2 864000\n0 1 2 10 10 5 10\n")).is_err());
+    assert!(read_tpgr(temp_file(&dir, "no_points.tpgr", "2 1 0 864000\n0 1 0\n")).is_err());
+}
+
+#[test]
+fn tbgr() {
+    let dir = TempDir::new("import_tbgr");
+    let mut bytes = Vec::new();
+    for value in &[2u32, 2, 2, 3, DAY, 0, 1, 1, 0, 0, 2, 0, 1000, 1500, 1000, 0, 2000] {
+        bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+    let tbgr = temp_file(&dir, "graph.tbgr", "");
+    std::fs::write(&tbgr, &bytes).unwrap();
+    let graph = read_tbgr(&tbgr).unwrap();
+    assert_eq!(graph.period, DAY);
+    assert_eq!(graph.first_out, vec![0, 1, 2]);
+    assert_eq!(graph.head, vec![1, 0]);
+    assert_eq!(graph.first_ipp_of_arc, vec![0, 2, 3]);
+    assert_eq!(graph.ipp_departure_time, vec![0, 1500, 0]);
+    assert_eq!(graph.ipp_travel_time, vec![1000, 1000, 2000]);
+
+    std::fs::write(&tbgr, &bytes[..bytes.len() - 4]).unwrap();
+    assert!(read_tbgr(&tbgr).is_err());
+    bytes[..4].copy_from_slice(&1u32.to_ne_bytes());
+    std::fs::write(&tbgr, &bytes).unwrap();
+    assert!(read_tbgr(&tbgr).is_err());
+}
@@ -1,6 +1,10 @@
 extern crate rust_road_router;
 
-use rust_road_router::link_speed_estimates::*;
+use rust_road_router::{
+    algo::dijkstra::{query::dijkstra::Server as DijkServer, DefaultOps},
+    datastr::graph::{time_dependent::*, *},
+    link_speed_estimates::{map_matching::*, profile_aggregation::*, streaming::*, *},
+};
 
 #[test]
 fn check_for_empty_errors() {
@@ -40,6 +44,43 @@ fn two_points_one_link() {
     );
 }
 
+#[test]
+fn stationary_vehicle() {
+    let links = vec![LinkData {
+        link_id: 1,
+        length: 10000,
+        speed_limit: 50,
+    }];
+    // standing at the first position for half a second, then the same as in `two_points_one_link`
+    let traces = vec![
+        TraceData {
+            timestamp: 100000,
+            link_id: 1,
+            traversed_in_travel_direction_fraction: 0.1,
+        },
+        TraceData {
+            timestamp: 100500,
+            link_id: 1,
+            traversed_in_travel_direction_fraction: 0.1,
+        },
+        TraceData {
+            timestamp: 101500,
+            link_id: 1,
+            traversed_in_travel_direction_fraction: 0.9,
+        },
+    ];
+    let result: Vec<LinkSpeedData> = estimate_iter(Box::new(links.iter()), Box::new(traces.iter())).unwrap().collect();
+    assert_eq!(
+        result,
+        vec![LinkSpeedData {
+            link_id: 1,
+            link_entered_timestamp: 100375,
+            estimate_quality: 0.9 - 0.1,
+            velocity: 28.8
+        }]
+    );
+}
+
 #[test]
 fn two_points_two_links() {
     let links = vec![
@@ -334,3 +375,209 @@ fn speed_limits_after_initial() {
         ]
     );
 }
+
+#[test]
+fn map_matched_trace_feeds_estimator() {
+    // Four nodes on a straight east-west road, roughly 73m apart, arcs in both directions.
+    //
+    //     0 <---> 1 <---> 2 <---> 3
+    //
+    let graph = OwnedGraph::new(vec![0, 1, 3, 5, 6], vec![1, 0, 2, 1, 3, 2], vec![73; 6]);
+    let lat = vec![49.0; 4];
+    let lng = vec![8.400, 8.401, 8.402, 8.403];
+    let speed_limit = vec![50; 6];
+
+    let server = DijkServer::<DefaultOps, _, _>::new(graph.clone());
+    let mut matcher = MapMatcher::new(&graph, &lat, &lng, graph.weight(), server, MapMatchingParams::default());
+
+    let fixes = vec![
+        GpsFix {
+            timestamp: 100_000,
+            latitude: 49.00002,
+            longitude: 8.4003,
+        },
+        GpsFix {
+            timestamp: 110_000,
+            latitude: 49.00002,
+            longitude: 8.4015,
+        },
+        GpsFix {
+            timestamp: 120_000,
+            latitude: 48.99998,
+            longitude: 8.4025,
+        },
+    ];
+
+    let matched = matcher.match_trace(&fixes);
+    assert_eq!(matched.len(), 1);
+    let matched = &matched[0];
+    assert_eq!(matched.path, vec![0, 2, 4]);
+    assert_eq!(matched.traces.iter().map(|trace| trace.link_id).collect::<Vec<_>>(), vec![0, 2, 4]);
+    for (trace, expected) in matched.traces.iter().zip(&[0.3, 0.5, 0.5]) {
+        assert!((trace.traversed_in_travel_direction_fraction - expected).abs() < 0.01);
+    }
+
+    let links = matched.links(graph.weight(), &speed_limit);
+    let result: Vec<LinkSpeedData> = estimate_iter(Box::new(links.iter()), Box::new(matched.traces.iter())).unwrap().collect();
+    assert_eq!(result.iter().map(|estimate| estimate.link_id).collect::<Vec<_>>(), vec![0, 2, 4]);
+}
+
+#[test]
+fn aggregate_observations_into_profiles() {
+    let geo_distance = vec![100, 100];
+    let free_flow = vec![7200, 7200];
+    let mut aggregator = ProfileAggregator::new(&geo_distance, &free_flow, AggregationParams::default());
+
+    let hour: u64 = 3600 * 1000;
+    let observations = (0..10).flat_map(|day| {
+        vec![
+            // slow during the morning, 10s for 100m
+            LinkSpeedData {
+                link_id: 0,
+                link_entered_timestamp: day * 24 * hour + 8 * hour,
+                estimate_quality: 1.0,
+                velocity: 36.0,
+            },
+            // broken estimate which should not affect the median
+            LinkSpeedData {
+                link_id: 0,
+                link_entered_timestamp: day * 24 * hour + 8 * hour + 1,
+                estimate_quality: 0.1,
+                velocity: 1.0,
+            },
+            // free flow in the evening
+            LinkSpeedData {
+                link_id: 0,
+                link_entered_timestamp: day * 24 * hour + 20 * hour,
+                estimate_quality: 1.0,
+                velocity: 50.0,
+            },
+            // unknown link
+            LinkSpeedData {
+                link_id: 42,
+                link_entered_timestamp: day * 24 * hour,
+                estimate_quality: 1.0,
+                velocity: 50.0,
+            },
+        ]
+        .into_iter()
+    });
+    aggregator.extend(observations, |link_id| if link_id < 2 { Some(link_id as EdgeId) } else { None });
+
+    let profiles = aggregator.profiles();
+    assert_eq!(profiles.first_ipp_of_arc.len(), 3);
+    // no observations - constant free flow profile
+    assert_eq!(profiles.first_ipp_of_arc[2] - profiles.first_ipp_of_arc[1], 1);
+
+    let graph = profiles.into_td_graph(vec![0, 2, 2], vec![1, 1]);
+    let morning = graph.travel_time_function(0).eval((8 * hour) as Timestamp);
+    let evening = graph.travel_time_function(0).eval((20 * hour) as Timestamp);
+    assert!(morning > 9800 && morning <= 10000, "{}", morning);
+    assert!(evening >= 7200 && evening < 7400, "{}", evening);
+    assert_eq!(graph.travel_time_function(1).eval((8 * hour) as Timestamp), 7200);
+    assert!(graph.travel_time_function(0).lower_bound() >= 7200);
+}
+
+#[test]
+fn interleaved_vehicle_stream() {
+    // vehicle 1 drives the same as in `two_points_two_links`, vehicle 2 as in `two_points_one_link`
+    let csv = "\
+1,link,1,10000,80
+2,link,1,10000,50
+1,trace,100000,1,0.5
+2,trace,100000,1,0.1
+1,link,2,30000,80
+2,trace,101000,1,0.9
+1,trace,101000,2,0.5
+";
+    let events = csv_events(csv.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+    let mut result: Vec<LinkSpeedData> = estimate_stream(events.into_iter(), StreamingParams::default()).collect();
+    result.sort_by_key(|estimate| (estimate.link_id, estimate.link_entered_timestamp));
+
+    assert_eq!(
+        result,
+        vec![
+            LinkSpeedData {
+                link_id: 1,
+                link_entered_timestamp: 99750,
+                estimate_quality: 0.125,
+                velocity: 72.0
+            },
+            LinkSpeedData {
+                link_id: 1,
+                link_entered_timestamp: 99875,
+                estimate_quality: 0.9 - 0.1,
+                velocity: 28.8
+            },
+            LinkSpeedData {
+                link_id: 2,
+                link_entered_timestamp: 100250,
+                estimate_quality: 0.375,
+                velocity: 72.0
+            },
+        ]
+    );
+}
+
+#[test]
+fn stream_from_json_lines_resets_invalid_vehicles() {
+    let json = r#"
+{"vehicle_id":7,"type":"link","link_id":1,"length":10000,"speed_limit":50}
+{"vehicle_id":7,"type":"trace","timestamp":100000,"link_id":1,"fraction":0.1}
+{"vehicle_id":7,"type":"trace","timestamp":100500,"link_id":3,"fraction":0.2}
+{"vehicle_id":7,"type":"link","link_id":1,"length":10000,"speed_limit":50}
+{"vehicle_id":7,"type":"trace","timestamp":200000,"link_id":1,"fraction":0.1}
+{"vehicle_id":7,"type":"trace","timestamp":201000,"link_id":1,"fraction":0.9}
+"#;
+    let mut estimator = StreamingEstimator::new(StreamingParams::default());
+    for event in json_lines_events(json.as_bytes()) {
+        estimator.push(event.unwrap());
+    }
+    // the trace on link 3 did not match and is also no valid start
+    assert_eq!(estimator.num_resets(), 1);
+    assert_eq!(estimator.pop_completed(), None);
+
+    estimator.finish();
+    assert_eq!(estimator.num_active_vehicles(), 0);
+    assert_eq!(
+        estimator.pop_completed(),
+        Some(LinkSpeedData {
+            link_id: 1,
+            link_entered_timestamp: 199875,
+            estimate_quality: 0.9 - 0.1,
+            velocity: 28.8
+        })
+    );
+}
+
+#[test]
+fn expired_vehicles_are_finished_in_id_order() {
+    // every vehicle has a complete estimate for the link with its id, which is only emitted once it is finished
+    let vehicles = [9, 3, 7, 1, 5];
+    let mut csv = String::new();
+    for vehicle in &vehicles {
+        csv.push_str(&format!("{},link,{},10000,50\n", vehicle, vehicle));
+        csv.push_str(&format!("{},trace,100000,{},0.1\n", vehicle, vehicle));
+        csv.push_str(&format!("{},trace,101000,{},0.9\n", vehicle, vehicle));
+    }
+    let events = csv_events(csv.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+
+    let mut estimator = StreamingEstimator::new(StreamingParams::default());
+    for event in events.iter().cloned() {
+        estimator.push(event);
+    }
+    assert_eq!(estimator.pop_completed(), None);
+    // a trace of another vehicle much later expires all of them
+    estimator.push(csv_events("100,trace,1000000,1,0.5\n".as_bytes()).next().unwrap().unwrap());
+    assert_eq!(estimator.num_active_vehicles(), 1);
+    let expired: Vec<u64> = std::iter::from_fn(|| estimator.pop_completed()).map(|estimate| estimate.link_id).collect();
+    assert_eq!(expired, vec![1, 3, 5, 7, 9]);
+
+    let mut estimator = StreamingEstimator::new(StreamingParams::default());
+    for event in events {
+        estimator.push(event);
+    }
+    estimator.finish();
+    let finished: Vec<u64> = std::iter::from_fn(|| estimator.pop_completed()).map(|estimate| estimate.link_id).collect();
+    assert_eq!(finished, vec![1, 3, 5, 7, 9]);
+}
@@ -0,0 +1,133 @@
+extern crate rust_road_router;
+
+use rust_road_router::{
+    algo::{
+        ch_potentials::live_td::LiveTDServer,
+        customizable_contraction_hierarchy::contract,
+        dijkstra::query::{dijkstra::Server as DijkServer, td_dijkstra::LiveTDDijkstraOps},
+        *,
+    },
+    datastr::{
+        graph::{time_dependent::*, *},
+        node_order::NodeOrder,
+    },
+    import::live_traffic::*,
+    util::in_range_option::InRangeOption,
+};
+use std::{sync::mpsc, time::Duration};
+
+#[test]
+fn ingest_and_expire_snapshots() {
+    let snapshot = "0,true,36,1000\n0,false,72,1000\n1,true,0,2000\n7,true,50,2000\ninvalid\n1,true,36,500\n";
+    let records: Vec<LiveRecord> = parse_snapshot(snapshot.as_bytes()).filter_map(Result::ok).collect();
+    assert_eq!(records.len(), 5);
+
+    // link 0 has two directions mapped to arcs 0 and 1, link 1 only the from ref direction mapped to arc 2
+    let link_to_edge = |link_id: u64, from_ref: bool| match (link_id, from_ref) {
+        (0, true) => Some(0),
+        (0, false) => Some(1),
+        (1, true) => Some(2),
+        _ => None,
+    };
+
+    let mut traffic = LiveTraffic::new(vec![100, 100, 50], 10_000);
+    let stats = traffic.ingest(records, 3000, link_to_edge);
+    assert_eq!(
+        stats,
+        IngestStats {
+            num_records: 5,
+            num_matched: 4,
+            num_outdated: 1
+        }
+    );
+
+    assert_eq!(traffic.travel_time(0), Some(10_000));
+    assert_eq!(traffic.travel_time(1), Some(5000));
+    assert_eq!(traffic.travel_time(2), Some(INFINITY));
+    assert_eq!(traffic.apply_to_metric(&[1, 2, 3]), vec![10_000, 5000, INFINITY]);
+
+    assert_eq!(traffic.expire(11_500), 2);
+    assert_eq!(traffic.num_live(), 1);
+    assert_eq!(traffic.apply_to_metric(&[1, 2, 3]), vec![1, 2, INFINITY]);
+}
+
+#[test]
+fn ingestion_batches_updates() {
+    let (tx, rx) = mpsc::channel();
+    let now = unix_now();
+    tx.send(vec![LiveRecord {
+        link_id: 0,
+        from_ref: true,
+        speed: 36,
+        observed_at: now,
+    }])
+    .unwrap();
+    tx.send(vec![LiveRecord {
+        link_id: 1,
+        from_ref: true,
+        speed: 72,
+        observed_at: now,
+    }])
+    .unwrap();
+    drop(tx);
+
+    let params = LiveTrafficParams {
+        min_update_interval: Duration::from_secs(3600),
+        ..LiveTrafficParams::default()
+    };
+    let mut metrics = Vec::new();
+    run_ingestion(
+        rx,
+        LiveTraffic::new(vec![100, 100], params.max_age),
+        params,
+        |link_id, _| Some(link_id as EdgeId),
+        |traffic| metrics.push(traffic.apply_to_metric(&[1, 1])),
+    );
+
+    // the first batch is applied right away, the second one is rate limited and only published when the senders hang up
+    assert_eq!(metrics, vec![vec![10_000, 1], vec![10_000, 5_000]]);
+}
+
+#[test]
+fn live_td_server_matches_dijkstra() {
+    //          10
+    //      +-------> 1 --+
+    //      |             | 10
+    //      0             v
+    //      |             3
+    //      +-------> 2 --^
+    //          15       10
+    let first_out = vec![0, 2, 3, 4, 4];
+    let head = vec![1, 2, 3, 3];
+    let predicted = TDGraph::new(first_out.clone(), head.clone(), vec![0, 1, 2, 3, 4], vec![0; 4], vec![10, 15, 10, 10]);
+    let cch = contract(
+        &FirstOutGraph::new(&first_out[..], &head[..], vec![10, 15, 10, 10]),
+        NodeOrder::from_node_order(vec![0, 1, 2, 3]),
+    );
+
+    let mut server = LiveTDServer::new(&cch, predicted.clone());
+    let query = |server: &mut LiveTDServer, departure| {
+        server
+            .query(TDQuery { from: 0, to: 3, departure })
+            .map(|mut result| (result.distance(), result.path()))
+    };
+    assert_eq!(query(&mut server, 0), Some((20, vec![0, 1, 3])));
+
+    // congestion on 0 -> 1, only relevant for departures before soon
+    let mut live = vec![InRangeOption::new(None); 4];
+    live[0] = InRangeOption::new(Some(100));
+    server.update_live(live.clone(), 1000);
+    assert_eq!(query(&mut server, 0), Some((25, vec![0, 2, 3])));
+    assert_eq!(query(&mut server, 5000), Some((20, vec![0, 1, 3])));
+
+    // faster than predicted, the potential has to be recomputed
+    live[1] = InRangeOption::new(Some(1));
+    server.update_live(live.clone(), 1000);
+    assert_eq!(query(&mut server, 0), Some((11, vec![0, 2, 3])));
+
+    let mut dijkstra = DijkServer::<LiveTDDijkstraOps, _, _>::new(LiveTDGraph::new(predicted, 1000, live));
+    for &departure in &[0, 500, 990, 5000] {
+        let arrival = TDQueryServer::query(&mut dijkstra, TDQuery { from: 0, to: 3, departure }).map(|result| result.distance());
+        assert_eq!(query(&mut server, departure).map(|(distance, _)| distance + departure), arrival);
+    }
+}
@@ -0,0 +1,91 @@
+extern crate rust_road_router;
+
+use rust_road_router::{
+    algo::{
+        catchup::{customize as catchup_customize, Server as CATCHUpServer},
+        customizable_contraction_hierarchy::contract,
+        dijkstra::query::{dijkstra::Server as DijkServer, td_dijkstra::TDDijkstraOps},
+        time_dependent_sampling::{default_windows, Server as TDSServer},
+        *,
+    },
+    datastr::{
+        graph::{floating_time_dependent, time_dependent::*},
+        node_order::NodeOrder,
+    },
+    link_speed_estimates::profile_aggregation::Profiles,
+};
+
+const MINUTE: Timestamp = 60 * 1000;
+const HOUR: Timestamp = 60 * MINUTE;
+
+// The period is global, so everything with a weekly period has to happen in this one test.
+#[test]
+fn weekly_profiles() {
+    configure_period(WEEK);
+
+    // 0 -> 1 -> 3 is fast except for a rush hour around 8:00 on 0 -> 1 on Mondays,
+    // 0 -> 2 -> 3 always takes 21 minutes.
+    let monday = Profiles {
+        first_ipp_of_arc: vec![0, 4, 5, 6, 7],
+        ipp_departure_time: vec![0, 7 * HOUR, 8 * HOUR, 9 * HOUR, 0, 0, 0],
+        ipp_travel_time: vec![10 * MINUTE, 10 * MINUTE, 60 * MINUTE, 10 * MINUTE, 20 * MINUTE, MINUTE, MINUTE],
+    };
+    let other_day = || Profiles {
+        first_ipp_of_arc: vec![0, 1, 2, 3, 4],
+        ipp_departure_time: vec![0, 0, 0, 0],
+        ipp_travel_time: vec![10 * MINUTE, 20 * MINUTE, MINUTE, MINUTE],
+    };
+    let mut days = vec![monday];
+    days.extend((1..7).map(|_| other_day()));
+    let profiles = Profiles::from_days(&days);
+    assert_eq!(&profiles.first_ipp_of_arc[..], &[0, 10, 11, 12, 13]);
+
+    let first_out = vec![0, 2, 3, 4, 4];
+    let head = vec![1, 2, 3, 3];
+    let floating_graph = floating_time_dependent::TDGraph::new(
+        first_out.clone(),
+        head.clone(),
+        profiles.first_ipp_of_arc.clone(),
+        profiles.ipp_departure_time.clone(),
+        profiles.ipp_travel_time.clone(),
+    );
+    let graph = profiles.into_td_graph(first_out, head);
+
+    // 1970-01-05 was a Monday
+    let calendar = Calendar::weekly(0);
+    let monday_8 = calendar.departure(u64::from(4 * DAY + 8 * HOUR));
+    let tuesday_8 = calendar.departure(u64::from(5 * DAY + 8 * HOUR));
+    assert_eq!(monday_8, 8 * HOUR);
+    assert_eq!(tuesday_8, DAY + 8 * HOUR);
+    assert_eq!(calendar.day(u64::from(5 * DAY + 8 * HOUR)), 1);
+    assert_eq!(calendar.departure(u64::from(11 * DAY + 8 * HOUR)), monday_8);
+    assert_eq!(Calendar::weekly(i64::from(HOUR)).departure(u64::from(4 * DAY + 8 * HOUR)), 9 * HOUR);
+
+    assert_eq!(graph.travel_time_function(0).eval(monday_8), 60 * MINUTE);
+    assert_eq!(graph.travel_time_function(0).eval(tuesday_8), 10 * MINUTE);
+    assert_eq!(graph.travel_time_function(0).eval(monday_8 + WEEK), 60 * MINUTE);
+
+    let mut dijkstra = DijkServer::<TDDijkstraOps, _, _>::new(graph.clone());
+    let mut arrival =
+        |departure| TDQueryServer::query(&mut dijkstra, TDQuery { from: 0, to: 3, departure }).map(|mut result| (result.distance(), result.path()));
+    assert_eq!(arrival(monday_8), Some((monday_8 + 21 * MINUTE, vec![0, 2, 3])));
+    assert_eq!(arrival(tuesday_8), Some((tuesday_8 + 11 * MINUTE, vec![0, 1, 3])));
+
+    let cch = contract(&graph, NodeOrder::from_node_order(vec![0, 1, 2, 3]));
+
+    assert_eq!(default_windows().len(), 28);
+    let mut tds = TDSServer::new(graph.clone(), &cch);
+    let mut tds_path = |departure| tds.query(TDQuery { from: 0, to: 3, departure }).map(|mut result| result.path());
+    assert_eq!(tds_path(monday_8), Some(vec![0, 2, 3]));
+    assert_eq!(tds_path(tuesday_8), Some(vec![0, 1, 3]));
+
+    assert_eq!(floating_time_dependent::period(), floating_time_dependent::Timestamp::new(7.0 * 86_400.0));
+    let customized = catchup_customize(&cch, &floating_graph);
+    let mut catchup = CATCHUpServer::new(&cch, &customized);
+    let mut travel_time = |departure: Timestamp| {
+        let departure = floating_time_dependent::Timestamp::new(f64::from(departure) / 1000.0);
+        f64::from(catchup.query(TDQuery { from: 0, to: 3, departure }).unwrap().distance())
+    };
+    assert!((travel_time(monday_8) - 21.0 * 60.0).abs() < 0.001);
+    assert!((travel_time(tuesday_8) - 11.0 * 60.0).abs() < 0.001);
+}
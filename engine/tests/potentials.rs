@@ -0,0 +1,281 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rand::prelude::*;
+use rust_road_router::{
+    algo::{
+        ch_potentials::{alt::*, bidirectional_query::Server as BidirServer, query::Server as TopoServer, td_turns::*, *},
+        customizable_contraction_hierarchy::{contract, customize},
+        dijkstra::{
+            query::{
+                dijkstra::Server as DijkServer,
+                td_dijkstra::{LiveTDDijkstraOps, TDDijkstraOps},
+            },
+            *,
+        },
+        *,
+    },
+    datastr::{
+        graph::{time_dependent::*, *},
+        node_order::NodeOrder,
+    },
+    io::*,
+    util::in_range_option::InRangeOption,
+};
+
+#[test]
+fn geo_potential_static_queries() {
+    let (graph, lat, lng) = grid();
+    let potential = GeoPotential::new(&graph, &lat, &lng);
+    assert!(potential.max_speed() > 0.0);
+
+    let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(graph.clone());
+    let mut a_star = DijkServer::<DefaultOps, _, _>::with_potential(graph.clone(), GeoPotential::new(&graph, &lat, &lng));
+    let mut topocore: TopoServer<_, _, OwnedGraph> = TopoServer::new(&graph, potential, DefaultOps::default());
+
+    for from in (0..graph.num_nodes() as NodeId).step_by(7) {
+        for to in 0..graph.num_nodes() as NodeId {
+            let expected = QueryServer::query(&mut dijkstra, Query { from, to }).map(|res| res.distance());
+            assert_eq!(QueryServer::query(&mut a_star, Query { from, to }).map(|res| res.distance()), expected);
+            assert_eq!(QueryServer::query(&mut topocore, Query { from, to }).map(|res| res.distance()), expected);
+        }
+    }
+}
+
+// The grid with every arc twice as slow around 8:00
+fn td_grid(graph: &OwnedGraph) -> TDGraph {
+    let hour = 60 * 60 * 1000;
+    let mut first_ipp_of_arc = vec![0];
+    let mut ipp_departure_time = Vec::new();
+    let mut ipp_travel_time = Vec::new();
+    for &weight in graph.weight() {
+        ipp_departure_time.extend_from_slice(&[0, 7 * hour, 8 * hour, 9 * hour]);
+        ipp_travel_time.extend_from_slice(&[weight, weight, 2 * weight, weight]);
+        first_ipp_of_arc.push(ipp_departure_time.len() as u32);
+    }
+    TDGraph::new(
+        graph.first_out().to_vec(),
+        graph.head().to_vec(),
+        first_ipp_of_arc,
+        ipp_departure_time,
+        ipp_travel_time,
+    )
+}
+
+#[test]
+fn geo_potential_td_queries() {
+    let (graph, lat, lng) = grid();
+    let hour = 60 * 60 * 1000;
+    let td_graph = td_grid(&graph);
+
+    let lower_bound = (0..td_graph.num_arcs() as EdgeId)
+        .map(|edge_id| td_graph.travel_time_function(edge_id).lower_bound())
+        .collect::<Vec<Weight>>();
+    let lower_bound = FirstOutGraph::new(td_graph.first_out(), td_graph.head(), lower_bound);
+
+    let mut dijkstra = DijkServer::<TDDijkstraOps, _, _>::new(td_graph.clone());
+    let mut topocore = TopoServer::new(&td_graph, GeoPotential::new(&lower_bound, &lat, &lng), TDDijkstraOps::default());
+
+    for &departure in &[0, 7 * hour + hour / 2, 8 * hour] {
+        for from in (0..td_graph.num_nodes() as NodeId).step_by(11) {
+            for to in 0..td_graph.num_nodes() as NodeId {
+                let query = TDQuery { from, to, departure };
+                let expected = TDQueryServer::query(&mut dijkstra, query).map(|res| res.distance() - departure);
+                assert_eq!(TDQueryServer::query(&mut topocore, query).map(|res| res.distance()), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn alt_potentials() {
+    let (graph, lat, lng) = grid();
+    let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(graph.clone());
+    let mut rng = StdRng::from_seed(Default::default());
+
+    for &selection in &[LandmarkSelection::Avoid, LandmarkSelection::Farthest, LandmarkSelection::Planar] {
+        let landmarks = Landmarks::new(&graph, 8, selection, &lat, &lng, &mut rng);
+        assert!(landmarks.num_landmarks() > 1, "{:?}", selection);
+
+        let dir = TempDir::new("alt");
+        landmarks.deconstruct_to(&dir.0).unwrap();
+        let reconstructed = Landmarks::reconstruct_from(&dir.0).unwrap();
+        assert_eq!(reconstructed.landmarks(), landmarks.landmarks());
+
+        let mut a_star = DijkServer::<DefaultOps, _, _>::with_potential(graph.clone(), ALTPotential::new(landmarks, 2));
+        let mut topocore: TopoServer<_, _, OwnedGraph> = TopoServer::new(&graph, ALTPotential::new(reconstructed, 2), DefaultOps::default());
+
+        for from in (0..graph.num_nodes() as NodeId).step_by(5) {
+            for to in 0..graph.num_nodes() as NodeId {
+                let expected = QueryServer::query(&mut dijkstra, Query { from, to }).map(|res| res.distance());
+                assert_eq!(QueryServer::query(&mut a_star, Query { from, to }).map(|res| res.distance()), expected);
+                assert_eq!(QueryServer::query(&mut topocore, Query { from, to }).map(|res| res.distance()), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn bidirectional_ch_potentials() {
+    let (graph, _, _) = grid();
+    let cch = contract(&graph, NodeOrder::identity(graph.num_nodes()));
+    let customized = customize(&cch, &graph);
+    let (forward_up, backward_up) = customized.into_ch_graphs();
+    let ch_graph = |up: &FirstOutGraph<&[EdgeId], &[NodeId], Vec<Weight>>| OwnedGraph::new(up.first_out().to_vec(), up.head().to_vec(), up.weight().to_vec());
+    let (forward_up, backward_up) = (ch_graph(&forward_up), ch_graph(&backward_up));
+
+    // slower everywhere and a few blocked arcs, potentials stay on the original metric
+    let mut modified_weight: Vec<Weight> = graph.weight().iter().map(|&weight| weight * 3 / 2).collect();
+    for weight in modified_weight.iter_mut().step_by(13) {
+        *weight = INFINITY;
+    }
+    let modified_graph = OwnedGraph::new(graph.first_out().to_vec(), graph.head().to_vec(), modified_weight);
+
+    let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(InfinityFilteringGraph(modified_graph.clone()));
+    let mut cch_bidir = BidirServer::new(
+        InfinityFilteringGraph(modified_graph.clone()),
+        CCHPotential::new(&cch, &graph),
+        CCHPotential::new_reversed(&cch, &graph),
+    );
+    let mut ch_bidir = BidirServer::new(
+        InfinityFilteringGraph(modified_graph.clone()),
+        CHPotential::new(forward_up.clone(), backward_up.clone(), NodeOrder::identity(graph.num_nodes())),
+        CHPotential::new(backward_up, forward_up, NodeOrder::identity(graph.num_nodes())),
+    );
+
+    for from in (0..graph.num_nodes() as NodeId).step_by(5) {
+        for to in 0..graph.num_nodes() as NodeId {
+            let expected = QueryServer::query(&mut dijkstra, Query { from, to }).map(|res| res.distance());
+            assert_eq!(QueryServer::query(&mut ch_bidir, Query { from, to }).map(|res| res.distance()), expected);
+
+            let mut result = QueryServer::query(&mut cch_bidir, Query { from, to });
+            assert_eq!(result.as_ref().map(|res| res.distance()), expected);
+            if let Some(result) = result.as_mut() {
+                let path = result.path();
+                assert_eq!((path.first(), path.last()), (Some(&from), Some(&to)));
+                let length: Weight = path
+                    .windows(2)
+                    .map(|arc| {
+                        modified_graph
+                            .edge_index(arc[0], arc[1])
+                            .map(|edge| modified_graph.weight()[edge as usize])
+                            .unwrap()
+                    })
+                    .sum();
+                assert_eq!(Some(length), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn bidirectional_ch_potentials_on_one_way_streets() {
+    // Small grids where many arcs are one-way and weights differ by direction.
+    // The queries run on a slower metric, so shortest paths take detours where the lower bounds of both directions differ a lot.
+    const SIZE: usize = 7;
+    let mut rng = StdRng::from_seed(Default::default());
+    for _ in 0..30 {
+        let mut adjacency_lists = vec![Vec::new(); SIZE * SIZE];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let node = row * SIZE + col;
+                for &neighbor in &[(row + 1, col), (row, col + 1)] {
+                    if neighbor.0 < SIZE && neighbor.1 < SIZE {
+                        let neighbor = neighbor.0 * SIZE + neighbor.1;
+                        let direction = rng.gen_range(0, 3);
+                        if direction != 1 {
+                            adjacency_lists[node].push(Link {
+                                node: neighbor as NodeId,
+                                weight: rng.gen_range(1, 100),
+                            });
+                        }
+                        if direction != 2 {
+                            adjacency_lists[neighbor].push(Link {
+                                node: node as NodeId,
+                                weight: rng.gen_range(1, 100),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        let graph = OwnedGraph::from_adjancecy_lists(adjacency_lists);
+        let cch = contract(&graph, NodeOrder::identity(graph.num_nodes()));
+
+        let slower_weight = graph.weight().iter().map(|&weight| weight * rng.gen_range(2, 10) / 2).collect();
+        let slower_graph = OwnedGraph::new(graph.first_out().to_vec(), graph.head().to_vec(), slower_weight);
+
+        let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(slower_graph.clone());
+        let mut bidir = BidirServer::new(slower_graph, CCHPotential::new(&cch, &graph), CCHPotential::new_reversed(&cch, &graph));
+        for from in 0..graph.num_nodes() as NodeId {
+            for to in 0..graph.num_nodes() as NodeId {
+                let expected = QueryServer::query(&mut dijkstra, Query { from, to }).map(|res| res.distance());
+                assert_eq!(
+                    QueryServer::query(&mut bidir, Query { from, to }).map(|res| res.distance()),
+                    expected,
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn turn_expanded_td_queries() {
+    let (graph, _, _) = grid();
+    let hour = 60 * 60 * 1000;
+    let td_graph = td_grid(&graph);
+    let cch = contract(&graph, NodeOrder::identity(graph.num_nodes()));
+
+    // every fifth turn at inner crossings is forbidden, all other turns take a second.
+    // This keeps the line graph strongly connected, the topocore server would dismiss targets in tiny components.
+    let mut inner_turns = Vec::new();
+    for from_arc in 0..graph.num_arcs() as EdgeId {
+        let node = graph.head()[from_arc as usize];
+        if graph.degree(node) == 4 {
+            inner_turns.extend((graph.first_out()[node as usize]..graph.first_out()[node as usize + 1]).map(|to_arc| (from_arc, to_arc)));
+        }
+    }
+    let (forbidden_turn_from_arc, forbidden_turn_to_arc): (Vec<EdgeId>, Vec<EdgeId>) = inner_turns.into_iter().step_by(5).unzip();
+    let turns = ForbiddenTurns::new(graph.first_out(), graph.head(), &forbidden_turn_from_arc, &forbidden_turn_to_arc);
+    let turn_costs = |from_arc, to_arc| turns.turn_cost(from_arc, to_arc).map(|_| 1000);
+
+    // a traffic jam and a few arcs much faster than predicted until 8:00
+    let mut live = vec![InRangeOption::new(None); graph.num_arcs()];
+    for live in live.iter_mut().step_by(17) {
+        *live = InRangeOption::new(Some(100_000));
+    }
+    for live in live.iter_mut().skip(8).step_by(34) {
+        *live = InRangeOption::new(Some(100));
+    }
+    let jammed_graph = LiveTDGraph::new(td_graph.clone(), 8 * hour, live);
+
+    for (live_graph, departures) in vec![
+        (LiveTDGraph::from(td_graph.clone()), vec![0, 8 * hour]),
+        (jammed_graph, vec![7 * hour, 7 * hour + hour / 2]),
+    ] {
+        let mut dijkstra = DijkServer::<LiveTDDijkstraOps, _, _>::new(live_graph.line_graph(turn_costs));
+        let mut server = TurnExpandedServer::with_cch(&cch, live_graph, turn_costs);
+
+        for &departure in &departures {
+            for from in (0..td_graph.num_arcs() as EdgeId).step_by(19) {
+                for to in 0..td_graph.num_arcs() as EdgeId {
+                    let query = TDQuery { from, to, departure };
+                    let expected = TDQueryServer::query(&mut dijkstra, query).map(|res| res.distance() - departure);
+                    let mut result = TDQueryServer::query(&mut server, query);
+                    assert_eq!(result.as_ref().map(|res| res.distance()), expected, "{:?}", query);
+
+                    if let Some(result) = result.as_mut() {
+                        let path = result.path();
+                        assert_eq!((path.first(), path.last()), (Some(&from), Some(&to)));
+                        assert!(path.windows(2).all(|turn| turns.turn_cost(turn[0], turn[1]).is_some()));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rand::prelude::*;
+use rust_road_router::{
+    algo::{dijkstra::query::dijkstra::Server as DijkServer, dijkstra::*, *},
+    datastr::graph::{time_dependent::period, *},
+    experiments::queries::*,
+    util::great_circle_distance,
+};
+
+// A line of 100 nodes, 100m and 10s apart, with arcs in both directions
+fn line() -> (OwnedGraph, Vec<f32>, Vec<f32>) {
+    let n = 100;
+    let mut first_out = vec![0];
+    let mut head = Vec::new();
+    for node in 0..n {
+        if node > 0 {
+            head.push(node as NodeId - 1);
+        }
+        if node + 1 < n {
+            head.push(node as NodeId + 1);
+        }
+        first_out.push(head.len() as EdgeId);
+    }
+    let weight = vec![10_000; head.len()];
+    let lat = (0..n).map(|node| 49.0 + node as f32 * 0.0009).collect();
+    let lng = vec![8.4; n];
+    (OwnedGraph::new(first_out, head, weight), lat, lng)
+}
+
+#[test]
+fn generate_store_and_load() {
+    let (graph, lat, lng) = line();
+    let dir = TempDir::new("queries");
+
+    let queries = uniform(graph.num_nodes(), 50, &mut StdRng::from_seed(Default::default()));
+    let same = uniform(graph.num_nodes(), 50, &mut StdRng::from_seed(Default::default()));
+    assert_eq!(format!("{:?}", queries), format!("{:?}", same));
+    store_queries(&dir, &queries).unwrap();
+    assert_eq!(format!("{:?}", load_queries(&dir).unwrap().collect::<Vec<_>>()), format!("{:?}", queries));
+
+    let mut rng = StdRng::from_seed(Default::default());
+    let td_queries = with_departures(queries, &mut rng);
+    assert!(td_queries.iter().all(|query| query.departure < period()));
+    store_td_queries(&dir, &td_queries).unwrap();
+    assert_eq!(format!("{:?}", load_td_queries(&dir).unwrap().collect::<Vec<_>>()), format!("{:?}", td_queries));
+
+    let rank_queries = dijkstra_rank(graph.clone(), 5, &mut rng);
+    let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(graph);
+    for &(query, rank) in &rank_queries {
+        // on a line, the 2^i-th settled node is at most 2^i hops away
+        let dist = QueryServer::query(&mut dijkstra, query).unwrap().distance();
+        assert!(dist <= 10_000 << rank);
+    }
+    store_rank_queries(&dir, &rank_queries).unwrap();
+    assert_eq!(load_ranks(&dir).unwrap(), rank_queries.iter().map(|&(_, rank)| rank).collect::<Vec<_>>());
+    assert_eq!(load_queries(&dir).unwrap().count(), rank_queries.len());
+
+    // roughly 100m between nodes, so the target is at most 5 nodes away
+    for query in local(&lat, &lng, 550.0, 100, &mut rng) {
+        assert!((query.from as i64 - query.to as i64).abs() <= 5, "{:?}", query);
+    }
+}
+
+#[test]
+fn local_queries_match_full_scan() {
+    let (_, lat, lng) = grid();
+    let radius = 250.0;
+    let queries = local(&lat, &lng, radius, 200, &mut StdRng::from_seed(Default::default()));
+
+    // the same random draws, but with the candidates from a scan over all nodes
+    let mut rng = StdRng::from_seed(Default::default());
+    for query in queries {
+        let from = rng.gen_range(0, lat.len() as NodeId);
+        let candidates: Vec<NodeId> = (0..lat.len() as NodeId)
+            .filter(|&node| great_circle_distance((lat[from as usize], lng[from as usize]), (lat[node as usize], lng[node as usize])) <= radius)
+            .collect();
+        let to = candidates[rng.gen_range(0, candidates.len())];
+        assert_eq!((query.from, query.to), (from, to));
+    }
+}
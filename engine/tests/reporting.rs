@@ -0,0 +1,55 @@
+#[macro_use]
+extern crate rust_road_router;
+
+use rayon::prelude::*;
+use rust_road_router::report::{table::Table, *};
+
+#[test]
+fn parallel_reports_merge_in_order() {
+    let _reporter = enable_reporting();
+
+    let (results, captured) = reporting_handle().capture(|| {
+        let handle = reporting_handle();
+        let (results, captured): (Vec<u32>, Vec<CapturedReport>) = (0..200u32)
+            .into_par_iter()
+            .map(|i| {
+                handle.capture(|| {
+                    report!("task", i);
+                    // nested parallelism, rayon may execute other tasks on this thread meanwhile
+                    let (a, b) = rayon::join(|| i * 2, || i * 3);
+                    report!("sum", a + b);
+                    a + b
+                })
+            })
+            .unzip();
+        report_captured_collection("tasks".to_string(), captured);
+        report!("num_tasks", results.len());
+        results
+    });
+
+    assert_eq!(results, (0..200u32).map(|i| i * 5).collect::<Vec<_>>());
+    let report = captured.into_value().unwrap();
+    assert_eq!(report["num_tasks"], json!(200));
+    let tasks = report["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 200);
+    for (i, task) in tasks.iter().enumerate() {
+        assert_eq!(task, &json!({ "task": i, "sum": i * 5 }));
+    }
+
+    let table = Table::from_report(&report);
+    assert_eq!(table.columns, vec!["num_tasks", "tasks.sum", "tasks.task"]);
+    assert_eq!(table.rows[10], vec![json!(200), json!(50), json!(10)]);
+
+    // merging into the actual report also works
+    report_captured_collection("captured".to_string(), vec![CapturedReport::default()]);
+}
+
+#[test]
+fn capture_without_reporting() {
+    let (res, captured) = reporting_handle().capture(|| {
+        report!("ignored", 1);
+        42
+    });
+    assert_eq!(res, 42);
+    assert_eq!(captured.into_value(), None);
+}
@@ -0,0 +1,68 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rust_road_router::{
+    algo::{customizable_contraction_hierarchy::contract, time_dependent_sampling::*, *},
+    datastr::{
+        graph::{time_dependent::*, Weight},
+        node_order::NodeOrder,
+    },
+    io::*,
+};
+
+const MINUTE: Timestamp = 60 * 1000;
+const HOUR: Timestamp = 60 * MINUTE;
+
+#[test]
+fn clustered_windows_and_persistence() {
+    // 0 -> 1 -> 3 is fast except for a rush hour around 8:00 on 0 -> 1,
+    // 0 -> 2 -> 3 always takes 21 minutes.
+    let first_out = vec![0, 2, 3, 4, 4];
+    let head = vec![1, 2, 3, 3];
+    let first_ipp_of_arc = vec![0, 4, 5, 6, 7];
+    let ipp_departure_time = vec![0, 7 * HOUR, 8 * HOUR, 9 * HOUR, 0, 0, 0];
+    let ipp_travel_time = vec![10 * MINUTE, 10 * MINUTE, 60 * MINUTE, 10 * MINUTE, 20 * MINUTE, MINUTE, MINUTE];
+    let graph = || {
+        TDGraph::new(
+            first_out.clone(),
+            head.clone(),
+            first_ipp_of_arc.clone(),
+            ipp_departure_time.clone(),
+            ipp_travel_time.clone(),
+        )
+    };
+
+    let cch = contract(&graph(), NodeOrder::from_node_order(vec![0, 1, 2, 3]));
+
+    let windows = clustered_windows(&graph(), 3);
+    assert_eq!(windows.len(), 3);
+    let rush_hour = windows.iter().find(|window| window.contains(8 * HOUR)).unwrap();
+    assert!(!rush_hour.contains(3 * HOUR));
+
+    let samples = Samples::new(&graph(), &cch, &windows, Aggregation::Percentile(90));
+    let dir = TempDir::new("tds");
+    samples.deconstruct_to(&dir.0).unwrap();
+
+    let reconstructed = SamplesReconstructor { cch: &cch }.reconstruct_from(&dir.0).unwrap();
+    // samples for another CCH are rejected
+    let weights = Vec::<Weight>::load_from(dir.join("tds_upward_weight_0")).unwrap();
+    weights[1..].to_vec().write_to(&dir.join("tds_upward_weight_0")).unwrap();
+    assert!(SamplesReconstructor { cch: &cch }.reconstruct_from(&dir.0).is_err());
+    assert_eq!(
+        reconstructed.windows().iter().map(|w| (w.start(), w.end())).collect::<Vec<_>>(),
+        windows.iter().map(|w| (w.start(), w.end())).collect::<Vec<_>>()
+    );
+
+    for samples in vec![samples, reconstructed] {
+        let mut server = Server::with_samples(graph(), samples);
+        let mut query = |departure| {
+            server
+                .query(TDQuery { from: 0, to: 3, departure })
+                .map(|mut result| (result.distance(), result.path()))
+        };
+        assert_eq!(query(3 * HOUR), Some((11 * MINUTE, vec![0, 1, 3])));
+        assert_eq!(query(8 * HOUR), Some((21 * MINUTE, vec![0, 2, 3])));
+    }
+}
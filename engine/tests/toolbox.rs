@@ -0,0 +1,200 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rust_road_router::{cli::toolbox::run, datastr::graph::*, io::*};
+use std::path::Path;
+
+// 0 <-> 1 <-> 2 -> 0, plus 3 -> 0 and an isolated node 4
+fn write_graph(dir: &Path) {
+    vec![0u32, 1, 3, 4, 5, 5].write_to(&dir.join("first_out")).unwrap();
+    vec![1u32, 0, 2, 1, 0].write_to(&dir.join("head")).unwrap();
+    vec![10u32, 11, 12, 21, 30].write_to(&dir.join("travel_time")).unwrap();
+    vec![1u8, 2, 3, 4, 5].write_to(&dir.join("arc_category")).unwrap();
+    vec![49.0f32, 49.1, 49.2, 49.3, 49.4].write_to(&dir.join("latitude")).unwrap();
+    vec![8.0f32, 8.1, 8.2, 8.3, 8.4].write_to(&dir.join("longitude")).unwrap();
+    vec![4u32, 3, 2, 1, 0].write_to(&dir.join("cch_perm")).unwrap();
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn chained_pipeline() {
+    let input = TempDir::new("toolbox_input");
+    let output = TempDir::new("toolbox_output");
+    write_graph(&input);
+    vec![2u32, 1, 0].write_to(&input.join("reverse")).unwrap();
+
+    run(args(&[
+        "read",
+        input.to_str().unwrap(),
+        "largest-scc",
+        "reorder",
+        "--order",
+        input.join("reverse").to_str().unwrap(),
+        "write",
+        output.to_str().unwrap(),
+        "--tail",
+    ]))
+    .unwrap();
+
+    let graph = OwnedGraph::new(
+        Vec::load_from(output.join("first_out")).unwrap(),
+        Vec::load_from(output.join("head")).unwrap(),
+        Vec::load_from(output.join("travel_time")).unwrap(),
+    );
+    assert_eq!(graph.num_nodes(), 3);
+    assert_eq!(graph.num_arcs(), 4);
+    // old 2 -> 1 is new 0 -> 1
+    assert_eq!(graph.edge_index(0, 1).map(|edge| graph.link(edge).weight), Some(21));
+    // old 1 -> 0 is new 1 -> 2
+    assert_eq!(graph.edge_index(1, 2).map(|edge| graph.link(edge).weight), Some(11));
+    assert_eq!(Vec::<u8>::load_from(output.join("arc_category")).unwrap(), vec![4, 2, 3, 1]);
+    assert_eq!(Vec::<f32>::load_from(output.join("latitude")).unwrap(), vec![49.2, 49.1, 49.0]);
+    assert_eq!(Vec::<NodeId>::load_from(output.join("tail")).unwrap(), vec![0, 1, 1, 2]);
+    // node orders are not carried over
+    assert!(!output.join("cch_perm").exists());
+
+    run(args(&[
+        "read",
+        input.to_str().unwrap(),
+        "subgraph",
+        "--bbox",
+        "49.05,8.05,49.5,8.5",
+        "write",
+        output.to_str().unwrap(),
+    ]))
+    .unwrap();
+    assert_eq!(Vec::<EdgeId>::load_from(output.join("first_out")).unwrap(), vec![0, 1, 2, 2, 2]);
+    assert_eq!(Vec::<NodeId>::load_from(output.join("head")).unwrap(), vec![1, 0]);
+}
+
+#[test]
+fn computed_orders() {
+    let input = TempDir::new("toolbox_orders_input");
+    let output = TempDir::new("toolbox_orders_output");
+    write_graph(&input);
+    let graph = OwnedGraph::new(
+        Vec::load_from(input.join("first_out")).unwrap(),
+        Vec::load_from(input.join("head")).unwrap(),
+        Vec::load_from(input.join("travel_time")).unwrap(),
+    );
+    let latitude = Vec::<f32>::load_from(input.join("latitude")).unwrap();
+    let mapping = input.join("mapping");
+
+    let cch_perm = input.join("cch_perm");
+    for &method in &["dfs", "bfs", "hilbert", "separator"] {
+        let mut command_line = vec!["read", input.to_str().unwrap(), "reorder", "--by", method];
+        // only the separator order builds on a given nested dissection order
+        if method == "separator" {
+            command_line.extend_from_slice(&["--order", cch_perm.to_str().unwrap()]);
+        }
+        command_line.extend_from_slice(&["--mapping", mapping.to_str().unwrap(), "write", output.to_str().unwrap()]);
+        run(args(&command_line)).unwrap();
+
+        let previous_id = Vec::<NodeId>::load_from(&mapping).unwrap();
+        let reordered = OwnedGraph::new(
+            Vec::load_from(output.join("first_out")).unwrap(),
+            Vec::load_from(output.join("head")).unwrap(),
+            Vec::load_from(output.join("travel_time")).unwrap(),
+        );
+        assert_eq!(reordered.num_arcs(), graph.num_arcs(), "{}", method);
+        for node in 0..reordered.num_nodes() as NodeId {
+            for link in LinkIterable::<Link>::link_iter(&reordered, node) {
+                let edge = graph.edge_index(previous_id[node as usize], previous_id[link.node as usize]).unwrap();
+                assert_eq!(graph.link(edge).weight, link.weight, "{}", method);
+            }
+        }
+        let expected_latitude: Vec<f32> = previous_id.iter().map(|&node| latitude[node as usize]).collect();
+        assert_eq!(Vec::<f32>::load_from(output.join("latitude")).unwrap(), expected_latitude, "{}", method);
+    }
+}
+
+#[test]
+fn invalid_command_lines() {
+    let input = TempDir::new("toolbox_invalid");
+    write_graph(&input);
+    let input = input.to_str().unwrap();
+
+    assert!(run(args(&["largest-scc"])).is_err());
+    assert!(run(args(&["read"])).is_err());
+    assert!(run(args(&["read", input, "subgraph"])).is_err());
+    assert!(run(args(&["read", input, "subgraph", "--bbox", "1,2,3"])).is_err());
+    assert!(run(args(&["read", input, "largest-scc", "--foo"])).is_err());
+    assert!(run(args(&["read", input, "frobnicate"])).is_err());
+    assert!(run(args(&["read", input, "reorder", "--order", &format!("{}/travel_time", input)])).is_err());
+    assert!(run(args(&["read", input, "reorder", "--by", "random"])).is_err());
+    assert!(run(args(&["read", input, "reorder", "--by", "separator"])).is_err());
+    assert!(run(args(&["read", input, "reorder", "--by", "dfs", "--order", &format!("{}/cch_perm", input)])).is_err());
+    assert!(run(args(&["read", input, "reorder", "--by", "hilbert", "--ranks"])).is_err());
+    assert!(run(args(&["invert-order", &format!("{}/travel_time", input), "/dev/null"])).is_err());
+    assert!(run(args(&["read", input, "export-tbgr", "/dev/null"])).is_err());
+    assert!(run(args(&["read", input, "info", "--help"])).is_ok());
+
+    // cch_perm would still refer to the previous node ids
+    let first_out = std::fs::read(format!("{}/first_out", input)).unwrap();
+    assert!(run(args(&["read", input, "reorder", "--by", "dfs", "write", input])).is_err());
+    assert_eq!(std::fs::read(format!("{}/first_out", input)).unwrap(), first_out);
+    assert!(run(args(&["read", input, "write", input])).is_ok());
+}
+
+#[test]
+fn scaled_tpgr() {
+    let dir = TempDir::new("toolbox_scale_tpgr");
+    let (input, output) = (dir.join("input.tpgr"), dir.join("output.tpgr"));
+    std::fs::write(&input, "2 2 3 86400\n1 0 1 0 10\n0 1 2 0 20 43200 30\n").unwrap();
+    run(args(&["scale-tpgr", input.to_str().unwrap(), output.to_str().unwrap(), "--factor", "100"])).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "2 2 3 8640000\n1 0 1 0 1000\n0 1 2 0 2000 4320000 3000\n"
+    );
+
+    std::fs::write(&input, "2 2 86400\n1 0 1 0 10\n").unwrap();
+    assert!(run(args(&["scale-tpgr", input.to_str().unwrap(), output.to_str().unwrap()])).is_err());
+}
+
+#[test]
+fn import_roundtrips() {
+    let input = TempDir::new("toolbox_import_input");
+    let output = TempDir::new("toolbox_import_output");
+    write_graph(&input);
+
+    let prefix = input.join("exported");
+    run(args(&["read", input.to_str().unwrap(), "export-dimacs", prefix.to_str().unwrap()])).unwrap();
+    run(args(&["read-dimacs", prefix.to_str().unwrap(), "write", output.to_str().unwrap()])).unwrap();
+    for file in &["first_out", "head", "travel_time", "latitude", "longitude"] {
+        assert_eq!(std::fs::read(input.join(file)).unwrap(), std::fs::read(output.join(file)).unwrap(), "{}", file);
+    }
+
+    let tpgr = input.join("graph.tpgr");
+    std::fs::write(&tpgr, "2 2 3 864000\n1 0 1 0 10\n0 1 2 0 20 432000 30\n").unwrap();
+    let tbgr = input.join("graph.tbgr");
+    let td_output = TempDir::new("toolbox_import_td_output");
+    run(args(&["read-tpgr", tpgr.to_str().unwrap(), "export-tbgr", tbgr.to_str().unwrap()])).unwrap();
+    run(args(&[
+        "read-tbgr",
+        tbgr.to_str().unwrap(),
+        "lower-bound",
+        "write",
+        td_output.to_str().unwrap(),
+    ]))
+    .unwrap();
+    assert_eq!(Vec::<EdgeId>::load_from(td_output.join("first_out")).unwrap(), vec![0, 1, 2]);
+    assert_eq!(Vec::<NodeId>::load_from(td_output.join("head")).unwrap(), vec![1, 0]);
+    assert_eq!(Vec::<u32>::load_from(td_output.join("first_ipp_of_arc")).unwrap(), vec![0, 2, 3]);
+    assert_eq!(Vec::<Weight>::load_from(td_output.join("ipp_travel_time")).unwrap(), vec![2000, 3000, 1000]);
+    assert_eq!(Vec::<Weight>::load_from(td_output.join("lower_bound")).unwrap(), vec![2000, 1000]);
+    assert_eq!(Vec::<u32>::load_from(td_output.join("period")).unwrap(), vec![86_400_000]);
+    assert!(!td_output.join("latitude").exists());
+
+    // overwriting a graph directory must not leave attributes of the previous graph behind
+    run(args(&["read", td_output.to_str().unwrap(), "write", output.to_str().unwrap()])).unwrap();
+    assert!(!output.join("latitude").exists());
+    assert!(!output.join("travel_time").exists());
+    run(args(&["read", output.to_str().unwrap()])).unwrap();
+
+    assert!(run(args(&["read-dimacs", prefix.to_str().unwrap(), "--weight", "latitude"])).is_err());
+}
@@ -0,0 +1,155 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rand::prelude::*;
+use rust_road_router::{
+    algo::{
+        dijkstra::{query::dijkstra::Server as DijkServer, *},
+        topocore::{query::Server as TopocoreServer, *},
+        *,
+    },
+    datastr::graph::*,
+    io::*,
+    util::True,
+};
+
+// A grid with random weights where some arcs are missing, with a few chains and trees hanging off
+fn random_graph(rng: &mut StdRng) -> OwnedGraph {
+    let size = 8;
+    let mut arcs = Vec::new();
+    for x in 0..size {
+        for y in 0..size {
+            let node = x * size + y;
+            if x + 1 < size {
+                arcs.push((node, node + size));
+                arcs.push((node + size, node));
+            }
+            if y + 1 < size {
+                arcs.push((node, node + 1));
+                arcs.push((node + 1, node));
+            }
+        }
+    }
+    arcs.retain(|_| rng.gen_bool(0.9));
+
+    let mut n = size * size;
+    for _ in 0..10 {
+        let anchor = rng.gen_range(0, n);
+        for _ in 0..rng.gen_range(1, 4) {
+            arcs.push((anchor, n));
+            if rng.gen_bool(0.8) {
+                arcs.push((n, anchor));
+            }
+            n += 1;
+        }
+    }
+
+    let mut first_out = vec![0];
+    let mut head = Vec::new();
+    let mut weight = Vec::new();
+    for node in 0..n {
+        for &(_, to) in arcs.iter().filter(|&&(from, _)| from == node) {
+            head.push(to as NodeId);
+            weight.push(rng.gen_range(1, 100));
+        }
+        first_out.push(head.len() as EdgeId);
+    }
+    OwnedGraph::new(first_out, head, weight)
+}
+
+#[test]
+fn same_distances_and_paths_as_dijkstra() {
+    let mut rng = StdRng::from_seed(Default::default());
+    for _ in 0..10 {
+        let graph = random_graph(&mut rng);
+        let topocore = preprocess::<_, True, True, True, True>(&graph);
+        assert!(topocore.core_size < graph.num_nodes());
+        assert!((0..graph.num_nodes() as NodeId).all(|node| topocore.in_core(node) == (topocore.contraction_kind(node) == ContractionKind::Core)));
+
+        let mut server = TopocoreServer::new(topocore);
+        let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(graph.clone());
+
+        for _ in 0..100 {
+            let query = Query {
+                from: rng.gen_range(0, graph.num_nodes() as NodeId),
+                to: rng.gen_range(0, graph.num_nodes() as NodeId),
+            };
+            let expected = QueryServer::query(&mut dijkstra, query).map(|res| res.distance());
+            let mut result = server.query(query);
+            assert_eq!(result.as_ref().map(|res| res.distance()), expected, "{:?}", query);
+
+            if let Some(result) = result.as_mut() {
+                let path = result.path();
+                assert_eq!(path.first(), Some(&query.from));
+                assert_eq!(path.last(), Some(&query.to));
+                let length: Weight = path
+                    .windows(2)
+                    .map(|arc| {
+                        LinkIterable::<Link>::link_iter(&graph, arc[0])
+                            .filter(|link| link.node == arc[1])
+                            .map(|link| link.weight)
+                            .min()
+                            .unwrap()
+                    })
+                    .sum();
+                assert_eq!(Some(length), expected, "{:?}", query);
+            }
+        }
+    }
+}
+
+#[test]
+fn deconstruct_and_reconstruct() {
+    let mut rng = StdRng::from_seed(Default::default());
+    let graph = random_graph(&mut rng);
+    let topocore = preprocess::<_, True, True, True, True>(&graph);
+    assert!((0..topocore.forward.num_arcs() as EdgeId).any(|edge| !topocore.forward_shortcut_path(edge).is_empty()));
+
+    let dir = TempDir::new("topocore");
+    topocore.deconstruct_to(&dir.to_str().unwrap()).unwrap();
+    let reconstructed = Topocore::reconstruct_from(&dir.to_str().unwrap()).unwrap();
+
+    assert_eq!(reconstructed.core_size, topocore.core_size);
+    assert_eq!(reconstructed.order.order(), topocore.order.order());
+    assert_eq!(reconstructed.forward.head(), topocore.forward.head());
+    assert_eq!(reconstructed.backward.weight(), topocore.backward.weight());
+    for node in 0..graph.num_nodes() as NodeId {
+        assert_eq!(reconstructed.contraction_kind(node), topocore.contraction_kind(node));
+    }
+    for edge in 0..topocore.forward.num_arcs() as EdgeId {
+        assert_eq!(reconstructed.forward_shortcut_path(edge), topocore.forward_shortcut_path(edge));
+    }
+    for edge in 0..topocore.backward.num_arcs() as EdgeId {
+        assert_eq!(reconstructed.backward_shortcut_path(edge), topocore.backward_shortcut_path(edge));
+    }
+
+    let mut server = TopocoreServer::new(topocore);
+    let mut reconstructed_server = TopocoreServer::new(reconstructed);
+    for _ in 0..100 {
+        let query = Query {
+            from: rng.gen_range(0, graph.num_nodes() as NodeId),
+            to: rng.gen_range(0, graph.num_nodes() as NodeId),
+        };
+        assert_eq!(
+            server.query(query).map(|res| res.distance()),
+            reconstructed_server.query(query).map(|res| res.distance())
+        );
+    }
+
+    // one node too few or too many
+    let first_out = Vec::<EdgeId>::load_from(dir.join("topocore_forward_first_out")).unwrap();
+    let mut truncated = first_out.clone();
+    truncated.remove(1);
+    truncated.write_to(&dir.join("topocore_forward_first_out")).unwrap();
+    assert!(Topocore::reconstruct_from(&dir.to_str().unwrap()).is_err());
+    let mut extended = first_out.clone();
+    extended.push(*first_out.last().unwrap());
+    extended.write_to(&dir.join("topocore_forward_first_out")).unwrap();
+    assert!(Topocore::reconstruct_from(&dir.to_str().unwrap()).is_err());
+    first_out.write_to(&dir.join("topocore_forward_first_out")).unwrap();
+
+    vec![graph.num_nodes() as u32 + 1].write_to(&dir.join("topocore_core_size")).unwrap();
+    assert!(Topocore::reconstruct_from(&dir.to_str().unwrap()).is_err());
+}
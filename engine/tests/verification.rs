@@ -0,0 +1,173 @@
+extern crate rust_road_router;
+
+mod common;
+use common::*;
+
+use rand::prelude::*;
+use rust_road_router::{
+    algo::{
+        catchup::{customize as catchup_customize, Server as CATCHUpServer},
+        ch_potentials::{query::Server as TopoServer, *},
+        customizable_contraction_hierarchy::{query::Server as CCHServer, *},
+        dijkstra::{
+            query::{
+                bidirectional_dijkstra::Server as BiDijkServer, dijkstra::Server as DijkServer, floating_td_dijkstra::Server as FlTDDijkServer,
+                td_dijkstra::TDDijkstraOps,
+            },
+            *,
+        },
+        *,
+    },
+    datastr::{
+        graph::{floating_time_dependent, time_dependent::*, *},
+        node_order::NodeOrder,
+    },
+    experiments::{queries::*, verification::*},
+    io::*,
+};
+
+// every arc is twice as slow from 7:00 to 9:00
+fn td_profiles(graph: &OwnedGraph) -> (Vec<u32>, Vec<u32>, Vec<u32>) {
+    let hour = 60 * 60 * 1000;
+    let mut first_ipp_of_arc = vec![0];
+    let mut ipp_departure_time = Vec::new();
+    let mut ipp_travel_time = Vec::new();
+    for &weight in graph.weight() {
+        ipp_departure_time.extend_from_slice(&[0, 6 * hour, 7 * hour, 9 * hour, 10 * hour]);
+        ipp_travel_time.extend_from_slice(&[weight, weight, 2 * weight, 2 * weight, weight]);
+        first_ipp_of_arc.push(ipp_departure_time.len() as u32);
+    }
+    (first_ipp_of_arc, ipp_departure_time, ipp_travel_time)
+}
+
+#[test]
+fn static_servers() {
+    let (graph, _, _) = grid();
+    let queries = uniform(graph.num_nodes(), 300, &mut StdRng::from_seed(Default::default()));
+
+    let mut dijkstra = DijkServer::<DefaultOps, _, _>::new(graph.clone());
+    let mut bidir_dijkstra = BiDijkServer::new(graph.clone());
+    let verification = verify(&graph, &mut dijkstra, &mut bidir_dijkstra, queries.iter().cloned());
+    verification.assert_ok();
+    assert_eq!(verification.num_queries, 300);
+
+    let cch = contract(&graph, NodeOrder::identity(graph.num_nodes()));
+    let mut cch_server = CCHServer::new(customize(&cch, &graph));
+    let mut verification = Verification::new();
+    for &query in &queries {
+        let reference = QueryServer::query(&mut dijkstra, query).map(|mut res| (res.distance(), res.path()));
+        let candidate = cch_server.query(query).map(|mut res| (res.distance(), res.path()));
+        verification.check(&graph, query, reference, candidate);
+    }
+    verification.assert_ok();
+
+    // a server on a different metric should be caught
+    let slower = OwnedGraph::new(
+        graph.first_out().to_vec(),
+        graph.head().to_vec(),
+        graph.weight().iter().map(|w| w + 1).collect(),
+    );
+    let mut wrong = DijkServer::<DefaultOps, _, _>::new(slower);
+    let verification = verify(&graph, &mut dijkstra, &mut wrong, queries.iter().cloned());
+    assert!(!verification.is_ok());
+    let failure = verification.failures.iter().find(|failure| failure.query.from != failure.query.to).unwrap();
+    match &failure.mismatch {
+        Mismatch::Distance { reference, candidate } => assert!(reference < candidate),
+        Mismatch::PathLength {
+            server, distance, path_length, ..
+        } => {
+            assert_eq!(*server, "candidate");
+            assert!(path_length < distance)
+        }
+        mismatch => panic!("unexpected {:?}", mismatch),
+    }
+    assert!(failure.to_string().contains(&format!("{:?}", failure.query)));
+}
+
+#[test]
+fn td_servers() {
+    let (graph, _, _) = grid();
+    let (first_ipp_of_arc, ipp_departure_time, ipp_travel_time) = td_profiles(&graph);
+    let td_graph = TDGraph::new(
+        graph.first_out().to_vec(),
+        graph.head().to_vec(),
+        first_ipp_of_arc,
+        ipp_departure_time,
+        ipp_travel_time,
+    );
+
+    let mut rng = StdRng::from_seed(Default::default());
+    let queries = with_departures(uniform(graph.num_nodes(), 300, &mut rng), &mut rng);
+
+    let mut dijkstra = DijkServer::<TDDijkstraOps, _, _>::new(td_graph.clone());
+    let mut topocore = TopoServer::new(&td_graph, ZeroPotential(), TDDijkstraOps::default());
+    verify_td(
+        &td_graph,
+        (&mut dijkstra, TDDistance::Arrival),
+        (&mut topocore, TDDistance::TravelTime),
+        queries.iter().cloned(),
+    )
+    .assert_ok();
+
+    // interpreting arrival times as travel times is wrong
+    let verification = verify_td(
+        &td_graph,
+        (&mut dijkstra, TDDistance::TravelTime),
+        (&mut topocore, TDDistance::TravelTime),
+        queries.iter().cloned(),
+    );
+    assert!(verification.failures.iter().all(|failure| failure.query.departure > 0));
+    assert!(!verification.is_ok());
+}
+
+#[test]
+fn floating_td_servers() {
+    let (graph, _, _) = grid();
+    let (first_ipp_of_arc, ipp_departure_time, ipp_travel_time) = td_profiles(&graph);
+    let td_graph = floating_time_dependent::TDGraph::new(
+        graph.first_out().to_vec(),
+        graph.head().to_vec(),
+        first_ipp_of_arc,
+        ipp_departure_time,
+        ipp_travel_time,
+    );
+
+    let mut rng = StdRng::from_seed(Default::default());
+    let queries: Vec<_> = with_departures(uniform(graph.num_nodes(), 100, &mut rng), &mut rng)
+        .into_iter()
+        .map(|query| TDQuery {
+            from: query.from,
+            to: query.to,
+            departure: floating_time_dependent::Timestamp::new(f64::from(query.departure) / 1000.0),
+        })
+        .collect();
+
+    let cch = contract(&graph, NodeOrder::identity(graph.num_nodes()));
+    let customized = catchup_customize(&cch, &td_graph);
+    let mut catchup = CATCHUpServer::new(&cch, &customized);
+    let mut dijkstra = FlTDDijkServer::new(td_graph.clone());
+
+    // CATCHUp borrows the CCH, so the queries have to be run here
+    let mut verification = Verification::new();
+    for &query in &queries {
+        let reference = TDQueryServer::query(&mut dijkstra, query).map(|mut res| (res.distance(), res.path()));
+        let candidate = catchup.query(query).map(|mut res| (res.distance(), res.path()));
+        verification.check_floating_td(&td_graph, query, reference, candidate);
+    }
+    verification.assert_ok();
+    assert_eq!(verification.num_queries, 100);
+
+    // an empty path is invalid, not a crash
+    let mut verification = Verification::new();
+    let query = queries[0];
+    let reference = TDQueryServer::query(&mut dijkstra, query).map(|mut res| (res.distance(), res.path()));
+    let candidate = reference.as_ref().map(|&(distance, _)| (distance, Vec::new()));
+    verification.check_floating_td(&td_graph, query, reference, candidate);
+    match &verification.failures[..] {
+        [Failure {
+            mismatch: Mismatch::InvalidPath { server: "candidate", .. },
+            ..
+        }] => (),
+        failures => panic!("expected an invalid candidate path, got {:?}", failures),
+    }
+}
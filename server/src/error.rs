@@ -0,0 +1,75 @@
+// Errors of query requests and how they are reported to the client.
+
+use std::{error::Error, fmt};
+
+use rocket::{
+    http::Status,
+    request::Request,
+    response::{self, Responder, Response},
+};
+use rocket_contrib::json::Json;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryError {
+    /// The here link id does not exist in the map (or not in the requested direction)
+    UnknownLink(u64),
+    /// Invalid coordinates or link fraction, or coordinates too far away from the road network
+    OutOfBounds,
+    /// Source and target are not connected
+    NoRoute,
+    /// The graph directory contains no predicted travel time profiles
+    NoProfiles,
+    /// The routing engine did not accept or answer the request in time
+    EngineBusy,
+}
+
+impl QueryError {
+    fn status(self) -> Status {
+        match self {
+            QueryError::UnknownLink(_) | QueryError::OutOfBounds => Status::BadRequest,
+            QueryError::NoRoute => Status::NotFound,
+            QueryError::NoProfiles => Status::NotImplemented,
+            QueryError::EngineBusy => Status::ServiceUnavailable,
+        }
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            QueryError::UnknownLink(_) => "unknown_link",
+            QueryError::OutOfBounds => "out_of_bounds",
+            QueryError::NoRoute => "no_route",
+            QueryError::NoProfiles => "no_profiles",
+            QueryError::EngineBusy => "engine_busy",
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::UnknownLink(link_id) => write!(f, "link {} does not exist", link_id),
+            QueryError::OutOfBounds => write!(f, "query position is invalid or not close to any road"),
+            QueryError::NoRoute => write!(f, "target is not reachable from source"),
+            QueryError::NoProfiles => write!(f, "departure time queries are not supported for this graph"),
+            QueryError::EngineBusy => write!(f, "routing engine is busy, try again later"),
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: &'static str,
+    message: String,
+}
+
+impl<'r> Responder<'r> for QueryError {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let body = ErrorResponse {
+            error: self.code(),
+            message: self.to_string(),
+        };
+        Response::build_from(Json(body).respond_to(request)?).status(self.status()).ok()
+    }
+}
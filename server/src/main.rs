@@ -15,6 +15,7 @@ use std::{
     sync::mpsc::{self, Sender},
     sync::{Arc, Mutex},
     thread,
+    time::{Duration, Instant},
 };
 
 use rocket::{request::Form, response::NamedFile, State};
@@ -24,20 +25,26 @@ use kdtree::kdtree::{Kdtree, KdtreePointTrait};
 
 use rust_road_router::{
     algo::{
+        ch_potentials::live_td::LiveTDServer,
         customizable_contraction_hierarchy::{contract, customize as cch_customize, query::Server, CCHReordering},
         *,
     },
     cli::CliErr,
     datastr::{
-        graph::{link_id_to_tail_mapper::*, *},
-        node_order::NodeOrder,
+        graph::{link_id_to_tail_mapper::*, time_dependent::*, *},
         rank_select_map::*,
     },
-    import::here::link_id_mapper::*,
+    import::{here::link_id_mapper::*, live_traffic::*},
     io::*,
     report::report_time,
+    util::great_circle_distance,
 };
 
+mod error;
+mod metrics;
+use error::QueryError;
+use metrics::{Endpoint, Metrics};
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 struct NodeCoord {
     coords: [f64; 2],
@@ -65,6 +72,15 @@ struct GeoResponse {
     path: Vec<(f32, f32)>,
 }
 
+#[derive(Debug, FromForm, Copy, Clone)]
+struct TDGeoQuery {
+    from_lat: f32,
+    from_lng: f32,
+    to_lat: f32,
+    to_lng: f32,
+    departure: Option<Timestamp>,
+}
+
 #[derive(Debug, FromForm, Copy, Clone)]
 struct HereQuery {
     from_link_id: u64,
@@ -83,11 +99,22 @@ struct HereResponse {
 
 #[derive(Debug)]
 enum Request {
-    Geo((GeoQuery, Sender<Option<GeoResponse>>)),
-    Here((HereQuery, Sender<Option<HereResponse>>)),
+    Geo((GeoQuery, Sender<Result<GeoResponse, QueryError>>)),
+    Here((HereQuery, Sender<Result<HereResponse, QueryError>>)),
+    TD((TDGeoQuery, Sender<Result<GeoResponse, QueryError>>)),
     Customize(Vec<(u64, bool, SerializedWeight)>),
+    Live(LiveTraffic),
 }
 
+// for how long live traffic overrides the predicted travel times
+const LIVE_HORIZON: Timestamp = 60 * 60 * 1000;
+// points further away from the closest node than this (in meters) will be rejected
+const MAX_SNAP_DISTANCE: f64 = 1000.0;
+// queries will be rejected when this many requests are already waiting for the engine
+const MAX_QUEUE_DEPTH: usize = 64;
+// how long a query request waits for the engine before giving up
+const QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[get("/")]
 fn index() -> Option<NamedFile> {
     NamedFile::open(Path::new("static/index.html")).ok()
@@ -98,36 +125,62 @@ fn files(file: PathBuf) -> Option<NamedFile> {
     NamedFile::open(Path::new("static/").join(file)).ok()
 }
 
+// Pass a request to the engine thread and wait for the answer.
+// Fails fast when too many requests are already waiting, so clients get an error instead of piling up.
+fn send_to_engine<T>(
+    state: &Mutex<Sender<Request>>,
+    metrics: &Metrics,
+    request: impl FnOnce(Sender<Result<T, QueryError>>) -> Request,
+) -> Result<T, QueryError> {
+    if metrics.queue_depth() >= MAX_QUEUE_DEPTH {
+        return Err(QueryError::EngineBusy);
+    }
+    let (tx_result, rx_result) = mpsc::channel();
+    metrics.enqueued();
+    if state.lock().unwrap().send(request(tx_result)).is_err() {
+        metrics.dequeued();
+        return Err(QueryError::EngineBusy);
+    }
+    rx_result.recv_timeout(QUERY_TIMEOUT).unwrap_or(Err(QueryError::EngineBusy))
+}
+
 #[get("/query?<query_params..>", format = "application/json")]
-fn query(query_params: Form<GeoQuery>, state: State<Mutex<Sender<Request>>>) -> Json<Option<GeoResponse>> {
+fn query(query_params: Form<GeoQuery>, state: State<Mutex<Sender<Request>>>, metrics: State<Arc<Metrics>>) -> Result<Json<GeoResponse>, QueryError> {
+    let start = Instant::now();
     let result = report_time("Total Query Request Time", || {
         println!("Received Query: {:?}", query_params);
-
-        let tx_query = state.lock().unwrap();
-        let (tx_result, rx_result) = mpsc::channel::<Option<GeoResponse>>();
-
-        tx_query.send(Request::Geo((*query_params, tx_result))).unwrap();
-        rx_result.recv().expect("routing engine crashed or hung up")
+        send_to_engine(&state, &metrics, |tx_result| Request::Geo((*query_params, tx_result)))
     });
+    metrics.observe_query(Endpoint::Query, start.elapsed());
 
     println!();
-    Json(result)
+    result.map(Json)
 }
 
 #[get("/here_query?<query_params..>", format = "application/json")]
-fn here_query(query_params: Form<HereQuery>, state: State<Mutex<Sender<Request>>>) -> Json<Option<HereResponse>> {
+fn here_query(query_params: Form<HereQuery>, state: State<Mutex<Sender<Request>>>, metrics: State<Arc<Metrics>>) -> Result<Json<HereResponse>, QueryError> {
+    let start = Instant::now();
     let result = report_time("Total Query Request Time", || {
         println!("Received Query: {:?}", query_params);
+        send_to_engine(&state, &metrics, |tx_result| Request::Here((*query_params, tx_result)))
+    });
+    metrics.observe_query(Endpoint::HereQuery, start.elapsed());
 
-        let tx_query = state.lock().unwrap();
-        let (tx_result, rx_result) = mpsc::channel::<Option<HereResponse>>();
+    println!();
+    result.map(Json)
+}
 
-        tx_query.send(Request::Here((*query_params, tx_result))).unwrap();
-        rx_result.recv().expect("routing engine crashed or hung up")
+#[get("/td_query?<query_params..>", format = "application/json")]
+fn td_query(query_params: Form<TDGeoQuery>, state: State<Mutex<Sender<Request>>>, metrics: State<Arc<Metrics>>) -> Result<Json<GeoResponse>, QueryError> {
+    let start = Instant::now();
+    let result = report_time("Total Query Request Time", || {
+        println!("Received Query: {:?}", query_params);
+        send_to_engine(&state, &metrics, |tx_result| Request::TD((*query_params, tx_result)))
     });
+    metrics.observe_query(Endpoint::TDQuery, start.elapsed());
 
     println!();
-    Json(result)
+    result.map(Json)
 }
 
 #[derive(Debug)]
@@ -164,27 +217,55 @@ impl<'de> Deserialize<'de> for SerializedWeight {
     }
 }
 
+#[get("/metrics")]
+fn metrics(metrics: State<Arc<Metrics>>) -> String {
+    metrics.render()
+}
+
 #[post("/customize", data = "<updates>")]
-fn customize(updates: Json<Vec<(u64, bool, SerializedWeight)>>, state: State<Mutex<Sender<Request>>>) {
+fn customize(updates: Json<Vec<(u64, bool, SerializedWeight)>>, state: State<Mutex<Sender<Request>>>, metrics: State<Arc<Metrics>>) -> Result<(), QueryError> {
     let tx_query = state.lock().unwrap();
-    tx_query.send(Request::Customize(updates.0)).expect("routing engine crashed or hung up");
+    metrics.enqueued();
+    tx_query.send(Request::Customize(updates.0)).map_err(|_| {
+        metrics.dequeued();
+        QueryError::EngineBusy
+    })
+}
+
+// `--live-max-age`, `--live-update-interval` and `--live-poll-interval`, all in seconds
+fn live_traffic_params(mut args: impl Iterator<Item = String>) -> Result<LiveTrafficParams, Box<dyn Error>> {
+    let mut params = LiveTrafficParams::default();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        let seconds: u64 = value.parse().map_err(|_| format!("invalid value {} for {}, expected seconds", value, flag))?;
+        match &flag[..] {
+            "--live-max-age" => params.max_age = seconds * 1000,
+            "--live-update-interval" => params.min_update_interval = Duration::from_secs(seconds),
+            "--live-poll-interval" => params.poll_interval = Duration::from_secs(seconds),
+            _ => return Err(format!("unknown flag {}", flag).into()),
+        }
+    }
+    Ok(params)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let (tx_query, rx_query) = mpsc::channel::<Request>();
+    let metrics = Arc::new(Metrics::default());
+    let engine_metrics = metrics.clone();
 
     let mut args = env::args();
     args.next();
 
     let arg = &args.next().ok_or(CliErr("No directory arg given"))?;
     let path = Path::new(arg);
+    let graph_dir = GraphDirectory::open(path)?;
 
-    let first_out = Vec::load_from(path.join("first_out"))?;
-    let head = Vec::load_from(path.join("head"))?;
-    let travel_time = Vec::load_from(path.join("travel_time"))?;
+    let first_out = graph_dir.first_out()?;
+    let head = graph_dir.head()?;
+    let travel_time = graph_dir.travel_time()?;
 
-    let lat = Vec::load_from(path.join("latitude"))?;
-    let lng = Vec::load_from(path.join("longitude"))?;
+    let lat = graph_dir.latitude()?;
+    let lng = graph_dir.longitude()?;
 
     let mut coords: Vec<NodeCoord> = lat
         .iter()
@@ -200,13 +281,33 @@ fn main() -> Result<(), Box<dyn Error>> {
     let link_id_mapping = BitVec::load_from(path.join("link_id_mapping"))?;
     let link_id_mapping = InvertableRankSelectMap::new(RankSelectMap::new(link_id_mapping));
     let here_rank_to_link_id = Vec::load_from(path.join("here_rank_to_link_id"))?;
-    let cch_order = NodeOrder::from_node_order(Vec::load_from(path.join("cch_perm"))?);
+    let cch_order = graph_dir.cch_order()?;
+
+    // optional live traffic feed, either a directory to poll or a socket path, followed by optional tuning flags
+    let live_feed = match args.next() {
+        Some(arg) => Some((
+            LiveFeed::from_path(PathBuf::from(arg)),
+            graph_dir.geo_distance()?.to_vec(),
+            live_traffic_params(args)?,
+        )),
+        None => None,
+    };
+    let tx_live = tx_query.clone();
+
+    // optional predicted travel time profiles for departure time queries
+    let calendar = load_period_config(path)?;
+    let td_graph = if graph_dir.has("first_ipp_of_arc") {
+        Some(graph_dir.td_graph()?)
+    } else {
+        None
+    };
 
     // all further preprocessing happening asynchronous
     thread::spawn(move || {
+        let metrics = engine_metrics;
         let id_mapper = LinkIdMapper::new(link_id_mapping, here_rank_to_link_id, head.len());
 
-        let graph = FirstOutGraph::new(&first_out[..], &head[..], travel_time.clone());
+        let graph = FirstOutGraph::new(&first_out[..], &head[..], travel_time.to_vec());
 
         let link_id_to_tail_mapper = LinkIdToTailMapper::new(&graph);
 
@@ -219,23 +320,66 @@ fn main() -> Result<(), Box<dyn Error>> {
         .reorder_for_seperator_based_customization();
         let cch = contract(&graph, cch_order);
 
+        let customization_start = Instant::now();
         let server = Arc::new(Mutex::new(Server::new(cch_customize(&cch, &graph))));
+        metrics.customized(customization_start.elapsed());
+        let mut td_server = td_graph.map(|td_graph| LiveTDServer::new(&cch, td_graph));
 
         let coords = |node: NodeId| -> (f32, f32) { (lat[node as usize], lng[node as usize]) };
 
-        let closest_node = |(p_lat, p_lng): (f32, f32)| -> NodeId {
-            tree.nearest_search(&NodeCoord {
-                coords: [f64::from(p_lat), f64::from(p_lng)],
-                node_id: 0,
-            })
-            .node_id
+        let closest_node = |(p_lat, p_lng): (f32, f32)| -> Result<NodeId, QueryError> {
+            if !(-90.0..=90.0).contains(&p_lat) || !(-180.0..=180.0).contains(&p_lng) {
+                return Err(QueryError::OutOfBounds);
+            }
+            let node = tree
+                .nearest_search(&NodeCoord {
+                    coords: [f64::from(p_lat), f64::from(p_lng)],
+                    node_id: 0,
+                })
+                .node_id;
+            if great_circle_distance((p_lat, p_lng), coords(node)) > MAX_SNAP_DISTANCE {
+                return Err(QueryError::OutOfBounds);
+            }
+            Ok(node)
         };
 
         // Customization should run asynchronous to queries but needs all the data,
         // Thus we create a scope here, so we can later spawn new threads
         // without the risk of data going out of scope.
         crossbeam_utils::thread::scope(|scope| {
+            if let Some((live_feed, geo_distance, params)) = live_feed {
+                let (tx_records, rx_records) = mpsc::channel();
+                live_feed.spawn(params.poll_interval, tx_records);
+
+                let id_mapper = &id_mapper;
+                let metrics = &metrics;
+
+                // the ingestion only collects the live values, the customization is triggered through the request channel
+                scope.spawn(move || {
+                    run_ingestion(
+                        rx_records,
+                        LiveTraffic::new(geo_distance, params.max_age),
+                        params,
+                        |here_link_id, is_from_ref| {
+                            let direction = if is_from_ref { LinkDirection::FromRef } else { LinkDirection::ToRef };
+                            id_mapper.here_to_local_link_id(here_link_id, direction)
+                        },
+                        |traffic| {
+                            println!("Live traffic update with {} live arcs", traffic.num_live());
+                            metrics.enqueued();
+                            tx_live.send(Request::Live(traffic.clone())).expect("routing engine crashed or hung up");
+                        },
+                    )
+                });
+            }
+
+            // weights posted to /customize replace the original travel times for good,
+            // live travel times take precedence over both while they are valid
+            let mut base_metric = travel_time.to_vec();
+            let mut latest_traffic: Option<LiveTraffic> = None;
+
             for query_params in rx_query {
+                metrics.dequeued();
                 match query_params {
                     Request::Geo((
                         GeoQuery {
@@ -248,16 +392,26 @@ fn main() -> Result<(), Box<dyn Error>> {
                     )) => {
                         let (from, to) = report_time("match nodes", || (closest_node((from_lat, from_lng)), closest_node((to_lat, to_lng))));
 
-                        let mut server = server.lock().unwrap();
-                        let result = report_time("cch query", || {
-                            server.query(Query { from, to }).as_mut().map(|result| {
-                                let distance = result.distance();
-                                let path = result.path().iter().map(|&node| coords(node)).collect();
-                                GeoResponse { distance, path }
-                            })
-                        });
-
-                        tx_result.send(result).unwrap();
+                        let result = match (from, to) {
+                            (Ok(from), Ok(to)) => {
+                                let mut server = server.lock().unwrap();
+                                report_time("cch query", || {
+                                    server.query(Query { from, to }).as_mut().map(|result| {
+                                        let distance = result.distance();
+                                        let path = result.path().iter().map(|&node| coords(node)).collect();
+                                        GeoResponse { distance, path }
+                                    })
+                                })
+                                .ok_or(QueryError::NoRoute)
+                            }
+                            (Err(err), _) | (_, Err(err)) => Err(err),
+                        };
+
+                        if let Err(err) = result {
+                            metrics.failed(Endpoint::Query, err);
+                        }
+                        // the request might have timed out in the meantime
+                        tx_result.send(result).ok();
                     }
                     Request::Here((
                         HereQuery {
@@ -271,66 +425,137 @@ fn main() -> Result<(), Box<dyn Error>> {
                         tx_result,
                     )) => {
                         let from_link_direction = if from_direction { LinkDirection::FromRef } else { LinkDirection::ToRef };
-                        let from_link_local_id = id_mapper.here_to_local_link_id(from_link_id, from_link_direction).expect("non existing link");
-                        let from_link = graph.link(from_link_local_id);
-                        let from = from_link.node;
-
+                        let from_link_local_id = id_mapper
+                            .here_to_local_link_id(from_link_id, from_link_direction)
+                            .ok_or(QueryError::UnknownLink(from_link_id));
                         let to_link_direction = if to_direction { LinkDirection::FromRef } else { LinkDirection::ToRef };
-                        let to_link_local_id = id_mapper.here_to_local_link_id(to_link_id, to_link_direction).expect("non existing link");
-                        let to_link = graph.link(to_link_local_id);
-                        let to = link_id_to_tail_mapper.link_id_to_tail(to_link_local_id);
-
-                        let mut server = server.lock().unwrap();
-                        let result = report_time("cch query", || {
-                            server.query(Query { from, to }).as_mut().map(|result| {
-                                let distance = result.distance()
-                                    + (from_link_fraction * from_link.weight as f32) as u32
-                                    + (to_link_fraction * to_link.weight as f32) as u32;
-
-                                let path = result.path();
-                                let path_iter = path.iter();
-                                let mut second_node_iter = path_iter.clone();
-                                second_node_iter.next();
-
-                                let path = once((from_link_id, from_direction))
-                                    .chain(
-                                        path_iter
-                                            .zip(second_node_iter)
-                                            .map(|(first_node, second_node)| graph.edge_index(*first_node, *second_node).unwrap())
-                                            .map(|link_id| {
-                                                let (id, dir) = id_mapper.local_to_here_link_id(link_id);
-                                                (id, dir == LinkDirection::FromRef)
-                                            }),
-                                    )
-                                    .chain(once((to_link_id, to_direction)))
-                                    .collect();
-
-                                HereResponse { distance, path }
-                            })
-                        });
+                        let to_link_local_id = id_mapper
+                            .here_to_local_link_id(to_link_id, to_link_direction)
+                            .ok_or(QueryError::UnknownLink(to_link_id));
+                        let valid_fractions = (0.0..=1.0).contains(&from_link_fraction) && (0.0..=1.0).contains(&to_link_fraction);
+
+                        let result = match (from_link_local_id, to_link_local_id) {
+                            _ if !valid_fractions => Err(QueryError::OutOfBounds),
+                            (Ok(from_link_local_id), Ok(to_link_local_id)) => {
+                                let from_link = graph.link(from_link_local_id);
+                                let from = from_link.node;
+                                let to_link = graph.link(to_link_local_id);
+                                let to = link_id_to_tail_mapper.link_id_to_tail(to_link_local_id);
+
+                                let mut server = server.lock().unwrap();
+                                report_time("cch query", || {
+                                    server.query(Query { from, to }).as_mut().map(|result| {
+                                        let distance = result.distance()
+                                            + (from_link_fraction * from_link.weight as f32) as u32
+                                            + (to_link_fraction * to_link.weight as f32) as u32;
+
+                                        let path = result.path();
+                                        let path_iter = path.iter();
+                                        let mut second_node_iter = path_iter.clone();
+                                        second_node_iter.next();
+
+                                        let path = once((from_link_id, from_direction))
+                                            .chain(
+                                                path_iter
+                                                    .zip(second_node_iter)
+                                                    .map(|(first_node, second_node)| graph.edge_index(*first_node, *second_node).unwrap())
+                                                    .map(|link_id| {
+                                                        let (id, dir) = id_mapper.local_to_here_link_id(link_id);
+                                                        (id, dir == LinkDirection::FromRef)
+                                                    }),
+                                            )
+                                            .chain(once((to_link_id, to_direction)))
+                                            .collect();
+
+                                        HereResponse { distance, path }
+                                    })
+                                })
+                                .ok_or(QueryError::NoRoute)
+                            }
+                            (Err(err), _) | (_, Err(err)) => Err(err),
+                        };
 
-                        tx_result.send(result).unwrap();
+                        if let Err(err) = result {
+                            metrics.failed(Endpoint::HereQuery, err);
+                        }
+                        tx_result.send(result).ok();
+                    }
+                    Request::TD((
+                        TDGeoQuery {
+                            from_lat,
+                            from_lng,
+                            to_lat,
+                            to_lng,
+                            departure,
+                        },
+                        tx_result,
+                    )) => {
+                        let (from, to) = report_time("match nodes", || (closest_node((from_lat, from_lng)), closest_node((to_lat, to_lng))));
+                        let departure = departure.unwrap_or_else(|| calendar.departure(unix_now()));
+
+                        let result = match (td_server.as_mut(), from, to) {
+                            (None, _, _) => Err(QueryError::NoProfiles),
+                            (Some(td_server), Ok(from), Ok(to)) => report_time("live td query", || {
+                                td_server.query(TDQuery { from, to, departure }).as_mut().map(|result| {
+                                    let distance = result.distance();
+                                    let path = result.path().iter().map(|&node| coords(node)).collect();
+                                    GeoResponse { distance, path }
+                                })
+                            })
+                            .ok_or(QueryError::NoRoute),
+                            (_, Err(err), _) | (_, _, Err(err)) => Err(err),
+                        };
+
+                        if let Err(err) = result {
+                            metrics.failed(Endpoint::TDQuery, err);
+                        }
+                        tx_result.send(result).ok();
                     }
                     Request::Customize(updates) => {
+                        for (here_link_id, is_from_ref, weight) in updates.into_iter() {
+                            let direction = if is_from_ref { LinkDirection::FromRef } else { LinkDirection::ToRef };
+                            if let Some(link_idx) = id_mapper.here_to_local_link_id(here_link_id, direction) {
+                                base_metric[link_idx as usize] = weight.0
+                            }
+                        }
+                        let metric = match &latest_traffic {
+                            Some(traffic) => traffic.apply_to_metric(&base_metric),
+                            None => base_metric.clone(),
+                        };
+
                         let server = server.clone();
-                        let mut travel_time = travel_time.clone();
-                        let id_mapper = &id_mapper;
                         let cch = &cch;
                         let first_out = &first_out;
                         let head = &head;
+                        let metrics = &metrics;
 
                         // asynchronous customization
                         scope.spawn(move || {
-                            for (here_link_id, is_from_ref, weight) in updates.into_iter() {
-                                if is_from_ref {
-                                    if let Some(link_idx) = id_mapper.here_to_local_link_id(here_link_id, LinkDirection::FromRef) {
-                                        travel_time[link_idx as usize] = weight.0
-                                    }
-                                } else if let Some(link_idx) = id_mapper.here_to_local_link_id(here_link_id, LinkDirection::ToRef) {
-                                    travel_time[link_idx as usize] = weight.0
-                                }
-                            }
-                            let customized = cch_customize(&cch, &FirstOutGraph::new(&first_out[..], &head[..], travel_time));
+                            let start = Instant::now();
+                            let customized = cch_customize(&cch, &FirstOutGraph::new(&first_out[..], &head[..], metric));
+                            metrics.customized(start.elapsed());
+                            server.lock().unwrap().update(customized);
+                        });
+                    }
+                    Request::Live(traffic) => {
+                        if let Some(td_server) = td_server.as_mut() {
+                            td_server.update_live(traffic.live_weights().to_vec(), calendar.departure(unix_now()) + LIVE_HORIZON);
+                        }
+
+                        let metric = traffic.apply_to_metric(&base_metric);
+                        latest_traffic = Some(traffic);
+                        let server = server.clone();
+                        let cch = &cch;
+                        let first_out = &first_out;
+                        let head = &head;
+                        let metrics = &metrics;
+
+                        scope.spawn(move || {
+                            let start = Instant::now();
+                            let customized = report_time("live customization", || {
+                                cch_customize(&cch, &FirstOutGraph::new(&first_out[..], &head[..], metric))
+                            });
+                            metrics.customized(start.elapsed());
                             server.lock().unwrap().update(customized);
                         });
                     }
@@ -340,8 +565,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     });
 
     rocket::ignite()
-        .mount("/", routes![index, files, query, here_query, customize])
+        .mount("/", routes![index, files, query, here_query, td_query, customize, metrics])
         .manage(Mutex::new(tx_query))
+        .manage(metrics)
         .launch();
 
     Ok(())
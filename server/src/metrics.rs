@@ -0,0 +1,201 @@
+// In-process metrics, rendered in the Prometheus text exposition format.
+// Everything is kept in atomics so it can be updated from the rocket workers and the engine thread without locking.
+
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
+
+use crate::error::QueryError;
+use rust_road_router::import::live_traffic::unix_now;
+
+// upper bounds of the latency buckets in seconds
+const LATENCY_BUCKETS: [f64; 12] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Query,
+    HereQuery,
+    TDQuery,
+}
+
+impl Endpoint {
+    const ALL: [Endpoint; 3] = [Endpoint::Query, Endpoint::HereQuery, Endpoint::TDQuery];
+
+    fn label(self) -> &'static str {
+        match self {
+            Endpoint::Query => "query",
+            Endpoint::HereQuery => "here_query",
+            Endpoint::TDQuery => "td_query",
+        }
+    }
+
+    fn idx(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    // not cumulative, cumulated when rendering, the last one is the +Inf bucket
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_us: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let bucket = LATENCY_BUCKETS.iter().position(|&bound| secs <= bound).unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket].fetch_add(1, Relaxed);
+        self.sum_us.fetch_add(duration.as_micros() as u64, Relaxed);
+        self.count.fetch_add(1, Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, endpoint: &str) {
+        let mut cumulative = 0;
+        for (bound, bucket) in LATENCY_BUCKETS
+            .iter()
+            .map(|bound| bound.to_string())
+            .chain(Some("+Inf".to_string()))
+            .zip(&self.buckets)
+        {
+            cumulative += bucket.load(Relaxed);
+            writeln!(out, "{}_bucket{{endpoint=\"{}\",le=\"{}\"}} {}", name, endpoint, bound, cumulative).unwrap();
+        }
+        writeln!(
+            out,
+            "{}_sum{{endpoint=\"{}\"}} {}",
+            name,
+            endpoint,
+            self.sum_us.load(Relaxed) as f64 / 1_000_000.0
+        )
+        .unwrap();
+        writeln!(out, "{}_count{{endpoint=\"{}\"}} {}", name, endpoint, self.count.load(Relaxed)).unwrap();
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    latency: [Histogram; 3],
+    snapping_failures: [AtomicU64; 3],
+    unreachable: [AtomicU64; 3],
+    queue_depth: AtomicUsize,
+    customizations: AtomicU64,
+    last_customization_duration_us: AtomicU64,
+    // unix timestamp in ms, 0 while no customization has finished yet
+    last_customization_at: AtomicU64,
+}
+
+impl Metrics {
+    pub fn observe_query(&self, endpoint: Endpoint, duration: Duration) {
+        self.latency[endpoint.idx()].observe(duration);
+    }
+
+    pub fn failed(&self, endpoint: Endpoint, err: QueryError) {
+        match err {
+            QueryError::UnknownLink(_) | QueryError::OutOfBounds => {
+                self.snapping_failures[endpoint.idx()].fetch_add(1, Relaxed);
+            }
+            QueryError::NoRoute => {
+                self.unreachable[endpoint.idx()].fetch_add(1, Relaxed);
+            }
+            QueryError::NoProfiles | QueryError::EngineBusy => (),
+        }
+    }
+
+    /// Call right before sending a request to the engine.
+    pub fn enqueued(&self) {
+        self.queue_depth.fetch_add(1, Relaxed);
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth.load(Relaxed)
+    }
+
+    /// Call when the engine takes a request from the channel.
+    pub fn dequeued(&self) {
+        self.queue_depth.fetch_sub(1, Relaxed);
+    }
+
+    pub fn customized(&self, duration: Duration) {
+        self.customizations.fetch_add(1, Relaxed);
+        self.last_customization_duration_us.store(duration.as_micros() as u64, Relaxed);
+        self.last_customization_at.store(unix_now(), Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(
+            out,
+            "# HELP routing_query_duration_seconds Total time to answer a query request, including waiting for the engine."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE routing_query_duration_seconds histogram").unwrap();
+        for &endpoint in &Endpoint::ALL {
+            self.latency[endpoint.idx()].render(&mut out, "routing_query_duration_seconds", endpoint.label());
+        }
+
+        render_counters(
+            &mut out,
+            "routing_snapping_failures_total",
+            "Query endpoints which could not be matched to the graph.",
+            &self.snapping_failures,
+        );
+        render_counters(
+            &mut out,
+            "routing_unreachable_total",
+            "Queries without a path between source and target.",
+            &self.unreachable,
+        );
+
+        writeln!(out, "# HELP routing_engine_queue_depth Requests waiting in the channel to the routing engine.").unwrap();
+        writeln!(out, "# TYPE routing_engine_queue_depth gauge").unwrap();
+        writeln!(out, "routing_engine_queue_depth {}", self.queue_depth.load(Relaxed)).unwrap();
+
+        writeln!(out, "# HELP routing_customizations_total Finished customizations.").unwrap();
+        writeln!(out, "# TYPE routing_customizations_total counter").unwrap();
+        writeln!(out, "routing_customizations_total {}", self.customizations.load(Relaxed)).unwrap();
+
+        let last_customization_at = self.last_customization_at.load(Relaxed);
+        if last_customization_at > 0 {
+            writeln!(
+                out,
+                "# HELP routing_last_customization_duration_seconds Running time of the latest customization."
+            )
+            .unwrap();
+            writeln!(out, "# TYPE routing_last_customization_duration_seconds gauge").unwrap();
+            writeln!(
+                out,
+                "routing_last_customization_duration_seconds {}",
+                self.last_customization_duration_us.load(Relaxed) as f64 / 1_000_000.0
+            )
+            .unwrap();
+
+            writeln!(
+                out,
+                "# HELP routing_seconds_since_last_customization Time since the latest customization finished."
+            )
+            .unwrap();
+            writeln!(out, "# TYPE routing_seconds_since_last_customization gauge").unwrap();
+            writeln!(
+                out,
+                "routing_seconds_since_last_customization {}",
+                unix_now().saturating_sub(last_customization_at) as f64 / 1000.0
+            )
+            .unwrap();
+        }
+
+        out
+    }
+}
+
+fn render_counters(out: &mut String, name: &str, help: &str, counters: &[AtomicU64; 3]) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} counter", name).unwrap();
+    for &endpoint in &Endpoint::ALL {
+        writeln!(out, "{}{{endpoint=\"{}\"}} {}", name, endpoint.label(), counters[endpoint.idx()].load(Relaxed)).unwrap();
+    }
+}